@@ -0,0 +1,106 @@
+//! Command-line arguments.
+
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use vinyl_engine::SampleFormat;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "vinyl-clean",
+    version,
+    about = "Removes clicks, crackle, hum and hiss from WAV transfers of records.",
+    after_help = "Exit status: 0 if every file was cleaned, 1 if the output of a file \
+                  clipped or held NaN samples, 2 if a file could not be read, processed \
+                  or written, or the arguments were invalid."
+)]
+/// The arguments of `vinyl-clean`.
+pub struct Args {
+    /// WAV files to clean, or directories to clean the `.wav` files in.
+    #[arg(required = true, value_name = "INPUT")]
+    pub inputs: Vec<PathBuf>,
+
+    /// Also clean the `.wav` files in subdirectories of input directories.
+    #[arg(short = 'R', long)]
+    pub recursive: bool,
+
+    /// Built-in preset to clean with, or one defined in `--presets`.
+    #[arg(short, long, value_name = "ID", conflicts_with_all = ["config", "auto"])]
+    pub preset: Option<String>,
+
+    /// TOML file with more presets for `--preset`.
+    #[arg(long, value_name = "FILE", requires = "preset")]
+    pub presets: Option<PathBuf>,
+
+    /// Configuration file to clean with, as TOML or, with a `.json`
+    /// extension, JSON. Settings it leaves out keep their defaults.
+    #[arg(short, long, value_name = "FILE", conflicts_with = "auto")]
+    pub config: Option<PathBuf>,
+
+    /// Analyze every file and clean it with the configuration derived for
+    /// it, instead of the standard preset.
+    #[arg(short, long)]
+    pub auto: bool,
+
+    /// Directory to write the cleaned files to [default: next to each
+    /// input].
+    #[arg(short, long, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Appended to the name of each input to name its cleaned file.
+    #[arg(long, value_name = "SUFFIX", default_value = "_clean")]
+    pub suffix: String,
+
+    /// Sample format of the cleaned files [default: that of each input].
+    #[arg(short, long, value_enum, value_name = "FORMAT")]
+    pub format: Option<OutputFormat>,
+
+    /// Replace cleaned files that already exist.
+    #[arg(long)]
+    pub overwrite: bool,
+
+    /// Write a JSON report of every file to this path.
+    #[arg(short, long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Clean every file and write the report, but no cleaned files. Files
+    /// are still cleaned in full to count clicks and find clipping, so a dry
+    /// run takes as long as a real one.
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+
+    /// Number of files to clean at once [default: one per CPU].
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: Option<u16>,
+
+    /// Only print errors.
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+/// The sample formats `--format` accepts.
+pub enum OutputFormat {
+    /// Signed 16-bit integer PCM.
+    Int16,
+    /// Signed 24-bit integer PCM.
+    Int24,
+    /// Signed 32-bit integer PCM.
+    Int32,
+    /// 32-bit IEEE float.
+    Float32,
+    /// 64-bit IEEE float.
+    Float64,
+}
+
+impl From<OutputFormat> for SampleFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Int16 => SampleFormat::Int16,
+            OutputFormat::Int24 => SampleFormat::Int24,
+            OutputFormat::Int32 => SampleFormat::Int32,
+            OutputFormat::Float32 => SampleFormat::Float32,
+            OutputFormat::Float64 => SampleFormat::Float64,
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! Finding the files to clean and cleaning them one by one.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde::Serialize;
+use vinyl_engine::{
+    analyze, read_wav, write_wav, BaselineConfig, Pipeline, RunReport, SampleFormat,
+};
+
+/// The configuration every file is cleaned with.
+pub enum ConfigSource {
+    /// One configuration for all files, from a preset or a file.
+    Fixed(Box<BaselineConfig>),
+    /// The configuration [`analyze`] derives for each file.
+    Auto,
+}
+
+/// One file to clean and where its cleaned version goes.
+pub struct Job {
+    /// The file to clean.
+    pub input: PathBuf,
+    /// Where the cleaned file is written.
+    pub output: PathBuf,
+}
+
+/// How the cleaned files are written.
+pub struct OutputOptions {
+    /// Directory for all cleaned files, instead of next to each input.
+    pub dir: Option<PathBuf>,
+    /// Appended to each input's file stem.
+    pub suffix: String,
+    /// Sample format, instead of that of each input.
+    pub format: Option<SampleFormat>,
+    /// Whether existing files may be replaced.
+    pub overwrite: bool,
+    /// Whether to skip writing altogether.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+/// The outcome of one file.
+pub enum Status {
+    /// The file was cleaned and written.
+    Cleaned,
+    /// The file was cleaned but, as asked for by `--dry-run`, not written.
+    Analyzed,
+    /// The output clipped or held NaN samples, so it was not written.
+    Flagged,
+    /// The file could not be read, processed or written.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// What happened to one file, as written to the JSON report.
+pub struct FileReport {
+    /// The file that was cleaned.
+    pub input: PathBuf,
+    /// Where the cleaned file was written, if it was.
+    pub output: Option<PathBuf>,
+    /// The outcome.
+    pub status: Status,
+    /// Why the file was flagged or failed.
+    pub error: Option<String>,
+    /// The summary of the analysis, with `--auto`.
+    pub analysis: Option<String>,
+    /// Number of clicks repaired, counting a click once across channels.
+    pub clicks: usize,
+    /// One report per channel, in channel order; empty if the file failed.
+    pub channels: Vec<RunReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// The JSON report of a whole batch.
+pub struct BatchReport {
+    /// Whether cleaned files were written.
+    pub dry_run: bool,
+    /// One entry per input file, in the order the files were found.
+    pub files: Vec<FileReport>,
+}
+
+/// Why the files to clean could not be listed.
+#[derive(Debug)]
+pub enum JobError {
+    /// An input or directory could not be read.
+    Io(PathBuf, io::Error),
+    /// The inputs hold no WAV files.
+    NoFiles,
+    /// Two inputs would be cleaned into the same file.
+    SameOutput(PathBuf),
+    /// A cleaned file would replace its input.
+    OverwritesInput(PathBuf),
+    /// A cleaned file already exists and `--overwrite` was not given.
+    OutputExists(PathBuf),
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Io(path, error) => write!(f, "{}: {error}", path.display()),
+            JobError::NoFiles => write!(f, "no WAV files found"),
+            JobError::SameOutput(path) => {
+                write!(f, "several inputs would be written to {}", path.display())
+            }
+            JobError::OverwritesInput(path) => {
+                write!(
+                    f,
+                    "{} would be overwritten by its cleaned file",
+                    path.display()
+                )
+            }
+            JobError::OutputExists(path) => write!(
+                f,
+                "{} already exists; pass --overwrite to replace it",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+/// Lists the WAV files among `inputs` and in the directories among them,
+/// and checks where they will be written.
+///
+/// Files given directly are cleaned whatever their extension; in
+/// directories, only files with a `.wav` extension (in any case) are, and
+/// not those that another file found would be cleaned into. Each file is
+/// listed once, directory contents in name order.
+///
+/// # Errors
+/// Returns a [`JobError`] if an input cannot be read or no files are found.
+/// Unless `options.dry_run` is set, also if the cleaned files would collide
+/// with each other, with an input or, unless `options.overwrite` is set,
+/// with existing files.
+pub fn find_jobs(
+    inputs: &[PathBuf],
+    recursive: bool,
+    options: &OutputOptions,
+) -> Result<Vec<Job>, JobError> {
+    let mut files = Vec::new();
+    let mut given = HashSet::new();
+    for input in inputs {
+        let metadata = fs::metadata(input).map_err(|error| JobError::Io(input.clone(), error))?;
+        if metadata.is_dir() {
+            collect_wav_files(input, recursive, &mut files)?;
+        } else {
+            given.insert(resolve(input));
+            files.push(input.clone());
+        }
+    }
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(resolve(file)));
+    // The cleaned files of an earlier run sit next to their inputs; a
+    // directory scan leaves them to be replaced rather than cleaned again.
+    let sources: HashMap<PathBuf, PathBuf> = files
+        .iter()
+        .map(|file| (resolve(&output_path(file, options)), resolve(file)))
+        .collect();
+    files.retain(|file| {
+        let file = resolve(file);
+        given.contains(&file) || sources.get(&file).is_none_or(|source| *source == file)
+    });
+    if files.is_empty() {
+        return Err(JobError::NoFiles);
+    }
+
+    let jobs: Vec<Job> = files
+        .into_iter()
+        .map(|input| Job {
+            output: output_path(&input, options),
+            input,
+        })
+        .collect();
+    // Compare the files themselves, not how their paths are spelled.
+    let inputs: HashSet<PathBuf> = jobs.iter().map(|job| resolve(&job.input)).collect();
+    let mut outputs = HashSet::new();
+    for job in jobs.iter().filter(|_| !options.dry_run) {
+        let output = resolve(&job.output);
+        if inputs.contains(&output) {
+            return Err(JobError::OverwritesInput(job.output.clone()));
+        }
+        if !outputs.insert(output) {
+            return Err(JobError::SameOutput(job.output.clone()));
+        }
+        if !options.overwrite && job.output.exists() {
+            return Err(JobError::OutputExists(job.output.clone()));
+        }
+    }
+    Ok(jobs)
+}
+
+fn collect_wav_files(
+    dir: &Path,
+    recursive: bool,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), JobError> {
+    let read_error = |error| JobError::Io(dir.to_path_buf(), error);
+    let mut entries = fs::read_dir(dir)
+        .map_err(read_error)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()
+        .map_err(read_error)?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            if recursive {
+                collect_wav_files(&path, recursive, files)?;
+            }
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("wav"))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The canonical form of `path`, or, for a file that does not exist yet, of
+/// its directory joined with its name; `path` itself if neither exists.
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match (fs::canonicalize(dir), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `input` with `options.suffix` added to its stem, in `options.dir` if
+/// given.
+fn output_path(input: &Path, options: &OutputOptions) -> PathBuf {
+    let mut name = input.file_stem().map(OsString::from).unwrap_or_default();
+    name.push(&options.suffix);
+    name.push(".wav");
+    match &options.dir {
+        Some(dir) => dir.join(name),
+        None => input.with_file_name(name),
+    }
+}
+
+/// Cleans the file of `job` and writes the result, unless the output fails
+/// validation or `options.dry_run` is set.
+pub fn run_job(job: &Job, source: &ConfigSource, options: &OutputOptions) -> FileReport {
+    let mut report = FileReport {
+        input: job.input.clone(),
+        output: None,
+        status: Status::Failed,
+        error: None,
+        analysis: None,
+        clicks: 0,
+        channels: Vec::new(),
+    };
+    let audio = match read_wav(&job.input) {
+        Ok(audio) => audio,
+        Err(error) => {
+            report.error = Some(format!("cannot read the file: {error}"));
+            return report;
+        }
+    };
+
+    let mut config = match source {
+        ConfigSource::Fixed(config) => (**config).clone(),
+        ConfigSource::Auto => {
+            let profile = analyze(&audio.downmix(), audio.sample_rate);
+            report.analysis = Some(profile.summary);
+            profile.config
+        }
+    };
+    config.sample_rate = audio.sample_rate;
+
+    let started = Instant::now();
+    let output = match Pipeline::new(&config).try_run_multichannel(&audio.channels) {
+        Ok(output) => output,
+        Err(error) => {
+            report.error = Some(error.to_string());
+            return report;
+        }
+    };
+    let elapsed = started.elapsed();
+    report.clicks = output.combined_impulses.len();
+    report.channels = output
+        .channels
+        .iter()
+        .map(|channel| RunReport::new(&config, audio.frames(), channel, elapsed))
+        .collect();
+
+    let problems: Vec<String> = output
+        .channels
+        .iter()
+        .enumerate()
+        .flat_map(|(channel, output)| {
+            let validation = &output.validation;
+            let clipped = (validation.clipped_samples > 0).then(|| {
+                format!(
+                    "channel {channel}: {} samples clipped",
+                    validation.clipped_samples
+                )
+            });
+            let nan = validation
+                .has_nan
+                .then(|| format!("channel {channel}: NaN samples"));
+            clipped.into_iter().chain(nan)
+        })
+        .collect();
+    if !problems.is_empty() {
+        report.status = Status::Flagged;
+        report.error = Some(problems.join("; "));
+        return report;
+    }
+    if options.dry_run {
+        report.status = Status::Analyzed;
+        return report;
+    }
+
+    let repaired: Vec<&[f32]> = output
+        .channels
+        .iter()
+        .map(|channel| channel.repaired.as_slice())
+        .collect();
+    let format = options.format.unwrap_or(audio.format);
+    let written = match job.output.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir),
+        _ => Ok(()),
+    }
+    .and_then(|()| write_wav(&job.output, &repaired, audio.sample_rate, format));
+    match written {
+        Ok(()) => {
+            report.status = Status::Cleaned;
+            report.output = Some(job.output.clone());
+        }
+        Err(error) => {
+            report.error = Some(format!("cannot write {}: {error}", job.output.display()));
+        }
+    }
+    report
+}
@@ -0,0 +1,149 @@
+//! `vinyl-clean`: cleans WAV transfers of records from the command line.
+//!
+//! Cleans every input file with the same configuration, or with one derived
+//! per file with `--auto`, several files at once, and optionally writes a
+//! JSON report of the whole batch. Run `vinyl-clean --help` for the options.
+
+mod args;
+mod batch;
+
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use clap::Parser;
+use rayon::prelude::*;
+use vinyl_engine::{BaselineConfig, PresetRegistry};
+
+use crate::args::Args;
+use crate::batch::{find_jobs, run_job, BatchReport, ConfigSource, OutputOptions, Status};
+
+/// Exit status when an output clipped or held NaN samples.
+const EXIT_FLAGGED: u8 = 1;
+/// Exit status when a file or the arguments could not be used.
+const EXIT_FAILED: u8 = 2;
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("vinyl-clean: {message}");
+            ExitCode::from(EXIT_FAILED)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<ExitCode, String> {
+    let source = config_source(args)?;
+    let options = OutputOptions {
+        dir: args.output_dir.clone(),
+        suffix: args.suffix.clone(),
+        format: args.format.map(Into::into),
+        overwrite: args.overwrite,
+        dry_run: args.dry_run,
+    };
+    let jobs =
+        find_jobs(&args.inputs, args.recursive, &options).map_err(|error| error.to_string())?;
+
+    let mut pool = rayon::ThreadPoolBuilder::new();
+    if let Some(jobs) = args.jobs {
+        pool = pool.num_threads(usize::from(jobs));
+    }
+    let pool = pool.build().map_err(|error| error.to_string())?;
+    let files: Vec<_> = pool.install(|| {
+        jobs.par_iter()
+            .map(|job| {
+                let report = run_job(job, &source, &options);
+                let input = job.input.display();
+                match (&report.status, &report.error) {
+                    (Status::Cleaned, _) if !args.quiet => eprintln!(
+                        "{input}: {} clicks repaired, written to {}",
+                        report.clicks,
+                        job.output.display()
+                    ),
+                    (Status::Analyzed, _) if !args.quiet => {
+                        eprintln!("{input}: {} clicks found", report.clicks)
+                    }
+                    (Status::Flagged | Status::Failed, Some(error)) => {
+                        eprintln!("{input}: {error}")
+                    }
+                    _ => {}
+                }
+                report
+            })
+            .collect()
+    });
+
+    let failed = files.iter().any(|file| file.status == Status::Failed);
+    let flagged = files.iter().any(|file| file.status == Status::Flagged);
+    if let Some(path) = &args.report {
+        let report = BatchReport {
+            dry_run: args.dry_run,
+            files,
+        };
+        let json = serde_json::to_string_pretty(&report).expect("a report always serializes");
+        fs::write(path, json + "\n")
+            .map_err(|error| format!("cannot write the report to {}: {error}", path.display()))?;
+    }
+
+    Ok(if failed {
+        ExitCode::from(EXIT_FAILED)
+    } else if flagged {
+        ExitCode::from(EXIT_FLAGGED)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// The configuration chosen by `--config`, `--preset` or `--auto`; the
+/// standard preset without any of them.
+fn config_source(args: &Args) -> Result<ConfigSource, String> {
+    if args.auto {
+        return Ok(ConfigSource::Auto);
+    }
+    let config = match (&args.config, &args.preset) {
+        (Some(path), _) => read_config(path)?,
+        (None, Some(id)) => {
+            let mut registry = PresetRegistry::default();
+            if let Some(path) = &args.presets {
+                registry
+                    .load_toml_file(path)
+                    .map_err(|error| format!("{}: {error}", path.display()))?;
+            }
+            let preset = registry.get(id).ok_or_else(|| {
+                let known: Vec<&str> = registry.iter().map(|preset| preset.id.as_str()).collect();
+                format!(
+                    "unknown preset \"{id}\"; known presets: {}",
+                    known.join(", ")
+                )
+            })?;
+            preset.config.clone()
+        }
+        (None, None) => BaselineConfig::standard(),
+    };
+    Ok(ConfigSource::Fixed(Box::new(config)))
+}
+
+/// Reads a configuration file, as JSON if its extension is `.json` and as
+/// TOML otherwise, and checks its settings.
+fn read_config(path: &Path) -> Result<BaselineConfig, String> {
+    let error = |message: String| format!("{}: {message}", path.display());
+    let source = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let config: BaselineConfig = if is_json {
+        serde_json::from_str(&source).map_err(|e| error(e.to_string()))?
+    } else {
+        toml::from_str(&source).map_err(|e| error(e.to_string()))?
+    };
+    // The sample rate comes from each file; check the rest.
+    BaselineConfig {
+        sample_rate: BaselineConfig::default().sample_rate,
+        ..config.clone()
+    }
+    .validate()
+    .map_err(|e| error(e.to_string()))?;
+    Ok(config)
+}
@@ -0,0 +1,250 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use vinyl_engine::{read_wav, write_wav, SampleFormat};
+
+/// A scratch directory for one test, removed when dropped.
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("vinyl-clean-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A second of a quiet stereo tone with a click in each channel.
+fn write_record(path: &Path) {
+    let left: Vec<f32> = (0..44_100).map(|i| 0.1 * (i as f32 * 0.02).sin()).collect();
+    let mut right = left.clone();
+    let mut left = left;
+    left[30_000] += 0.8;
+    right[30_001] += 0.7;
+    write_wav(path, &[&left, &right], 44_100, SampleFormat::Int16).unwrap();
+}
+
+fn vinyl_clean(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_vinyl-clean"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn arg(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+#[test]
+fn cleans_directories_in_parallel_and_writes_a_report() {
+    let scratch = Scratch::new("batch");
+    let records = scratch.path("records");
+    fs::create_dir_all(records.join("side b")).unwrap();
+    write_record(&records.join("a.wav"));
+    write_record(&records.join("B.WAV"));
+    write_record(&records.join("side b").join("c.wav"));
+    fs::write(records.join("notes.txt"), "not audio").unwrap();
+
+    let out = scratch.path("out");
+    let report = scratch.path("report.json");
+    let output = vinyl_clean(&[
+        arg(&records),
+        "--recursive",
+        "--output-dir",
+        arg(&out),
+        "--format",
+        "float32",
+        "--report",
+        arg(&report),
+        "--jobs",
+        "2",
+        "--preset",
+        "warm-vinyl",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+
+    for name in ["a_clean.wav", "B_clean.wav", "c_clean.wav"] {
+        let cleaned = read_wav(out.join(name)).unwrap();
+        assert_eq!(cleaned.format, SampleFormat::Float32);
+        assert_eq!((cleaned.channels.len(), cleaned.frames()), (2, 44_100));
+        assert!(cleaned.channels[0][30_000].abs() < 0.3);
+    }
+
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(report["dry_run"], false);
+    let files = report["files"].as_array().unwrap();
+    assert_eq!(files.len(), 3);
+    assert!(files[0]["input"].as_str().unwrap().ends_with("B.WAV"));
+    for file in files {
+        assert_eq!(file["status"], "cleaned");
+        assert_eq!(file["clicks"], 1);
+        assert_eq!(file["channels"].as_array().unwrap().len(), 2);
+        assert_eq!(file["channels"][0]["config"]["max_click_duration_ms"], 12.0);
+    }
+}
+
+#[test]
+fn dry_runs_analyze_and_report_without_writing() {
+    let scratch = Scratch::new("dry-run");
+    let input = scratch.path("side_a.wav");
+    write_record(&input);
+    let report = scratch.path("report.json");
+
+    let output = vinyl_clean(&[arg(&input), "--dry-run", "--auto", "-r", arg(&report)]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(!scratch.path("side_a_clean.wav").exists());
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(report["dry_run"], true);
+    let file = &report["files"][0];
+    assert_eq!(file["status"], "analyzed");
+    assert!(file["output"].is_null());
+    assert!(!file["analysis"].as_str().unwrap().is_empty());
+}
+
+#[test]
+fn clipping_outputs_are_flagged_with_exit_status_one() {
+    let scratch = Scratch::new("clipping");
+    let input = scratch.path("hot.wav");
+    let tone: Vec<f32> = (0..44_100).map(|i| 0.5 * (i as f32 * 0.02).sin()).collect();
+    write_wav(&input, &[&tone], 44_100, SampleFormat::Int16).unwrap();
+    // Normalizing a tone above full scale clips every peak.
+    let config = scratch.path("hot.toml");
+    fs::write(&config, "target_peak = 1.5\n").unwrap();
+
+    let output = vinyl_clean(&[arg(&input), "--config", arg(&config)]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("samples clipped"), "{stderr}");
+    assert!(!scratch.path("hot_clean.wav").exists());
+}
+
+#[test]
+fn bad_inputs_fail_with_exit_status_two() {
+    let scratch = Scratch::new("errors");
+    let input = scratch.path("side_a.wav");
+    write_record(&input);
+    let failure = |args: &[&str], expected: &str| {
+        let output = vinyl_clean(args);
+        assert_eq!(output.status.code(), Some(2), "{output:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(expected), "{stderr}");
+    };
+
+    failure(
+        &[arg(&input), "--preset", "nope"],
+        "unknown preset \"nope\"",
+    );
+    failure(&[arg(&scratch.path("missing.wav"))], "missing.wav");
+    failure(
+        &[arg(&input), "--auto", "--preset", "standard"],
+        "cannot be used with",
+    );
+
+    let config = scratch.path("config.json");
+    fs::write(&config, r#"{ "target_peak": -1.0 }"#).unwrap();
+    failure(&[arg(&input), "-c", arg(&config)], "target_peak");
+
+    // A cleaned file is never replaced unless asked for.
+    assert!(vinyl_clean(&[arg(&input), "-q"]).status.success());
+    failure(&[arg(&input)], "--overwrite");
+    assert!(vinyl_clean(&[arg(&input), "--overwrite"]).status.success());
+
+    // A file that is not a WAV file fails, but the others are still cleaned.
+    let broken = scratch.path("broken.wav");
+    fs::write(&broken, "not audio").unwrap();
+    let report = scratch.path("report.json");
+    failure(
+        &[arg(&broken), arg(&input), "--overwrite", "-r", arg(&report)],
+        "cannot read the file",
+    );
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(report["files"][0]["status"], "failed");
+    assert_eq!(report["files"][1]["status"], "cleaned");
+}
+
+#[test]
+fn inputs_are_never_overwritten_under_another_name() {
+    let scratch = Scratch::new("aliases");
+    let input = scratch.path("side_a.wav");
+    write_record(&input);
+    let original = fs::read(&input).unwrap();
+
+    // `.` and the absolute path of the input name the same directory.
+    let output = Command::new(env!("CARGO_BIN_EXE_vinyl-clean"))
+        .args(["--suffix", "", "-o", ".", "--overwrite", arg(&input)])
+        .current_dir(&scratch.0)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("would be overwritten"), "{stderr}");
+
+    let detour = scratch.path("sub").join("..");
+    fs::create_dir_all(scratch.path("sub")).unwrap();
+    let output = vinyl_clean(&["--suffix", "", "-o", arg(&detour), arg(&input)]);
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    assert_eq!(fs::read(&input).unwrap(), original);
+}
+
+#[test]
+fn a_second_run_leaves_the_cleaned_files_of_the_first_alone() {
+    let scratch = Scratch::new("second-run");
+    let records = scratch.path("records");
+    fs::create_dir_all(&records).unwrap();
+    write_record(&records.join("a.wav"));
+    let report = scratch.path("report.json");
+    let inputs = |report: &serde_json::Value| -> Vec<String> {
+        report["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|file| file["input"].as_str().unwrap().to_owned())
+            .collect()
+    };
+    let read_report = || -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap()
+    };
+
+    assert!(vinyl_clean(&[arg(&records), "-q"]).status.success());
+    assert!(records.join("a_clean.wav").exists());
+
+    // `a_clean.wav` is the output of `a.wav`, not another input.
+    let output = vinyl_clean(&[arg(&records)]);
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("a_clean.wav already exists"), "{stderr}");
+
+    let output = vinyl_clean(&[arg(&records), "--overwrite", "-q", "-r", arg(&report)]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(inputs(&read_report()).len(), 1);
+    assert!(!records.join("a_clean_clean.wav").exists());
+
+    // A dry run writes nothing, so existing files never stop it.
+    let output = vinyl_clean(&[arg(&records), "--dry-run", "-q", "-r", arg(&report)]);
+    assert!(output.status.success(), "{output:?}");
+    let report = read_report();
+    assert!(inputs(&report)[0].ends_with("a.wav"), "{report}");
+    let output = vinyl_clean(&[
+        arg(&records.join("a.wav")),
+        "--suffix",
+        "",
+        "--dry-run",
+        "-q",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+}
@@ -0,0 +1,185 @@
+//! The commands the frontend calls through `invoke`.
+//!
+//! Each command locks the [`AppState`] and delegates to the open
+//! [`Session`]. All of them are `async`, so the main thread never waits for
+//! the engine or for a command holding the lock. `clean` only holds the lock
+//! to prepare the run and to keep its result, so the views stay responsive
+//! while it runs.
+
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use tauri::{AppHandle, State, Window};
+use vinyl_engine::{
+    AutoProfile, CancellationToken, Progress, SampleFormat, SpectrogramTile, SpectrogramView,
+    WaveformPeaks, WaveformSignal,
+};
+
+use crate::error::{Error, Result};
+use crate::session::{CleanResult, CleanSettings, ClickMarker, FileInfo, ProgressEvent, Session};
+
+/// Name of the event `clean` reports its progress with.
+pub const CLEAN_PROGRESS_EVENT: &str = "clean-progress";
+
+#[derive(Default)]
+/// State shared by all commands: the open session, if any, and the token
+/// that cancels the running `clean`, if one is running.
+pub struct AppState {
+    session: Mutex<Option<Session>>,
+    cancel: Mutex<Option<CancellationToken>>,
+}
+
+/// Marks a `clean` as running until dropped.
+struct Running<'a> {
+    state: &'a AppState,
+    token: CancellationToken,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        *self.state.cancel_slot() = None;
+    }
+}
+
+impl AppState {
+    fn lock(&self) -> MutexGuard<'_, Option<Session>> {
+        // Keep serving the session even if a command panicked while holding it.
+        self.session
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn cancel_slot(&self) -> MutexGuard<'_, Option<CancellationToken>> {
+        self.cancel
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Marks a new `clean` as running, with a token that `cancel_clean` will
+    /// cancel.
+    ///
+    /// # Errors
+    /// Returns [`Error::Busy`] if a `clean` is running already.
+    fn start_clean(&self) -> Result<Running<'_>> {
+        let mut slot = self.cancel_slot();
+        if slot.is_some() {
+            return Err(Error::Busy);
+        }
+        let token = CancellationToken::new();
+        *slot = Some(token.clone());
+        Ok(Running { state: self, token })
+    }
+
+    fn with_session<T>(&self, f: impl FnOnce(&mut Session) -> Result<T>) -> Result<T> {
+        self.lock().as_mut().map_or(Err(Error::NoFile), f)
+    }
+}
+
+/// Opens a WAV file, replacing the open session. Its waveform overview is
+/// cached in the app's cache directory.
+///
+/// Fails while a `clean` runs, whose result would otherwise land on the new
+/// file.
+#[tauri::command(async)]
+pub fn open_file(path: PathBuf, app: AppHandle, state: State<'_, AppState>) -> Result<FileInfo> {
+    // Refuse before reading the file, which can take a while.
+    if state.cancel_slot().is_some() {
+        return Err(Error::Busy);
+    }
+    let cache_dir = app.path_resolver().app_cache_dir();
+    let session = Session::open(path, cache_dir.as_deref())?;
+    let info = session.info();
+    // A clean may have started while the file was read.
+    let running = state.cancel_slot();
+    if running.is_some() {
+        return Err(Error::Busy);
+    }
+    *state.lock() = Some(session);
+    Ok(info)
+}
+
+/// Measures the open file and derives a configuration for it.
+#[tauri::command(async)]
+pub fn analyze(state: State<'_, AppState>) -> Result<AutoProfile> {
+    state.with_session(|session| Ok(session.analyze()))
+}
+
+/// Cleans the open file, emitting [`CLEAN_PROGRESS_EVENT`] to `window` as it
+/// goes.
+///
+/// Fails with [`Error::Busy`] while another `clean` runs.
+#[tauri::command(async)]
+pub fn clean(
+    settings: Option<CleanSettings>,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<CleanResult> {
+    let running = state.start_clean()?;
+    let job = state.with_session(|session| session.prepare_clean(settings.unwrap_or_default()))?;
+    let mut forward = |progress: &Progress| {
+        // The run goes on even if the window is gone.
+        let _ = window.emit(CLEAN_PROGRESS_EVENT, ProgressEvent::from(progress));
+    };
+    let run = job.run(&mut forward, &running.token)?;
+    state.with_session(|session| Ok(session.finish_clean(run)))
+}
+
+/// Stops the running `clean`, which then fails with a cancellation error and
+/// keeps the previous result. Does nothing if no `clean` is running.
+#[tauri::command]
+pub fn cancel_clean(state: State<'_, AppState>) {
+    if let Some(token) = &*state.cancel_slot() {
+        token.cancel();
+    }
+}
+
+/// Reduces a time range of the original or cleaned waveform to `buckets`
+/// columns per channel.
+#[tauri::command(async)]
+pub fn get_waveform_peaks(
+    signal: WaveformSignal,
+    start_seconds: f64,
+    end_seconds: f64,
+    buckets: usize,
+    state: State<'_, AppState>,
+) -> Result<WaveformPeaks> {
+    state
+        .with_session(|session| session.waveform_peaks(signal, start_seconds, end_seconds, buckets))
+}
+
+/// Lists the repaired clicks in a time range.
+#[tauri::command(async)]
+pub fn get_click_markers(
+    start_seconds: f64,
+    end_seconds: f64,
+    state: State<'_, AppState>,
+) -> Result<Vec<ClickMarker>> {
+    state.with_session(|session| session.click_markers(start_seconds, end_seconds))
+}
+
+/// Renders a time range of one channel as a spectrogram tile of levels, from
+/// the highest frequency row down.
+#[tauri::command(async)]
+pub fn get_spectrogram_tile(
+    view: SpectrogramView,
+    channel: usize,
+    start_seconds: f64,
+    end_seconds: f64,
+    width: usize,
+    height: usize,
+    state: State<'_, AppState>,
+) -> Result<SpectrogramTile> {
+    state.with_session(|session| {
+        session.spectrogram_tile(view, channel, start_seconds, end_seconds, width, height)
+    })
+}
+
+/// Writes the cleaned file to `path`.
+#[tauri::command(async)]
+pub fn export(
+    path: PathBuf,
+    format: Option<SampleFormat>,
+    state: State<'_, AppState>,
+) -> Result<FileInfo> {
+    state.with_session(|session| session.export(path, format))
+}
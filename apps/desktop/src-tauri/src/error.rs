@@ -0,0 +1,66 @@
+//! Errors returned to the frontend by the commands in [`crate::commands`].
+
+use std::fmt;
+use std::io;
+
+use serde::{Serialize, Serializer};
+
+#[derive(Debug)]
+/// Why a command failed.
+///
+/// Serialized as its message, which the frontend shows as is.
+pub enum Error {
+    /// The command needs an open file, but none was opened.
+    NoFile,
+    /// The command needs the result of `clean`, but the open file was not
+    /// cleaned yet.
+    NotCleaned,
+    /// A `clean` is running, so the file can neither be cleaned again nor
+    /// replaced until it finishes or is cancelled.
+    Busy,
+    /// `clean` was asked for a preset that does not exist.
+    UnknownPreset(String),
+    /// A command was asked for a channel the open file does not have.
+    UnknownChannel(usize),
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// The engine refused the signal or the configuration.
+    Engine(vinyl_engine::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoFile => write!(f, "no file is open"),
+            Error::NotCleaned => write!(f, "the file has not been cleaned yet"),
+            Error::Busy => write!(f, "the file is being cleaned"),
+            Error::UnknownPreset(id) => write!(f, "unknown preset \"{id}\""),
+            Error::UnknownChannel(channel) => write!(f, "the file has no channel {channel}"),
+            Error::Io(error) => write!(f, "{error}"),
+            Error::Engine(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<vinyl_engine::Error> for Error {
+    fn from(error: vinyl_engine::Error) -> Self {
+        Error::Engine(error)
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Result type of the commands.
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,7 +1,24 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod commands;
+mod error;
+mod session;
+
+use commands::AppState;
+
 fn main() {
     tauri::Builder::default()
+        .manage(AppState::default())
+        .invoke_handler(tauri::generate_handler![
+            commands::open_file,
+            commands::analyze,
+            commands::clean,
+            commands::cancel_clean,
+            commands::get_waveform_peaks,
+            commands::get_click_markers,
+            commands::get_spectrogram_tile,
+            commands::export,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
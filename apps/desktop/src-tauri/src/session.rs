@@ -0,0 +1,575 @@
+//! The file the user is working on and what the engine made of it.
+//!
+//! Nothing here depends on Tauri; [`crate::commands`] exposes a [`Session`]
+//! to the frontend.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use vinyl_engine::{
+    analyze, read_wav, write_wav, AutoProfile, BaselineConfig, CancellationToken,
+    MultichannelOutput, Pipeline, PresetRegistry, Progress, ProgressSink, RunReport, SampleFormat,
+    Spectrogram, SpectrogramConfig, SpectrogramTile, SpectrogramView, Stage, WavAudio,
+    WaveformCache, WaveformPeaks, WaveformSignal,
+};
+
+use crate::error::{Error, Result};
+
+/// An open file, its analysis and the result of the last clean.
+pub struct Session {
+    path: PathBuf,
+    audio: Arc<WavAudio>,
+    profile: Option<AutoProfile>,
+    cleaned: Option<Cleaned>,
+    waveform: WaveformCache,
+    spectrogram: Spectrogram,
+}
+
+struct Cleaned {
+    output: MultichannelOutput,
+}
+
+/// A `clean` of a [`Session`], prepared with [`Session::prepare_clean`] so
+/// that it can run without access to the session.
+pub struct CleanJob {
+    audio: Arc<WavAudio>,
+    config: BaselineConfig,
+}
+
+/// The output of a [`CleanJob`], for [`Session::finish_clean`].
+pub struct CleanRun {
+    result: CleanResult,
+    output: MultichannelOutput,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// What `open_file` tells the frontend about the file.
+pub struct FileInfo {
+    /// Path the file was read from.
+    pub path: PathBuf,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    /// Number of channels.
+    pub channels: usize,
+    /// Number of samples per channel.
+    pub frames: usize,
+    /// Length of the recording in seconds.
+    pub duration_seconds: f64,
+    /// Sample encoding of the file.
+    pub format: SampleFormat,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+/// Which configuration `clean` runs with.
+///
+/// An explicit `config` wins over a `preset`; with neither, the configuration
+/// derived by `analyze` is used, or the standard preset if the file was not
+/// analyzed. The sample rate is always taken from the file.
+pub struct CleanSettings {
+    /// Id of a built-in preset, e.g. `"warm-vinyl"`.
+    pub preset: Option<String>,
+    /// A full configuration, e.g. one the user tuned by hand.
+    pub config: Option<BaselineConfig>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+/// What `clean` tells the frontend about the run.
+pub struct CleanResult {
+    /// Number of clicks repaired, counting a click once across channels.
+    pub clicks: usize,
+    /// One report per channel, in channel order.
+    pub reports: Vec<RunReport>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+/// Progress of a `clean`, sent to the frontend as a `clean-progress` event.
+pub struct ProgressEvent {
+    /// The stage being run.
+    pub stage: Stage,
+    /// Name of the stage to show to users.
+    pub label: &'static str,
+    /// Fraction of the whole run done, in `[0.0, 1.0]`.
+    pub fraction: f32,
+    /// Time since the run started.
+    pub elapsed_seconds: f64,
+    /// Estimated time until the run finishes, if known yet.
+    pub eta_seconds: Option<f64>,
+}
+
+impl From<&Progress> for ProgressEvent {
+    fn from(progress: &Progress) -> Self {
+        Self {
+            stage: progress.stage,
+            label: progress.stage.label(),
+            fraction: progress.fraction,
+            elapsed_seconds: progress.elapsed.as_secs_f64(),
+            eta_seconds: progress.eta.map(|eta| eta.as_secs_f64()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+/// A repaired click, positioned in seconds for the timeline.
+pub struct ClickMarker {
+    /// Start of the repaired span.
+    pub start_seconds: f64,
+    /// End of the repaired span.
+    pub end_seconds: f64,
+    /// Position of the click's peak.
+    pub peak_seconds: f64,
+    /// Signed amplitude of the peak.
+    pub amplitude: f32,
+    /// Detector confidence in `[0.0, 1.0]`.
+    pub confidence: f32,
+}
+
+impl CleanJob {
+    /// Cleans every channel, reporting to `progress` as it goes.
+    ///
+    /// # Errors
+    /// Returns [`Error::Engine`] if the engine rejects the configuration or
+    /// the samples, or the run was cancelled through `cancel`.
+    pub fn run(
+        self,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancellationToken,
+    ) -> Result<CleanRun> {
+        let started = Instant::now();
+        let output = Pipeline::new(&self.config).run_multichannel_with_progress(
+            &self.audio.channels,
+            progress,
+            cancel,
+        )?;
+        let elapsed = started.elapsed();
+
+        let frames = self.audio.frames();
+        let result = CleanResult {
+            clicks: output.combined_impulses.len(),
+            reports: output
+                .channels
+                .iter()
+                .map(|channel| RunReport::new(&self.config, frames, channel, elapsed))
+                .collect(),
+        };
+        Ok(CleanRun { result, output })
+    }
+}
+
+impl Session {
+    /// Opens the WAV file at `path`.
+    ///
+    /// The waveform overview of the file is kept in `cache_dir`, if given, so
+    /// reopening the file does not have to summarize it again.
+    pub fn open(path: impl AsRef<Path>, cache_dir: Option<&Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let audio = read_wav(&path)?;
+        let spectrogram = Spectrogram::new(SpectrogramConfig::default(), audio.sample_rate)?;
+        let cache_file = cache_dir.and_then(|dir| waveform_cache_file(dir, &path));
+        let waveform = cache_file
+            .as_deref()
+            .and_then(|file| WaveformCache::load(file).ok())
+            .filter(|cache| {
+                let original = cache.original();
+                original.sample_rate() == audio.sample_rate
+                    && original.frames() == audio.frames()
+                    && original.channel_count() == audio.channels.len()
+                    && cache.repaired().is_none()
+            })
+            .unwrap_or_else(|| {
+                let cache = WaveformCache::new(&audio.channels, audio.sample_rate);
+                if let Some(file) = &cache_file {
+                    // The cache only saves time; the session works without it.
+                    let _ = fs::create_dir_all(file.parent().unwrap_or(file))
+                        .and_then(|()| cache.save(file));
+                }
+                cache
+            });
+        Ok(Self {
+            path,
+            audio: Arc::new(audio),
+            profile: None,
+            cleaned: None,
+            waveform,
+            spectrogram,
+        })
+    }
+
+    /// Describes the open file.
+    pub fn info(&self) -> FileInfo {
+        FileInfo {
+            path: self.path.clone(),
+            sample_rate: self.audio.sample_rate,
+            channels: self.audio.channels.len(),
+            frames: self.audio.frames(),
+            duration_seconds: self.audio.frames() as f64 / f64::from(self.audio.sample_rate),
+            format: self.audio.format,
+        }
+    }
+
+    /// Analyzes the mono downmix of the file and remembers the derived
+    /// configuration for `clean`.
+    pub fn analyze(&mut self) -> AutoProfile {
+        let profile = analyze(&self.audio.downmix(), self.audio.sample_rate);
+        self.profile = Some(profile.clone());
+        profile
+    }
+
+    /// Chooses the configuration for a clean with `settings` and takes a
+    /// snapshot of the file to run it on.
+    ///
+    /// Cleaning takes three steps so that the run itself needs no access to
+    /// the session: this one, [`CleanJob::run`] and
+    /// [`Session::finish_clean`]. A cancelled or failed run is never
+    /// finished, so the result of the previous clean is kept.
+    ///
+    /// # Errors
+    /// Returns [`Error::UnknownPreset`] for a preset id that does not exist.
+    pub fn prepare_clean(&self, settings: CleanSettings) -> Result<CleanJob> {
+        let mut config = match (settings.config, settings.preset) {
+            (Some(config), _) => config,
+            (None, Some(id)) => PresetRegistry::default()
+                .get(&id)
+                .map(|preset| preset.config.clone())
+                .ok_or(Error::UnknownPreset(id))?,
+            (None, None) => self
+                .profile
+                .as_ref()
+                .map_or_else(BaselineConfig::standard, |profile| profile.config.clone()),
+        };
+        config.sample_rate = self.audio.sample_rate;
+        Ok(CleanJob {
+            audio: Arc::clone(&self.audio),
+            config,
+        })
+    }
+
+    /// Keeps the output of a run prepared by this session's
+    /// [`Session::prepare_clean`] as the result of the clean.
+    pub fn finish_clean(&mut self, run: CleanRun) -> CleanResult {
+        let repaired: Vec<&[f32]> = run
+            .output
+            .channels
+            .iter()
+            .map(|channel| channel.repaired.as_slice())
+            .collect();
+        self.waveform
+            .set_cleaned(&repaired, &run.output.combined_impulses);
+        self.cleaned = Some(Cleaned { output: run.output });
+        run.result
+    }
+
+    /// Reduces `start_seconds..end_seconds` of `signal` to `buckets` columns
+    /// per channel.
+    ///
+    /// The range is clamped to the file; fewer columns are returned if the
+    /// range holds fewer than `buckets` frames.
+    ///
+    /// # Errors
+    /// Returns [`Error::NotCleaned`] for [`WaveformSignal::Repaired`] before
+    /// the file was cleaned.
+    pub fn waveform_peaks(
+        &self,
+        signal: WaveformSignal,
+        start_seconds: f64,
+        end_seconds: f64,
+        buckets: usize,
+    ) -> Result<WaveformPeaks> {
+        let range = self
+            .waveform
+            .original()
+            .frame_range(start_seconds, end_seconds);
+        self.waveform
+            .peaks(signal, range, buckets)
+            .ok_or(Error::NotCleaned)
+    }
+
+    /// The clicks repaired by the last clean that overlap
+    /// `start_seconds..end_seconds`, ordered by position.
+    ///
+    /// # Errors
+    /// Returns [`Error::NotCleaned`] before the file was cleaned.
+    pub fn click_markers(&self, start_seconds: f64, end_seconds: f64) -> Result<Vec<ClickMarker>> {
+        self.cleaned()?;
+        let range = self
+            .waveform
+            .original()
+            .frame_range(start_seconds, end_seconds);
+        let seconds = |frame: usize| frame as f64 / f64::from(self.audio.sample_rate);
+        Ok(self
+            .waveform
+            .clicks(range)
+            .iter()
+            .map(|event| ClickMarker {
+                start_seconds: seconds(event.start),
+                end_seconds: seconds(event.end),
+                peak_seconds: seconds(event.peak_index),
+                amplitude: event.amplitude,
+                confidence: event.confidence,
+            })
+            .collect())
+    }
+
+    /// Renders `start_seconds..end_seconds` of `view` of one channel as a
+    /// spectrogram tile of `width` by `height` levels.
+    ///
+    /// Before the file was cleaned, [`SpectrogramView::Normalized`] shows the
+    /// file as it was opened.
+    ///
+    /// # Errors
+    /// Returns [`Error::UnknownChannel`] for a channel the file does not
+    /// have, and [`Error::NotCleaned`] for the other views before the file
+    /// was cleaned.
+    pub fn spectrogram_tile(
+        &self,
+        view: SpectrogramView,
+        channel: usize,
+        start_seconds: f64,
+        end_seconds: f64,
+        width: usize,
+        height: usize,
+    ) -> Result<SpectrogramTile> {
+        let original = self
+            .audio
+            .channels
+            .get(channel)
+            .ok_or(Error::UnknownChannel(channel))?;
+        let range = self
+            .waveform
+            .original()
+            .frame_range(start_seconds, end_seconds);
+        match (&self.cleaned, view) {
+            (None, SpectrogramView::Normalized) => {
+                Ok(self.spectrogram.tile(original, range, width, height))
+            }
+            (None, _) => Err(Error::NotCleaned),
+            (Some(cleaned), view) => Ok(self.spectrogram.view_tile(
+                &cleaned.output.channels[channel],
+                view,
+                range,
+                width,
+                height,
+            )),
+        }
+    }
+
+    /// Writes the cleaned channels to `path` as a WAV file, in `format` or
+    /// in the format of the opened file.
+    ///
+    /// # Errors
+    /// Returns [`Error::NotCleaned`] before the file was cleaned, and
+    /// [`Error::Io`] if the file cannot be written.
+    pub fn export(&self, path: impl AsRef<Path>, format: Option<SampleFormat>) -> Result<FileInfo> {
+        let path = path.as_ref();
+        let repaired: Vec<&[f32]> = self
+            .cleaned()?
+            .output
+            .channels
+            .iter()
+            .map(|channel| channel.repaired.as_slice())
+            .collect();
+        let format = format.unwrap_or(self.audio.format);
+        write_wav(path, &repaired, self.audio.sample_rate, format)?;
+        Ok(FileInfo {
+            path: path.to_path_buf(),
+            format,
+            ..self.info()
+        })
+    }
+
+    fn cleaned(&self) -> Result<&Cleaned> {
+        self.cleaned.as_ref().ok_or(Error::NotCleaned)
+    }
+}
+
+/// Where the waveform overview of the file at `path` is cached in
+/// `cache_dir`: named after the file's path, size and modification time, so
+/// an edited file gets a fresh overview. `None` if the file's metadata
+/// cannot be read.
+fn waveform_cache_file(cache_dir: &Path, path: &Path) -> Option<PathBuf> {
+    let metadata = fs::metadata(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    fs::canonicalize(path).ok()?.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    metadata.modified().ok()?.hash(&mut hasher);
+    Some(
+        cache_dir
+            .join("waveforms")
+            .join(format!("{:016x}.vwpk", hasher.finish())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use vinyl_engine::{
+        read_wav, write_wav, CancellationToken, Progress, SampleFormat, SpectrogramView,
+        WaveformSignal,
+    };
+
+    use super::{CleanSettings, Session};
+    use crate::error::Error;
+
+    /// A scratch directory for one test, removed when dropped.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "vinyl-cleanup-desktop-{name}-{}",
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Two seconds of a quiet stereo tone with two clicks.
+    fn write_record(path: &Path) {
+        let mut left: Vec<f32> = (0..88_200)
+            .map(|i| 0.05 * (i as f32 * 0.03).sin())
+            .collect();
+        left[30_000] += 0.7;
+        left[60_000] -= 0.6;
+        let right = left.clone();
+        write_wav(path, &[&left, &right], 44_100, SampleFormat::Int24).unwrap();
+    }
+
+    fn clean(session: &mut Session, preset: Option<&str>) -> super::Result<super::CleanResult> {
+        let settings = CleanSettings {
+            preset: preset.map(String::from),
+            config: None,
+        };
+        let run = session
+            .prepare_clean(settings)?
+            .run(&mut |_: &Progress| {}, &CancellationToken::new())?;
+        Ok(session.finish_clean(run))
+    }
+
+    #[test]
+    fn open_describes_the_file_and_caches_its_waveform() {
+        let scratch = Scratch::new("open");
+        let input = scratch.path("side_a.wav");
+        write_record(&input);
+        let cache = scratch.path("cache");
+
+        let session = Session::open(&input, Some(&cache)).unwrap();
+        let info = session.info();
+        assert_eq!((info.sample_rate, info.channels), (44_100, 2));
+        assert_eq!(info.frames, 88_200);
+        assert_eq!(info.duration_seconds, 2.0);
+        assert_eq!(info.format, SampleFormat::Int24);
+        assert_eq!(fs::read_dir(cache.join("waveforms")).unwrap().count(), 1);
+
+        let reopened = Session::open(&input, Some(&cache)).unwrap();
+        let peaks = |session: &Session| {
+            session
+                .waveform_peaks(WaveformSignal::Original, 0.0, 2.0, 100)
+                .unwrap()
+        };
+        assert_eq!(peaks(&reopened), peaks(&session));
+
+        assert!(matches!(
+            Session::open(scratch.path("missing.wav"), None),
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn clean_runs_presets_and_rejects_unknown_ones() {
+        let scratch = Scratch::new("clean");
+        let input = scratch.path("side_a.wav");
+        write_record(&input);
+        let mut session = Session::open(&input, None).unwrap();
+
+        let error = clean(&mut session, Some("nope")).err().unwrap();
+        assert!(matches!(&error, Error::UnknownPreset(id) if id == "nope"));
+        assert_eq!(error.to_string(), "unknown preset \"nope\"");
+        assert!(matches!(
+            session.click_markers(0.0, 2.0),
+            Err(Error::NotCleaned)
+        ));
+
+        let result = clean(&mut session, Some("warm-vinyl")).unwrap();
+        assert_eq!(result.clicks, 2);
+        assert_eq!(result.reports.len(), 2);
+        let markers = session.click_markers(0.0, 1.0).unwrap();
+        assert_eq!(markers.len(), 1);
+        assert!((markers[0].peak_seconds - 30_000.0 / 44_100.0).abs() < 1e-9);
+
+        // A failed clean keeps the previous result.
+        assert!(clean(&mut session, Some("nope")).is_err());
+        assert_eq!(session.click_markers(0.0, 2.0).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn export_needs_a_clean() {
+        let scratch = Scratch::new("export");
+        let input = scratch.path("side_a.wav");
+        write_record(&input);
+        let output = scratch.path("side_a_clean.wav");
+        let mut session = Session::open(&input, None).unwrap();
+
+        assert!(matches!(
+            session.export(&output, None),
+            Err(Error::NotCleaned)
+        ));
+        assert!(!output.exists());
+
+        clean(&mut session, None).unwrap();
+        let info = session
+            .export(&output, Some(SampleFormat::Float32))
+            .unwrap();
+        assert_eq!(info.path, output);
+        assert_eq!(info.format, SampleFormat::Float32);
+        let written = read_wav(&output).unwrap();
+        assert_eq!((written.channels.len(), written.frames()), (2, 88_200));
+        assert!(written.channels[0][30_000].abs() < 0.3);
+    }
+
+    #[test]
+    fn spectrogram_tiles_check_the_channel_and_the_view() {
+        let scratch = Scratch::new("spectrogram");
+        let input = scratch.path("side_a.wav");
+        write_record(&input);
+        let mut session = Session::open(&input, None).unwrap();
+
+        let tile = session
+            .spectrogram_tile(SpectrogramView::Normalized, 1, 0.0, 2.0, 16, 8)
+            .unwrap();
+        assert_eq!((tile.width, tile.height, tile.levels.len()), (16, 8, 128));
+        assert!(matches!(
+            session.spectrogram_tile(SpectrogramView::Difference, 0, 0.0, 2.0, 16, 8),
+            Err(Error::NotCleaned)
+        ));
+
+        clean(&mut session, None).unwrap();
+        let error = session
+            .spectrogram_tile(SpectrogramView::Difference, 2, 0.0, 2.0, 16, 8)
+            .err()
+            .unwrap();
+        assert!(matches!(error, Error::UnknownChannel(2)));
+        assert_eq!(error.to_string(), "the file has no channel 2");
+        assert!(session
+            .spectrogram_tile(SpectrogramView::Difference, 0, 0.0, 2.0, 16, 8)
+            .is_ok());
+    }
+}
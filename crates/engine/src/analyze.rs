@@ -0,0 +1,431 @@
+//! Automatic configuration from a first look at the recording.
+//!
+//! [`analyze`] measures four properties of the input and derives a
+//! [`BaselineConfig`] from them, starting from the presets in
+//! [`crate::preset`]:
+//!
+//! - the **click density** picks the aggressiveness: a worn surface gets the
+//!   looser gates of [`Aggressiveness::High`], a clean one the stricter gates
+//!   of [`Aggressiveness::Low`];
+//! - the **crest factor** adapts the impulse threshold to the dynamics: on a
+//!   heavily compressed master a click cannot rise far above the programme
+//!   level, while a very dynamic recording has sharp attacks to protect;
+//! - the **noise floor** decides whether hiss reduction is worth running;
+//! - the **spectral tilt** scales the step gate: steps between neighbouring
+//!   samples are small in dull recordings, so clicks stand out more, and
+//!   large in bright ones.
+//!
+//! The measurements and the reasoning behind every adjustment are returned
+//! together with the configuration, so a front end can show what was
+//! chosen and why.
+
+use crate::dehiss::DehissConfig;
+use crate::detect::{BaselineDetector, DetectionContext, ImpulseDetector};
+use crate::fft::{hann, Fft};
+use crate::pipeline::{normalize_to_peak, BaselineConfig, SignalLevels};
+use crate::preset::Aggressiveness;
+
+/// Click density, in clicks per second, above which the surface counts as
+/// worn.
+const WORN_CLICK_DENSITY: f32 = 2.0;
+/// Click density below which the surface counts as clean.
+const CLEAN_CLICK_DENSITY: f32 = 0.2;
+/// Crest factor, in dB, below which the material counts as compressed.
+const COMPRESSED_CREST_DB: f32 = 12.0;
+/// Crest factor above which the material counts as very dynamic.
+const DYNAMIC_CREST_DB: f32 = 24.0;
+/// Noise floor, in dB relative to the peak, above which hiss reduction is
+/// enabled.
+const NOISY_FLOOR_DB: f32 = -50.0;
+/// Noise floor below which the surface counts as quiet.
+const QUIET_FLOOR_DB: f32 = -70.0;
+/// Spectral tilt, in dB per octave, above which the material counts as
+/// bright.
+const BRIGHT_TILT_DB: f32 = -2.0;
+/// Spectral tilt below which the material counts as dull.
+const DULL_TILT_DB: f32 = -9.0;
+
+/// Impulse threshold multiplier for compressed material.
+const COMPRESSED_THRESHOLD_MULTIPLIER: f32 = 4.0;
+/// Amount added to the local contrast gate for very dynamic material.
+const DYNAMIC_CONTRAST_INCREASE: f32 = 1.0;
+/// Factor applied to the step gate for dull material.
+const DULL_DIFF_SCALE: f32 = 0.6;
+/// Factor applied to the step gate for bright material.
+const BRIGHT_DIFF_SCALE: f32 = 1.5;
+
+/// Share of the frames, quietest first, whose level is taken as the noise
+/// floor.
+const NOISE_PERCENTILE: f32 = 0.1;
+/// Centre frequencies of the octave bands the spectral tilt is fitted over.
+const TILT_BANDS_HZ: [f32; 7] = [125.0, 250.0, 500.0, 1_000.0, 2_000.0, 4_000.0, 8_000.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What [`analyze`] measured on a signal.
+///
+/// Levels are relative to the peak of the signal, so they do not depend on
+/// the transfer level.
+pub struct SignalAnalysis {
+    /// Peak to RMS ratio, in dB.
+    pub crest_factor_db: f32,
+    /// RMS level of the broadband noise in the quietest tenth of the
+    /// signal, in dB relative to the peak; `-inf` for a silent signal.
+    pub noise_floor_db: f32,
+    /// Clicks per second that the [`Aggressiveness::Standard`] detector
+    /// finds.
+    pub click_density: f32,
+    /// Slope of the average power spectrum between 125 Hz and 8 kHz, in dB
+    /// per octave: `0` for white noise, `-3` for pink noise, steeper for
+    /// dull recordings.
+    pub spectral_tilt_db_per_octave: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A configuration derived from a signal, with the reasons for it.
+pub struct AutoProfile {
+    /// The measurements the configuration was derived from.
+    pub analysis: SignalAnalysis,
+    /// The derived configuration, at the analysed sample rate.
+    pub config: BaselineConfig,
+    /// Short description of the recording, e.g.
+    /// `"worn surface, noisy, dull"`, for a label such as
+    /// "Auto profile: worn surface, noisy, dull".
+    pub summary: String,
+    /// One sentence per measurement, saying what it showed and what was
+    /// changed because of it.
+    pub reasoning: Vec<String>,
+}
+
+/// Measures `input` and derives a configuration for cleaning it.
+///
+/// # Parameters
+/// - `input`: the complete signal, at any level.
+/// - `sample_rate`: sample rate of `input`, in Hz.
+///
+/// # Returns
+/// The measurements, the derived configuration and the reasoning behind it.
+/// A silent or empty signal gets the [`Aggressiveness::Standard`] preset.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::analyze::analyze;
+///
+/// let mut signal: Vec<f32> = (0..44_100)
+///     .map(|i| 0.1 * (i as f32 * 0.05).sin())
+///     .collect();
+/// for i in (1_000..44_100).step_by(4_000) {
+///     signal[i] += 0.6;
+/// }
+/// let profile = analyze(&signal, 44_100);
+/// assert!(profile.analysis.click_density > 5.0);
+/// assert_eq!(profile.config.sample_rate, 44_100);
+/// println!("Auto profile: {}", profile.summary);
+/// ```
+pub fn analyze(input: &[f32], sample_rate: u32) -> AutoProfile {
+    let analysis = SignalAnalysis::measure(input, sample_rate);
+    let (config, summary, reasoning) = analysis.derive(sample_rate);
+    AutoProfile {
+        analysis,
+        config,
+        summary,
+        reasoning,
+    }
+}
+
+impl SignalAnalysis {
+    /// Measures `input`, recorded at `sample_rate`.
+    pub fn measure(input: &[f32], sample_rate: u32) -> Self {
+        let mean = if input.is_empty() {
+            0.0
+        } else {
+            input.iter().map(|&s| f64::from(s)).sum::<f64>() / input.len() as f64
+        };
+        let centred: Vec<f32> = input
+            .iter()
+            .map(|&s| (f64::from(s) - mean) as f32)
+            .collect();
+        let peak = centred.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        if peak == 0.0 || !peak.is_finite() {
+            return Self {
+                crest_factor_db: 0.0,
+                noise_floor_db: f32::NEG_INFINITY,
+                click_density: 0.0,
+                spectral_tilt_db_per_octave: 0.0,
+            };
+        }
+
+        let spectra = FrameSpectra::new(sample_rate);
+        Self {
+            crest_factor_db: decibels(peak / rms(&centred)),
+            noise_floor_db: decibels(noise_floor(&centred, &spectra) / peak),
+            click_density: click_density(&centred, sample_rate),
+            spectral_tilt_db_per_octave: spectral_tilt(&centred, sample_rate, &spectra),
+        }
+    }
+
+    /// Derives a configuration from the measurements, with its summary and
+    /// reasoning.
+    fn derive(&self, sample_rate: u32) -> (BaselineConfig, String, Vec<String>) {
+        let mut labels = Vec::new();
+        let mut reasoning = Vec::new();
+        if self.noise_floor_db == f32::NEG_INFINITY {
+            let config = BaselineConfig {
+                sample_rate,
+                ..Aggressiveness::Standard.config()
+            };
+            reasoning.push("The signal is silent: standard settings are used.".to_owned());
+            return (config, "silent".to_owned(), reasoning);
+        }
+
+        let aggressiveness = if self.click_density > WORN_CLICK_DENSITY {
+            labels.push("worn surface");
+            reasoning.push(format!(
+                "{:.1} clicks per second: a worn surface, so quiet clicks and heavier crackle are removed too.",
+                self.click_density
+            ));
+            Aggressiveness::High
+        } else if self.click_density < CLEAN_CLICK_DENSITY {
+            labels.push("clean surface");
+            reasoning.push(format!(
+                "{:.1} clicks per second: a clean surface, so only clear, loud clicks are repaired.",
+                self.click_density
+            ));
+            Aggressiveness::Low
+        } else {
+            reasoning.push(format!(
+                "{:.1} clicks per second: a typical surface, cleaned with the standard gates.",
+                self.click_density
+            ));
+            Aggressiveness::Standard
+        };
+        let mut config = BaselineConfig {
+            sample_rate,
+            ..aggressiveness.config()
+        };
+
+        if self.crest_factor_db < COMPRESSED_CREST_DB {
+            labels.push("compressed");
+            config.impulse_threshold_multiplier = config
+                .impulse_threshold_multiplier
+                .min(COMPRESSED_THRESHOLD_MULTIPLIER);
+            reasoning.push(format!(
+                "Crest factor {:.1} dB: compressed material, so the impulse threshold sits closer to the programme level.",
+                self.crest_factor_db
+            ));
+        } else if self.crest_factor_db > DYNAMIC_CREST_DB {
+            labels.push("dynamic");
+            config.local_contrast_multiplier += DYNAMIC_CONTRAST_INCREASE;
+            reasoning.push(format!(
+                "Crest factor {:.1} dB: very dynamic material, so the contrast gate is raised to protect sharp attacks.",
+                self.crest_factor_db
+            ));
+        } else {
+            reasoning.push(format!(
+                "Crest factor {:.1} dB: typical dynamics, no change.",
+                self.crest_factor_db
+            ));
+        }
+
+        if self.noise_floor_db > NOISY_FLOOR_DB {
+            labels.push("noisy");
+            config.dehiss = Some(DehissConfig::default());
+            reasoning.push(format!(
+                "Noise floor {:.1} dB below the peak: audible hiss, so hiss reduction is enabled.",
+                -self.noise_floor_db
+            ));
+        } else if self.noise_floor_db < QUIET_FLOOR_DB {
+            labels.push("quiet");
+            reasoning.push(format!(
+                "Noise floor {:.1} dB below the peak: a quiet surface, no hiss reduction needed.",
+                -self.noise_floor_db
+            ));
+        } else {
+            reasoning.push(format!(
+                "Noise floor {:.1} dB below the peak: low hiss, no hiss reduction needed.",
+                -self.noise_floor_db
+            ));
+        }
+
+        if self.spectral_tilt_db_per_octave < DULL_TILT_DB {
+            labels.push("dull");
+            config.diff_threshold *= DULL_DIFF_SCALE;
+            reasoning.push(format!(
+                "Spectral tilt {:.1} dB per octave: dull material, where clicks stand out, so the step gate is lowered.",
+                self.spectral_tilt_db_per_octave
+            ));
+        } else if self.spectral_tilt_db_per_octave > BRIGHT_TILT_DB {
+            labels.push("bright");
+            config.diff_threshold *= BRIGHT_DIFF_SCALE;
+            reasoning.push(format!(
+                "Spectral tilt {:.1} dB per octave: bright material with large sample-to-sample steps, so the step gate is raised.",
+                self.spectral_tilt_db_per_octave
+            ));
+        } else {
+            reasoning.push(format!(
+                "Spectral tilt {:.1} dB per octave: a typical balance, no change.",
+                self.spectral_tilt_db_per_octave
+            ));
+        }
+
+        let summary = if labels.is_empty() {
+            "typical record".to_owned()
+        } else {
+            labels.join(", ")
+        };
+        (config, summary, reasoning)
+    }
+}
+
+fn decibels(ratio: f32) -> f32 {
+    20.0 * ratio.log10()
+}
+
+fn rms(signal: &[f32]) -> f32 {
+    let energy: f64 = signal.iter().map(|&s| f64::from(s) * f64::from(s)).sum();
+    (energy / signal.len() as f64).sqrt() as f32
+}
+
+/// Power spectra of consecutive Hann-windowed frames of about 50 ms.
+struct FrameSpectra {
+    fft: Fft,
+    window: Vec<f64>,
+}
+
+impl FrameSpectra {
+    fn new(sample_rate: u32) -> Self {
+        let length = (sample_rate as usize / 20).next_power_of_two().max(64);
+        Self {
+            fft: Fft::new(length),
+            window: hann(length),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.fft.len()
+    }
+
+    /// Power in each of the `len() / 2 + 1` bins of `frame`.
+    fn power(&self, frame: &[f32]) -> Vec<f64> {
+        let mut re: Vec<f64> = self
+            .window
+            .iter()
+            .enumerate()
+            .map(|(n, weight)| frame.get(n).map_or(0.0, |&s| f64::from(s)) * weight)
+            .collect();
+        let mut im = vec![0.0; re.len()];
+        self.fft.transform(&mut re, &mut im, false);
+        (0..=re.len() / 2)
+            .map(|bin| re[bin] * re[bin] + im[bin] * im[bin])
+            .collect()
+    }
+
+    /// The complete frames of `signal`, or the whole of it if it is shorter
+    /// than a frame.
+    fn frames<'a>(&self, signal: &'a [f32]) -> std::slice::Chunks<'a, f32> {
+        let complete = signal.len() - signal.len() % self.len();
+        let usable = if complete == 0 {
+            signal.len()
+        } else {
+            complete
+        };
+        signal[..usable].chunks(self.len())
+    }
+
+    /// Average power spectrum of the frames of `signal` for which `keep`
+    /// returns `true`, given the frame's energy.
+    fn average(&self, signal: &[f32], keep: impl Fn(f64) -> bool) -> Vec<f64> {
+        let mut total = vec![0.0; self.len() / 2 + 1];
+        let mut frames = 0;
+        for frame in self.frames(signal) {
+            if !keep(energy(frame)) {
+                continue;
+            }
+            for (sum, power) in total.iter_mut().zip(self.power(frame)) {
+                *sum += power;
+            }
+            frames += 1;
+        }
+        let scale = 1.0 / frames.max(1) as f64;
+        total.iter().map(|sum| sum * scale).collect()
+    }
+}
+
+fn energy(frame: &[f32]) -> f64 {
+    frame.iter().map(|&s| f64::from(s) * f64::from(s)).sum()
+}
+
+/// RMS level of the broadband noise in the quietest frames.
+///
+/// Music concentrates in a few bins of the spectrum, while noise fills all
+/// of them, so the median bin of the average spectrum of the quietest frames
+/// measures the noise even when the music never pauses. Frames of digital
+/// silence are ignored.
+fn noise_floor(signal: &[f32], spectra: &FrameSpectra) -> f32 {
+    let mut energies: Vec<f64> = spectra
+        .frames(signal)
+        .map(energy)
+        .filter(|&energy| energy > 0.0)
+        .collect();
+    energies.sort_by(f64::total_cmp);
+    let index = ((energies.len() as f32 * NOISE_PERCENTILE) as usize).min(energies.len() - 1);
+    let limit = energies[index];
+    let mut power = spectra.average(signal, |energy| energy > 0.0 && energy <= limit);
+    let middle = power.len() / 2;
+    let median = *power.select_nth_unstable_by(middle, f64::total_cmp).1;
+    // Each bin of white noise with variance v holds v times the energy of
+    // the window.
+    let window_energy: f64 = spectra.window.iter().map(|w| w * w).sum();
+    (median / window_energy).sqrt() as f32
+}
+
+/// Clicks per second found by the standard detector.
+fn click_density(signal: &[f32], sample_rate: u32) -> f32 {
+    let config = BaselineConfig {
+        sample_rate,
+        ..Aggressiveness::Standard.config()
+    };
+    let levels = SignalLevels::scan(signal);
+    let context = DetectionContext {
+        levels: &levels,
+        gain: levels.gain(config.target_peak),
+        offset: 0,
+    };
+    let normalized = normalize_to_peak(signal, levels.peak(), config.target_peak);
+    let detection = BaselineDetector::new(&config).detect(&normalized, &context);
+    detection.events.len() as f32 * sample_rate as f32 / signal.len() as f32
+}
+
+/// Least-squares slope of the average spectrum, fitted to the mean power
+/// density of octave bands: white noise has no tilt, pink noise -3 dB per
+/// octave.
+fn spectral_tilt(signal: &[f32], sample_rate: u32, spectra: &FrameSpectra) -> f32 {
+    let power = spectra.average(signal, |_| true);
+    let bin_hz = sample_rate as f32 / spectra.len() as f32;
+    let points: Vec<(f64, f64)> = TILT_BANDS_HZ
+        .iter()
+        .enumerate()
+        .filter(|(_, &centre)| centre * std::f32::consts::SQRT_2 < sample_rate as f32 / 2.0)
+        .filter_map(|(octave, &centre)| {
+            let low = (centre / std::f32::consts::SQRT_2 / bin_hz).ceil() as usize;
+            let high = (centre * std::f32::consts::SQRT_2 / bin_hz).ceil() as usize;
+            let band = &power[low..high.min(power.len())];
+            let density = band.iter().sum::<f64>() / band.len() as f64;
+            (density > 0.0).then(|| (octave as f64, 10.0 * density.log10()))
+        })
+        .collect();
+    if points.len() < 3 {
+        return 0.0;
+    }
+
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    (covariance / variance) as f32
+}
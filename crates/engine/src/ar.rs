@@ -0,0 +1,246 @@
+//! Autoregressive (linear prediction) modelling shared by repair and
+//! detection.
+//!
+//! All arithmetic is done in `f64`: the normal equations involved are poorly
+//! conditioned for strongly tonal material, and single precision loses most of
+//! the benefit of the model.
+
+use std::ops::Range;
+
+/// Fits an AR model of the given `order` to `segments` by least squares (the
+/// covariance method).
+///
+/// Forward predictions are only formed within each segment, so the segments
+/// may be disjoint pieces of a signal (e.g. the context on either side of a
+/// gap). Unlike the autocorrelation method, no implicit zero-padding takes
+/// place at segment edges, which keeps the model's resonances sharp enough to
+/// carry tonal content across gaps of a hundred samples and more.
+///
+/// Returns the prediction-error filter `a` with `a[0] == 1.0`, such that
+/// `e[n] = sum(a[k] * x[n - k])` is the prediction residual, or `None` if the
+/// segments are too short or the equations are singular.
+pub(crate) fn fit_ar(segments: &[&[f32]], order: usize) -> Option<Vec<f64>> {
+    let equations: usize = segments
+        .iter()
+        .map(|segment| segment.len().saturating_sub(order))
+        .sum();
+    if order == 0 || equations < order {
+        return None;
+    }
+
+    // Normal equations R c = -r for the predictor coefficients c = a[1..].
+    let mut covariance = vec![0.0_f64; order * order];
+    let mut cross = vec![0.0_f64; order];
+    for segment in segments {
+        for n in order..segment.len() {
+            let target = f64::from(segment[n]);
+            for j in 0..order {
+                let lagged = f64::from(segment[n - 1 - j]);
+                cross[j] += lagged * target;
+                for k in 0..=j {
+                    covariance[j * order + k] += lagged * f64::from(segment[n - 1 - k]);
+                }
+            }
+        }
+    }
+    for j in 0..order {
+        for k in 0..j {
+            covariance[k * order + j] = covariance[j * order + k];
+        }
+        // A touch of diagonal loading keeps the system solvable on
+        // near-periodic or near-silent input.
+        covariance[j * order + j] = covariance[j * order + j] * (1.0 + 1e-9) + 1e-12;
+    }
+
+    let coefficients = solve_symmetric(&mut covariance, &mut cross, order)?;
+    let mut filter = Vec::with_capacity(order + 1);
+    filter.push(1.0);
+    filter.extend(coefficients.iter().map(|c| -c));
+    Some(filter)
+}
+
+/// Solves the symmetric positive-definite system `matrix * x = rhs` in place
+/// with a Cholesky factorization. `matrix` is `size * size`, row-major; only
+/// its lower triangle is read.
+pub(crate) fn solve_symmetric(
+    matrix: &mut [f64],
+    rhs: &mut [f64],
+    size: usize,
+) -> Option<Vec<f64>> {
+    for row in 0..size {
+        for col in 0..=row {
+            let mut sum = matrix[row * size + col];
+            for k in 0..col {
+                sum -= matrix[row * size + k] * matrix[col * size + k];
+            }
+            if row == col {
+                if sum.is_nan() || sum <= 0.0 {
+                    return None;
+                }
+                matrix[row * size + row] = sum.sqrt();
+            } else {
+                matrix[row * size + col] = sum / matrix[col * size + col];
+            }
+        }
+    }
+
+    for row in 0..size {
+        let mut sum = rhs[row];
+        for k in 0..row {
+            sum -= matrix[row * size + k] * rhs[k];
+        }
+        rhs[row] = sum / matrix[row * size + row];
+    }
+    for row in (0..size).rev() {
+        let mut sum = rhs[row];
+        for k in row + 1..size {
+            sum -= matrix[k * size + row] * rhs[k];
+        }
+        rhs[row] = sum / matrix[row * size + row];
+    }
+
+    rhs.iter()
+        .all(|value| value.is_finite())
+        .then(|| rhs.to_vec())
+}
+
+/// Least-squares AR interpolation (LSAR) of the samples `start..end` of
+/// `signal`.
+///
+/// Given the prediction-error filter `filter` (from [`fit_ar`]), the missing
+/// samples are chosen to minimize the total energy of the prediction residual
+/// over every prediction that involves them. This leads to a symmetric,
+/// positive-definite, banded system of `end - start` equations which is
+/// solved with a banded Cholesky factorization.
+///
+/// The samples `start - order..start` and `end..end + order` must be present
+/// in `signal`; their values are treated as known. Returns `None` if that
+/// context is missing or the system cannot be solved.
+pub(crate) fn lsar_interpolate(
+    signal: &[f32],
+    start: usize,
+    end: usize,
+    filter: &[f64],
+) -> Option<Vec<f32>> {
+    let order = filter.len() - 1;
+    let gap = end - start;
+    if gap == 0 || start < order || end + order > signal.len() {
+        return None;
+    }
+
+    // Normal equations M x = b. Row `n` of the residual involves unknown `j`
+    // (absolute index `start + j`) with weight `filter[n - start - j]`.
+    // M is Toeplitz inside the gap with bandwidth `order`.
+    let band = order;
+    let mut matrix = vec![0.0_f64; gap * (band + 1)];
+    let at = |row: usize, diagonal: usize| row * (band + 1) + diagonal;
+    for row in 0..gap {
+        for diagonal in 0..=band.min(row) {
+            // Entry (row, row - diagonal).
+            let value: f64 = (0..=order - diagonal)
+                .map(|k| filter[k] * filter[k + diagonal])
+                .sum();
+            matrix[at(row, diagonal)] = value;
+        }
+    }
+
+    let mut rhs = vec![0.0_f64; gap];
+    for residual_row in start..end + order {
+        // Contribution of the known samples to this residual.
+        let known: f64 = (0..=order)
+            .filter_map(|k| {
+                let index = residual_row - k;
+                (index < start || index >= end).then(|| filter[k] * f64::from(signal[index]))
+            })
+            .sum();
+        for (k, &weight) in filter.iter().enumerate() {
+            let index = residual_row - k;
+            if index >= start && index < end {
+                rhs[index - start] -= weight * known;
+            }
+        }
+    }
+
+    // Banded Cholesky: M = L L^T, with L stored in the same band layout.
+    for row in 0..gap {
+        for diagonal in (0..=band.min(row)).rev() {
+            let col = row - diagonal;
+            let mut sum = matrix[at(row, diagonal)];
+            let first = row.saturating_sub(band).max(col.saturating_sub(band));
+            for k in first..col {
+                sum -= matrix[at(row, row - k)] * matrix[at(col, col - k)];
+            }
+            if diagonal == 0 {
+                if sum.is_nan() || sum <= 0.0 {
+                    return None;
+                }
+                matrix[at(row, 0)] = sum.sqrt();
+            } else {
+                matrix[at(row, diagonal)] = sum / matrix[at(col, 0)];
+            }
+        }
+    }
+
+    // Forward substitution (L y = b), then back substitution (L^T x = y).
+    for row in 0..gap {
+        let mut sum = rhs[row];
+        for k in row.saturating_sub(band)..row {
+            sum -= matrix[at(row, row - k)] * rhs[k];
+        }
+        rhs[row] = sum / matrix[at(row, 0)];
+    }
+    for row in (0..gap).rev() {
+        let mut sum = rhs[row];
+        for k in row + 1..(row + band + 1).min(gap) {
+            sum -= matrix[at(k, k - row)] * rhs[k];
+        }
+        rhs[row] = sum / matrix[at(row, 0)];
+    }
+
+    if rhs.iter().any(|value| !value.is_finite()) {
+        return None;
+    }
+    Some(rhs.into_iter().map(|value| value as f32).collect())
+}
+
+/// Forward prediction error `e[n] = sum(a[k] * x[n - k])` of `signal` under
+/// the prediction-error `filter`, for every `n` in `range`.
+///
+/// Positions without enough preceding samples get `0.0`.
+pub(crate) fn forward_residual(signal: &[f32], range: Range<usize>, filter: &[f64]) -> Vec<f32> {
+    let order = filter.len() - 1;
+    range
+        .map(|n| {
+            if n < order {
+                return 0.0;
+            }
+            let error: f64 = filter
+                .iter()
+                .enumerate()
+                .map(|(k, &a)| a * f64::from(signal[n - k]))
+                .sum();
+            error as f32
+        })
+        .collect()
+}
+
+/// Backward prediction error `b[n] = sum(a[k] * x[n + k])`: the error made
+/// when predicting each sample from the ones after it, with the same model.
+///
+/// Positions without enough following samples get `0.0`.
+pub(crate) fn backward_residual(signal: &[f32], range: Range<usize>, filter: &[f64]) -> Vec<f32> {
+    let order = filter.len() - 1;
+    range
+        .map(|n| {
+            if n + order >= signal.len() {
+                return 0.0;
+            }
+            let error: f64 = filter
+                .iter()
+                .enumerate()
+                .map(|(k, &a)| a * f64::from(signal[n + k]))
+                .sum();
+            error as f32
+        })
+        .collect()
+}
@@ -0,0 +1,264 @@
+//! How confident the engine is that a cleaned signal is free of defects.
+//!
+//! The [`Cleanliness`] score combines four measures, each in `[0, 1]`:
+//!
+//! | Measure | Weight | `1.0` means |
+//! |---------|--------|-------------|
+//! | click removal | 0.35 | the detector finds nothing left in the output |
+//! | residual impulse energy | 0.25 | none of the impulse energy is left |
+//! | transient preservation | 0.25 | no repair looks like it hit the music |
+//! | repair extent | 0.15 | at most a negligible share of the signal was interpolated |
+//!
+//! The score maps to the bands a front end shows:
+//!
+//! | Score | Band |
+//! |-------|------|
+//! | `0.90..=1.00` | [`CleanlinessBand::Excellent`] |
+//! | `0.75..0.90` | [`CleanlinessBand::Great`] |
+//! | below `0.75` | [`CleanlinessBand::Good`] |
+
+use crate::event::ImpulseEvent;
+
+/// Weight of the click removal measure in the score.
+const REMOVAL_WEIGHT: f32 = 0.35;
+/// Weight of the residual impulse energy measure.
+const RESIDUAL_WEIGHT: f32 = 0.25;
+/// Weight of the transient preservation estimate.
+const TRANSIENT_WEIGHT: f32 = 0.25;
+/// Weight of the repair extent measure.
+const EXTENT_WEIGHT: f32 = 0.15;
+/// Share of interpolated samples at which the repair extent measure reaches
+/// zero.
+const HEAVY_REPAIR_FRACTION: f32 = 0.02;
+/// Contrast ratio from which a repair is taken to have hit a click rather
+/// than the music. A click towers over its neighbours; the peak of a musical
+/// attack sits among samples of similar size.
+const CLICK_CONTRAST: f32 = 4.0;
+/// Lowest score of [`CleanlinessBand::Excellent`].
+const EXCELLENT_SCORE: f32 = 0.9;
+/// Lowest score of [`CleanlinessBand::Great`].
+const GREAT_SCORE: f32 = 0.75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// Label for a [`Cleanliness`] score.
+pub enum CleanlinessBand {
+    /// Below `0.75`: clicks remain, or so much was repaired that some of
+    /// the music is likely to have been touched. Worth a listen.
+    Good,
+    /// `0.75` up to `0.9`: a few doubtful spots.
+    Great,
+    /// `0.9` and above: nothing left for the detector to find and little
+    /// risk to the music.
+    Excellent,
+}
+
+impl CleanlinessBand {
+    /// The band a score falls into.
+    pub fn from_score(score: f32) -> Self {
+        if score >= EXCELLENT_SCORE {
+            CleanlinessBand::Excellent
+        } else if score >= GREAT_SCORE {
+            CleanlinessBand::Great
+        } else {
+            CleanlinessBand::Good
+        }
+    }
+
+    /// Name of the band to show to users.
+    pub fn label(self) -> &'static str {
+        match self {
+            CleanlinessBand::Good => "Good",
+            CleanlinessBand::Great => "Great",
+            CleanlinessBand::Excellent => "Excellent",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Confidence that a cleaned signal is free of defects, with the measures
+/// it was computed from (see the [module documentation](self)).
+pub struct Cleanliness {
+    /// Weighted mean of the measures, in `[0, 1]`.
+    pub score: f32,
+    /// The band `score` falls into.
+    pub band: CleanlinessBand,
+    /// Clicks per second found in the input.
+    pub clicks_per_second_before: f32,
+    /// Clicks per second the same detector still finds in the output.
+    pub clicks_per_second_after: f32,
+    /// Energy of the clicks found in the output, as a share of the energy of
+    /// the clicks found in the input.
+    pub residual_impulse_energy: f32,
+    /// Estimated share of the energy changed by click repair that was taken
+    /// from clicks rather than from the music.
+    ///
+    /// A detection whose peak is at least four times the size of its
+    /// neighbours ([`ImpulseEvent::contrast_ratio`]) is taken to be a click.
+    /// Below that, the repair may have flattened a musical attack, and a
+    /// growing part of the energy it changed is counted as lost.
+    pub transient_preservation: f32,
+    /// Share of the samples that click repair replaced.
+    pub repaired_fraction: f32,
+    /// Duration of the longest repair, in milliseconds.
+    pub longest_repair_ms: f32,
+}
+
+/// Scores a cleaned channel.
+///
+/// `detected` is what the detector found in `normalized`; `repairs` the
+/// spans that were repaired, which the multichannel pipeline widens to the
+/// clicks of other channels. `repaired` is the output, in the same units as
+/// `normalized`, and `residual` what the same detector finds in it.
+pub(crate) fn assess(
+    normalized: &[f32],
+    repaired: &[f32],
+    detected: &[ImpulseEvent],
+    repairs: &[ImpulseEvent],
+    residual: &[ImpulseEvent],
+    sample_rate: u32,
+) -> Cleanliness {
+    let mut meter = CleanlinessMeter::default();
+    for event in detected {
+        meter.detected(span(normalized, event));
+    }
+    for event in repairs {
+        let end = event.end.min(normalized.len()).min(repaired.len());
+        let start = event.start.min(end);
+        meter.repaired(event, &normalized[start..end], &repaired[start..end]);
+    }
+    for event in residual {
+        meter.residual(span(repaired, event));
+    }
+    meter.finish(normalized.len(), sample_rate)
+}
+
+#[derive(Debug, Clone, Default)]
+/// Running totals behind a [`Cleanliness`] score, so that a signal can be
+/// scored piece by piece.
+///
+/// Feeding every event in order gives the same score as scoring the whole
+/// signal at once.
+pub(crate) struct CleanlinessMeter {
+    detected: usize,
+    residual: usize,
+    impulse_energy: f64,
+    residual_energy: f64,
+    changed: f64,
+    doubtful: f64,
+    repaired_samples: usize,
+    reached: usize,
+    longest_repair: usize,
+}
+
+impl CleanlinessMeter {
+    /// Counts a detection in the input, given the input samples under it.
+    pub(crate) fn detected(&mut self, span: &[f32]) {
+        self.detected += 1;
+        self.impulse_energy += energy(span);
+    }
+
+    /// Counts a repair, given the input and output samples under it.
+    /// Repairs must be fed in order of position.
+    pub(crate) fn repaired(&mut self, event: &ImpulseEvent, before: &[f32], after: &[f32]) {
+        let change = span_change(before, after);
+        self.changed += change;
+        self.doubtful += f64::from(doubt(event.contrast_ratio)) * change;
+        let start = event.start.max(self.reached);
+        if event.end > start {
+            self.repaired_samples += event.end - start;
+        }
+        self.reached = self.reached.max(event.end);
+        self.longest_repair = self.longest_repair.max(event.len());
+    }
+
+    /// Counts a detection in the output, given the output samples under it.
+    pub(crate) fn residual(&mut self, span: &[f32]) {
+        self.residual += 1;
+        self.residual_energy += energy(span);
+    }
+
+    /// The score of a signal of `samples` samples.
+    pub(crate) fn finish(&self, samples: usize, sample_rate: u32) -> Cleanliness {
+        let seconds = samples as f32 / sample_rate as f32;
+        let per_second = |count: usize| {
+            if seconds > 0.0 {
+                count as f32 / seconds
+            } else {
+                0.0
+            }
+        };
+
+        let removal = if self.residual == 0 {
+            1.0
+        } else {
+            1.0 - (self.residual as f32 / self.detected.max(1) as f32).min(1.0)
+        };
+
+        let residual_impulse_energy = if self.impulse_energy > 0.0 {
+            (self.residual_energy / self.impulse_energy).min(1.0) as f32
+        } else if self.residual_energy > 0.0 {
+            1.0
+        } else {
+            0.0
+        };
+
+        let transient_preservation = if self.changed > 0.0 {
+            (1.0 - self.doubtful / self.changed) as f32
+        } else {
+            1.0
+        };
+
+        let repaired_fraction = if samples == 0 {
+            0.0
+        } else {
+            self.repaired_samples as f32 / samples as f32
+        };
+        let extent = 1.0 - (repaired_fraction / HEAVY_REPAIR_FRACTION).min(1.0);
+        let longest_repair_ms = self.longest_repair as f32 * 1000.0 / sample_rate as f32;
+
+        let score = REMOVAL_WEIGHT * removal
+            + RESIDUAL_WEIGHT * (1.0 - residual_impulse_energy)
+            + TRANSIENT_WEIGHT * transient_preservation
+            + EXTENT_WEIGHT * extent;
+        Cleanliness {
+            score,
+            band: CleanlinessBand::from_score(score),
+            clicks_per_second_before: per_second(self.detected),
+            clicks_per_second_after: per_second(self.residual),
+            residual_impulse_energy,
+            transient_preservation,
+            repaired_fraction,
+            longest_repair_ms,
+        }
+    }
+}
+
+/// How likely a repair of a detection with the given contrast ratio is to
+/// have hit the music rather than a click: `1.0` for a peak no larger than
+/// its neighbours, falling to `0.0` at [`CLICK_CONTRAST`].
+fn doubt(contrast_ratio: f32) -> f32 {
+    ((CLICK_CONTRAST - contrast_ratio) / (CLICK_CONTRAST - 1.0)).clamp(0.0, 1.0)
+}
+
+/// The samples of `signal` under `event`.
+fn span<'a>(signal: &'a [f32], event: &ImpulseEvent) -> &'a [f32] {
+    let end = event.end.min(signal.len());
+    &signal[event.start.min(end)..end]
+}
+
+/// Sum of the squared samples.
+fn energy(span: &[f32]) -> f64 {
+    span.iter()
+        .fold(0.0, |sum, &s| sum + f64::from(s) * f64::from(s))
+}
+
+/// Sum of the squared differences between `before` and `after`.
+fn span_change(before: &[f32], after: &[f32]) -> f64 {
+    before.iter().zip(after).fold(0.0, |sum, (&a, &b)| {
+        let change = f64::from(a - b);
+        sum + change * change
+    })
+}
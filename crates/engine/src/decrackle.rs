@@ -0,0 +1,167 @@
+//! Crackle removal: dense, low-level impulsive surface noise.
+//!
+//! Crackle is made of thousands of tiny impulses per second, far below the
+//! amplitude floor of the click detector and too many to model one by one.
+//! The decrackler compares every sample against a short running median, which
+//! follows the music but not single-sample spikes, and replaces the samples
+//! that deviate from it by more than the local noise scale. Stretches where
+//! such outliers are rare are left alone, which keeps isolated clicks (and
+//! sharp musical detail) for the stages that are meant to handle them.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+/// Parameters of the decrackle stage ([`BaselineConfig::decrackle`]).
+///
+/// [`BaselineConfig::decrackle`]: crate::BaselineConfig::decrackle
+pub struct DecrackleConfig {
+    /// Length of the running median that serves as the crackle-free
+    /// reference, in samples. Odd lengths centre the median on the sample;
+    /// even values are rounded up. Crackle impulses have to be shorter than
+    /// half of it to be removed.
+    pub median_length: usize,
+    /// A sample is a crackle candidate when it deviates from the running
+    /// median by more than this many robust standard deviations
+    /// (`1.4826 * MAD`) of the deviations in its block.
+    pub threshold_multiplier: f32,
+    /// Deviations smaller than this are never treated, however quiet the
+    /// block. Smooth material deviates from its running median only by tiny
+    /// amounts around its peaks, which would otherwise count as crackle.
+    pub min_amplitude: f32,
+    /// Deviations larger than this are not crackle but clicks, and are
+    /// never touched by this stage.
+    pub max_amplitude: f32,
+    /// Length of the blocks, in samples, over which the noise scale and the
+    /// crackle density are measured.
+    pub block_length: usize,
+    /// Minimum fraction of crackle candidates in a block for the block to be
+    /// treated. Below it, candidates are taken to be isolated impulses or
+    /// musical detail.
+    pub min_density: f32,
+}
+
+impl Default for DecrackleConfig {
+    fn default() -> Self {
+        Self {
+            median_length: 5,
+            threshold_multiplier: 4.0,
+            min_amplitude: 0.005,
+            max_amplitude: 0.1,
+            block_length: 2048,
+            min_density: 0.002,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What the decrackle stage removed from a signal.
+pub struct DecrackleReport {
+    /// Number of samples that were replaced.
+    pub repaired_samples: usize,
+    /// Energy removed: the sum of the squared changes to the signal.
+    pub removed_energy: f64,
+    /// Energy (sum of squared samples) of the signal before decrackling.
+    pub input_energy: f64,
+}
+
+impl DecrackleReport {
+    /// Removed energy relative to the input energy, in dB; negative infinity
+    /// if nothing was removed.
+    pub fn removed_db(&self) -> f64 {
+        if self.input_energy <= 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        10.0 * (self.removed_energy / self.input_energy).log10()
+    }
+
+    /// Folds a block of the stage's input and output into the report, so that
+    /// block-wise reporting matches reporting on the concatenated signal.
+    pub(crate) fn accumulate(&mut self, before: &[f32], after: &[f32]) {
+        for (&before, &after) in before.iter().zip(after) {
+            let change = f64::from(before - after);
+            if change != 0.0 {
+                self.repaired_samples += 1;
+            }
+            self.removed_energy += change * change;
+            self.input_energy += f64::from(before) * f64::from(before);
+        }
+    }
+}
+
+/// Number of samples on either side of a sample that its decrackled value
+/// depends on.
+pub(crate) fn context(config: &DecrackleConfig) -> usize {
+    2 * block_length(config) + config.median_length
+}
+
+fn block_length(config: &DecrackleConfig) -> usize {
+    config.block_length.max(1)
+}
+
+/// Returns `signal` with crackle removed.
+///
+/// Blocks are aligned to fixed positions of the complete signal (`offset` is
+/// the position of `signal[0]`), so a block of a stream is decrackled exactly
+/// like the whole signal given [`context`] samples on either side.
+pub(crate) fn decrackle(signal: &[f32], offset: usize, config: &DecrackleConfig) -> Vec<f32> {
+    let median = running_median(signal, config.median_length / 2);
+    let deviation: Vec<f32> = signal
+        .iter()
+        .zip(&median)
+        .map(|(sample, median)| sample - median)
+        .collect();
+
+    let mut output = signal.to_vec();
+    let block = block_length(config);
+    let mut start = 0;
+    while start < signal.len() {
+        let end = (((offset + start) / block + 1) * block - offset).min(signal.len());
+        let deviations = &deviation[start..end];
+
+        let threshold =
+            (config.threshold_multiplier * robust_scale(deviations)).max(config.min_amplitude);
+        let is_crackle =
+            |deviation: f32| deviation.abs() > threshold && deviation.abs() <= config.max_amplitude;
+        let candidates = deviations.iter().filter(|&&d| is_crackle(d)).count();
+        if candidates as f32 >= config.min_density * deviations.len() as f32 {
+            for index in start..end {
+                if is_crackle(deviation[index]) {
+                    output[index] = median[index];
+                }
+            }
+        }
+        start = end;
+    }
+    output
+}
+
+/// Median of `signal` over `2 * radius + 1` samples centred on each sample,
+/// with the window clamped at the ends of the signal.
+fn running_median(signal: &[f32], radius: usize) -> Vec<f32> {
+    let mut window = Vec::with_capacity(2 * radius + 1);
+    (0..signal.len())
+        .map(|index| {
+            window.clear();
+            window.extend_from_slice(
+                &signal[index.saturating_sub(radius)..(index + radius + 1).min(signal.len())],
+            );
+            let middle = window.len() / 2;
+            *window.select_nth_unstable_by(middle, f32::total_cmp).1
+        })
+        .collect()
+}
+
+/// Robust estimate of the standard deviation of `values`: `1.4826 * MAD`.
+fn robust_scale(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut scratch = values.to_vec();
+    let middle = scratch.len() / 2;
+    let center = *scratch.select_nth_unstable_by(middle, f32::total_cmp).1;
+    for value in &mut scratch {
+        *value = (*value - center).abs();
+    }
+    1.4826 * *scratch.select_nth_unstable_by(middle, f32::total_cmp).1
+}
@@ -0,0 +1,489 @@
+//! Broadband hiss reduction by spectral gain.
+//!
+//! Hiss is stationary noise spread over the whole spectrum, so it cannot be
+//! cut out in the time domain like clicks and crackle. The dehisser learns the
+//! average noise power in every frequency bin (a [`NoiseProfile`]) from a part
+//! of the recording that holds nothing but noise, then analyses the signal in
+//! overlapping frames and attenuates each bin according to how far it rises
+//! above that profile.
+//!
+//! A gain computed independently for every bin and frame fluctuates wherever
+//! the signal is close to the noise floor, and those fluctuations are heard as
+//! "musical noise": short, tonal chirps left behind in the background. The
+//! gains are therefore averaged over neighbouring frames and bins, and never
+//! drop below a floor set by [`DehissConfig::max_reduction_db`], which keeps
+//! the residual noise smooth rather than silent.
+
+use std::collections::VecDeque;
+
+use crate::fft::{hann, Fft};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+/// Parameters of the hiss reduction stage ([`BaselineConfig::dehiss`]).
+///
+/// [`BaselineConfig::dehiss`]: crate::BaselineConfig::dehiss
+pub struct DehissConfig {
+    /// Length of the analysis frames, in samples, rounded up to a power of
+    /// two. Frames overlap by 75 %. Longer frames resolve the spectrum more
+    /// finely but smear transients over a longer time.
+    pub frame_length: usize,
+    /// Where the noise profile is learned from.
+    pub noise_source: NoiseSource,
+    /// Factor applied to the noise profile before it is subtracted. Values
+    /// above `1.0` remove more hiss at the cost of some quiet detail.
+    pub over_subtraction: f32,
+    /// Largest attenuation applied to any bin, in dB. The floor keeps some
+    /// of the noise in place, which masks what is left of it and avoids
+    /// musical noise.
+    pub max_reduction_db: f32,
+    /// Number of frames on either side of a frame that its gains are
+    /// averaged over.
+    pub time_smoothing: usize,
+    /// Number of frequency bins on either side of a bin that its gain is
+    /// averaged over.
+    pub frequency_smoothing: usize,
+}
+
+impl Default for DehissConfig {
+    fn default() -> Self {
+        Self {
+            frame_length: 2048,
+            noise_source: NoiseSource::default(),
+            over_subtraction: 1.5,
+            max_reduction_db: 12.0,
+            time_smoothing: 2,
+            frequency_smoothing: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// The part of the recording that a [`NoiseProfile`] is learned from.
+///
+/// The profile is learned from the input, before click repair, so the
+/// chosen part should be free of clicks. Only frames that lie completely
+/// inside it are used; a part shorter than one frame yields an empty profile,
+/// which disables the reduction.
+pub enum NoiseSource {
+    /// The samples `start..end` of the input, typically a region the user
+    /// marked as "noise only".
+    Region {
+        /// First sample of the region.
+        start: usize,
+        /// End of the region (exclusive).
+        end: usize,
+    },
+    /// The first `length` samples of the input: the lead-in groove, before
+    /// the music starts.
+    LeadIn {
+        /// Length of the lead-in, in samples.
+        length: usize,
+    },
+    /// The quietest frames of the whole input, whatever their position.
+    ///
+    /// Works without any knowledge of the recording, but on material without
+    /// pauses the quietest frames still contain music, and the reduction
+    /// then dulls quiet passages. Frames of digital silence are ignored.
+    Auto {
+        /// Number of frames averaged into the profile.
+        frames: usize,
+    },
+}
+
+impl Default for NoiseSource {
+    fn default() -> Self {
+        NoiseSource::Auto { frames: 32 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Average noise power per frequency bin.
+pub struct NoiseProfile {
+    /// Frame length the profile was measured with.
+    pub frame_length: usize,
+    /// Mean power of each of the `frame_length / 2 + 1` bins of a
+    /// Hann-windowed frame, at the level of the input signal. Estimated
+    /// robustly from the per-bin median for [`NoiseSource::Auto`].
+    pub power: Vec<f32>,
+    /// Number of frames the profile was averaged over; `0` if the noise
+    /// source held no complete frame.
+    pub frames: usize,
+}
+
+impl NoiseProfile {
+    /// Learns the profile that `config` describes from a complete signal.
+    pub fn learn(signal: &[f32], config: &DehissConfig) -> Self {
+        let mut profiler = NoiseProfiler::new(config);
+        profiler.accumulate(signal);
+        profiler.profile()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What the hiss reduction stage removed from a signal.
+pub struct DehissReport {
+    /// Number of frames the noise profile was learned from.
+    pub noise_frames: usize,
+    /// Energy removed: the sum of the squared changes to the signal.
+    pub removed_energy: f64,
+    /// Energy (sum of squared samples) of the signal before hiss reduction.
+    pub input_energy: f64,
+}
+
+impl DehissReport {
+    /// Removed energy relative to the input energy, in dB; negative infinity
+    /// if nothing was removed.
+    pub fn removed_db(&self) -> f64 {
+        if self.input_energy <= 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        10.0 * (self.removed_energy / self.input_energy).log10()
+    }
+
+    /// Folds a block of the stage's input and output into the report, so that
+    /// block-wise reporting matches reporting on the concatenated signal.
+    pub(crate) fn accumulate(&mut self, before: &[f32], after: &[f32]) {
+        for (&before, &after) in before.iter().zip(after) {
+            let change = f64::from(before - after);
+            self.removed_energy += change * change;
+            self.input_energy += f64::from(before) * f64::from(before);
+        }
+    }
+}
+
+fn frame_length(config: &DehissConfig) -> usize {
+    config.frame_length.max(4).next_power_of_two()
+}
+
+/// Number of samples on either side of a sample that its dehissed value
+/// depends on.
+pub(crate) fn context(config: &DehissConfig) -> usize {
+    let length = frame_length(config);
+    2 * length + config.time_smoothing * length / 4
+}
+
+#[derive(Debug, Clone)]
+/// Learns a [`NoiseProfile`] block by block.
+///
+/// Frames start at multiples of a quarter frame from the start of the
+/// signal, whatever the block sizes, so any split of a signal gives the same
+/// profile as [`NoiseProfile::learn`].
+pub(crate) struct NoiseProfiler {
+    source: NoiseSource,
+    fft: Fft,
+    window: Vec<f64>,
+    /// Samples from `position` on that have not been analysed yet.
+    pending: Vec<f32>,
+    position: usize,
+    sum: Vec<f64>,
+    frames: usize,
+    /// Energy and power spectrum of the quietest frames so far
+    /// ([`NoiseSource::Auto`] only).
+    quietest: Vec<(f64, Vec<f64>)>,
+}
+
+impl NoiseProfiler {
+    pub(crate) fn new(config: &DehissConfig) -> Self {
+        let length = frame_length(config);
+        Self {
+            source: config.noise_source,
+            fft: Fft::new(length),
+            window: hann(length),
+            pending: Vec::new(),
+            position: 0,
+            sum: vec![0.0; length / 2 + 1],
+            frames: 0,
+            quietest: Vec::new(),
+        }
+    }
+
+    pub(crate) fn accumulate(&mut self, block: &[f32]) {
+        let length = self.fft.len();
+        let hop = length / 4;
+        self.pending.extend_from_slice(block);
+
+        let mut start = 0;
+        while self.pending.len() - start >= length {
+            let frame_end = self.position + length;
+            match self.source {
+                NoiseSource::Region { start: from, end } => {
+                    if self.position >= from && frame_end <= end {
+                        let power = self.power(&self.pending[start..start + length]);
+                        self.add(&power);
+                    }
+                }
+                NoiseSource::LeadIn { length: lead_in } => {
+                    if frame_end <= lead_in {
+                        let power = self.power(&self.pending[start..start + length]);
+                        self.add(&power);
+                    }
+                }
+                NoiseSource::Auto { frames } => {
+                    let frame = &self.pending[start..start + length];
+                    let energy: f64 = frame.iter().map(|&s| f64::from(s) * f64::from(s)).sum();
+                    if frames > 0 && energy > 0.0 {
+                        self.keep_if_quiet(energy, frames, start);
+                    }
+                }
+            }
+            start += hop;
+            self.position += hop;
+        }
+        self.pending.drain(..start);
+    }
+
+    pub(crate) fn profile(&self) -> NoiseProfile {
+        let power = match self.source {
+            NoiseSource::Auto { .. } => self.median_power(),
+            _ => {
+                let scale = if self.frames == 0 {
+                    0.0
+                } else {
+                    1.0 / self.frames as f64
+                };
+                self.sum
+                    .iter()
+                    .map(|total| (total * scale) as f32)
+                    .collect()
+            }
+        };
+        let frames = match self.source {
+            NoiseSource::Auto { .. } => self.quietest.len(),
+            _ => self.frames,
+        };
+        NoiseProfile {
+            frame_length: self.fft.len(),
+            power,
+            frames,
+        }
+    }
+
+    /// Per-bin median of the quietest frames, scaled to estimate the mean.
+    ///
+    /// Some of the quietest frames may still catch the edge of a note; the
+    /// median keeps their tonal peaks out of the profile. The power of a bin
+    /// of noise is exponentially distributed, with a median of `ln 2` times
+    /// its mean.
+    fn median_power(&self) -> Vec<f32> {
+        let mut values = Vec::with_capacity(self.quietest.len());
+        (0..self.sum.len())
+            .map(|bin| {
+                values.clear();
+                values.extend(self.quietest.iter().map(|(_, power)| power[bin]));
+                if values.is_empty() {
+                    return 0.0;
+                }
+                let middle = values.len() / 2;
+                let median = *values.select_nth_unstable_by(middle, f64::total_cmp).1;
+                (median / std::f64::consts::LN_2) as f32
+            })
+            .collect()
+    }
+
+    fn add(&mut self, power: &[f64]) {
+        for (total, value) in self.sum.iter_mut().zip(power) {
+            *total += value;
+        }
+        self.frames += 1;
+    }
+
+    /// Keeps the frame at `pending[start..]` if it is among the `frames`
+    /// quietest seen so far.
+    fn keep_if_quiet(&mut self, energy: f64, frames: usize, start: usize) {
+        if self.quietest.len() < frames {
+            let power = self.power(&self.pending[start..start + self.fft.len()]);
+            self.quietest.push((energy, power));
+            return;
+        }
+        let (loudest, &(loudest_energy, _)) = self
+            .quietest
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1 .0.total_cmp(&b.1 .0))
+            .expect("at least one frame is kept");
+        if energy < loudest_energy {
+            let power = self.power(&self.pending[start..start + self.fft.len()]);
+            self.quietest[loudest] = (energy, power);
+        }
+    }
+
+    fn power(&self, frame: &[f32]) -> Vec<f64> {
+        let mut re: Vec<f64> = frame
+            .iter()
+            .zip(&self.window)
+            .map(|(&sample, weight)| f64::from(sample) * weight)
+            .collect();
+        let mut im = vec![0.0; re.len()];
+        self.fft.transform(&mut re, &mut im, false);
+        (0..self.sum.len())
+            .map(|bin| re[bin] * re[bin] + im[bin] * im[bin])
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The hiss reduction stage, set up with a noise profile.
+pub(crate) struct Dehisser {
+    config: DehissConfig,
+    fft: Fft,
+    window: Vec<f64>,
+    /// Noise power to subtract from each bin, at the level of the signal
+    /// being processed and including the over-subtraction factor.
+    noise: Vec<f64>,
+    floor: f64,
+}
+
+impl Dehisser {
+    /// Prepares the stage for a signal that was scaled by `gain` after the
+    /// noise `profile` was learned from it.
+    pub(crate) fn new(config: &DehissConfig, profile: &NoiseProfile, gain: f32) -> Self {
+        let length = frame_length(config);
+        let scale = f64::from(config.over_subtraction) * f64::from(gain) * f64::from(gain);
+        let noise = (0..=length / 2)
+            .map(|bin| {
+                profile
+                    .power
+                    .get(bin)
+                    .map_or(0.0, |&p| f64::from(p) * scale)
+            })
+            .collect();
+        Self {
+            config: *config,
+            fft: Fft::new(length),
+            window: hann(length),
+            noise,
+            floor: 10f64.powf(-f64::from(config.max_reduction_db.max(0.0)) / 20.0),
+        }
+    }
+
+    /// Returns `signal` with hiss reduced.
+    ///
+    /// Frames are aligned to fixed positions of the complete signal (`offset`
+    /// is the position of `signal[0]`) and the signal is taken to be silent
+    /// outside `signal`, so a block of a stream is processed exactly like the
+    /// whole signal given [`context`] samples on either side.
+    pub(crate) fn process(&self, signal: &[f32], offset: usize) -> Vec<f32> {
+        if signal.is_empty() {
+            return Vec::new();
+        }
+        let length = self.fft.len();
+        let hop = length / 4;
+        let smoothing = self.config.time_smoothing as i64;
+
+        // Frames that overlap the signal. Gains are only smoothed over these,
+        // so the first and last frames are not pulled down by silence.
+        let first = (offset as i64 - length as i64).div_euclid(hop as i64) + 1;
+        let last = (offset + signal.len() - 1) as i64 / hop as i64;
+
+        let mut accumulated = vec![0.0_f64; signal.len()];
+        let mut spectra: VecDeque<(Vec<f64>, Vec<f64>)> = VecDeque::new();
+        let mut gains: VecDeque<(i64, Vec<f64>)> = VecDeque::new();
+        for frame in first..=last + smoothing {
+            if frame <= last {
+                let (re, im) = self.analyse(signal, offset, frame * hop as i64);
+                gains.push_back((frame, self.raw_gains(&re, &im)));
+                spectra.push_back((re, im));
+            }
+
+            // Every neighbour of this frame has been analysed now.
+            let ready = frame - smoothing;
+            if ready < first {
+                continue;
+            }
+            while gains
+                .front()
+                .is_some_and(|&(index, _)| index < ready - smoothing)
+            {
+                gains.pop_front();
+            }
+            let (mut re, mut im) = spectra.pop_front().expect("frame was analysed");
+            let gain = self.smoothed_gains(&gains);
+            for bin in 0..length {
+                let gain = gain[bin.min(length - bin)];
+                re[bin] *= gain;
+                im[bin] *= gain;
+            }
+            self.fft.transform(&mut re, &mut im, true);
+            self.overlap_add(&re, ready * hop as i64 - offset as i64, &mut accumulated);
+        }
+
+        // The squared Hann window sums to 1.5 at 75 % overlap.
+        accumulated
+            .iter()
+            .map(|&sample| (sample / 1.5) as f32)
+            .collect()
+    }
+
+    /// Windowed spectrum of the frame starting at absolute position `start`.
+    fn analyse(&self, signal: &[f32], offset: usize, start: i64) -> (Vec<f64>, Vec<f64>) {
+        let mut re: Vec<f64> = self
+            .window
+            .iter()
+            .enumerate()
+            .map(|(n, weight)| {
+                let index = start + n as i64 - offset as i64;
+                if index < 0 {
+                    return 0.0;
+                }
+                signal
+                    .get(index as usize)
+                    .map_or(0.0, |&sample| f64::from(sample) * weight)
+            })
+            .collect();
+        let mut im = vec![0.0; re.len()];
+        self.fft.transform(&mut re, &mut im, false);
+        (re, im)
+    }
+
+    /// Spectral subtraction gain of each bin of one frame.
+    fn raw_gains(&self, re: &[f64], im: &[f64]) -> Vec<f64> {
+        self.noise
+            .iter()
+            .enumerate()
+            .map(|(bin, &noise)| {
+                let power = re[bin] * re[bin] + im[bin] * im[bin];
+                if noise <= 0.0 {
+                    1.0
+                } else if power <= 0.0 {
+                    self.floor
+                } else {
+                    (1.0 - noise / power).max(0.0).sqrt().max(self.floor)
+                }
+            })
+            .collect()
+    }
+
+    /// Averages the gains of the frames in `gains` and of neighbouring bins.
+    fn smoothed_gains(&self, gains: &VecDeque<(i64, Vec<f64>)>) -> Vec<f64> {
+        let bins = self.noise.len();
+        let spread = self.config.frequency_smoothing;
+        (0..bins)
+            .map(|bin| {
+                let from = bin.saturating_sub(spread);
+                let to = (bin + spread + 1).min(bins);
+                let total: f64 = gains
+                    .iter()
+                    .map(|(_, frame)| frame[from..to].iter().sum::<f64>())
+                    .sum();
+                total / (gains.len() * (to - from)) as f64
+            })
+            .collect()
+    }
+
+    /// Adds the windowed `frame`, which starts at `start` relative to the
+    /// processed signal, into `output`.
+    fn overlap_add(&self, frame: &[f64], start: i64, output: &mut [f64]) {
+        for (n, (&sample, weight)) in frame.iter().zip(&self.window).enumerate() {
+            let index = start + n as i64;
+            if index >= 0 && (index as usize) < output.len() {
+                output[index as usize] += sample * weight;
+            }
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! Mains hum removal.
+//!
+//! Ground loops add hum at the mains frequency, 50 or 60 Hz depending on the
+//! country, together with a series of harmonics. The mains frequency is not
+//! exact: it wanders by a few tenths of a hertz, and so does the hum on a
+//! recording made from it.
+//!
+//! The dehummer works on overlapping windows. In each window it looks for the
+//! fundamental near its nominal value, fits a sinusoid to the fundamental and
+//! to each harmonic by least squares, and subtracts the fit. Because the fit
+//! only models those exact frequencies over a window of several hundred
+//! milliseconds, it behaves like a comb of very narrow notches that follows
+//! the drift, and leaves the music between the harmonics alone.
+
+use std::f64::consts::PI;
+
+use crate::ar::solve_symmetric;
+use crate::fft::hann;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+/// Parameters of the hum removal stage ([`BaselineConfig::dehum`]).
+///
+/// Frequencies and durations are in physical units and converted with
+/// [`BaselineConfig::sample_rate`].
+///
+/// [`BaselineConfig::dehum`]: crate::BaselineConfig::dehum
+/// [`BaselineConfig::sample_rate`]: crate::BaselineConfig::sample_rate
+pub struct DehumConfig {
+    /// Nominal frequency of the hum.
+    pub fundamental: HumFundamental,
+    /// Number of harmonics removed, the fundamental included. Harmonics above
+    /// the Nyquist frequency are skipped.
+    pub harmonics: usize,
+    /// Largest deviation from the nominal frequency, in Hz, that the tracker
+    /// follows.
+    pub max_drift_hz: f32,
+    /// Length of the analysis windows, in milliseconds. Longer windows make
+    /// narrower notches but follow fast drift less closely.
+    pub window_ms: f32,
+}
+
+impl Default for DehumConfig {
+    fn default() -> Self {
+        Self {
+            fundamental: HumFundamental::default(),
+            harmonics: 8,
+            max_drift_hz: 0.5,
+            window_ms: 400.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// Nominal frequency of the hum to remove.
+pub enum HumFundamental {
+    #[default]
+    /// Decide between 50 and 60 Hz from the whole signal. If neither series
+    /// of harmonics stands out clearly from the spectrum around it, no hum
+    /// is assumed and the stage leaves the signal alone. Hum is recognised by
+    /// at least two of its harmonics (or its fundamental, if that is the only
+    /// one below the Nyquist frequency).
+    Auto,
+    /// A known frequency in Hz, e.g. `50.0` for European mains.
+    Fixed(f32),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What the hum removal stage removed from a signal.
+pub struct DehumReport {
+    /// Nominal hum frequency in Hz, or `None` if no hum was detected.
+    pub fundamental_hz: Option<f32>,
+    /// Energy removed: the sum of the squared changes to the signal.
+    pub removed_energy: f64,
+    /// Energy (sum of squared samples) of the signal before hum removal.
+    pub input_energy: f64,
+}
+
+impl DehumReport {
+    /// Removed energy relative to the input energy, in dB; negative infinity
+    /// if nothing was removed.
+    pub fn removed_db(&self) -> f64 {
+        if self.input_energy <= 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        10.0 * (self.removed_energy / self.input_energy).log10()
+    }
+
+    /// Folds a block of the stage's input and output into the report, so that
+    /// block-wise reporting matches reporting on the concatenated signal.
+    pub(crate) fn accumulate(&mut self, before: &[f32], after: &[f32]) {
+        for (&before, &after) in before.iter().zip(after) {
+            let change = f64::from(before - after);
+            self.removed_energy += change * change;
+            self.input_energy += f64::from(before) * f64::from(before);
+        }
+    }
+}
+
+/// Candidate mains frequencies for [`HumFundamental::Auto`].
+const MAINS_HZ: [f64; 2] = [50.0, 60.0];
+
+/// How many times more energy two harmonics of a mains frequency need than
+/// the same harmonics of frequencies [`NEIGHBOUR_SPACING`] away for
+/// [`HumFundamental::Auto`] to report hum.
+const DOMINANCE: f64 = 4.0;
+
+/// Relative distance of the reference frequencies on either side of each
+/// mains frequency. Music spreads its energy over the spectrum, hum
+/// concentrates it on exact harmonics.
+const NEIGHBOUR_SPACING: f64 = 0.1;
+
+/// Number of frequencies tried on either side of the nominal one when
+/// tracking drift.
+const DRIFT_STEPS: usize = 5;
+
+fn window_length(config: &DehumConfig, sample_rate: u32) -> usize {
+    let samples = f64::from(config.window_ms.max(0.0)) / 1000.0 * f64::from(sample_rate);
+    (samples as usize / 2 * 2).max(64)
+}
+
+/// Number of samples on either side of a sample that its dehummed value
+/// depends on.
+pub(crate) fn context(config: &DehumConfig, sample_rate: u32) -> usize {
+    2 * window_length(config, sample_rate)
+}
+
+#[derive(Debug, Clone)]
+/// Decides between 50 and 60 Hz hum block by block.
+///
+/// Blocks are aligned to the start of the signal, so any split of a signal
+/// gives the same decision.
+pub(crate) struct HumDetector {
+    sample_rate: f64,
+    window: Vec<f64>,
+    /// Samples of the current, incomplete block.
+    pending: Vec<f32>,
+    /// Energy accumulated at each harmonic of each of [`MAINS_HZ`], and at
+    /// the same harmonic of its lower and upper neighbours.
+    scores: [Vec<[f64; 3]>; 2],
+}
+
+impl HumDetector {
+    pub(crate) fn new(config: &DehumConfig, sample_rate: u32) -> Self {
+        let length = window_length(config, sample_rate);
+        Self {
+            sample_rate: f64::from(sample_rate),
+            window: hann(length),
+            pending: Vec::with_capacity(length),
+            scores: MAINS_HZ.map(|mains| {
+                let neighbour = mains * (1.0 + NEIGHBOUR_SPACING);
+                let harmonics =
+                    audible_harmonics(neighbour, config.harmonics, f64::from(sample_rate));
+                vec![[0.0; 3]; harmonics]
+            }),
+        }
+    }
+
+    pub(crate) fn accumulate(&mut self, mut block: &[f32]) {
+        let length = self.window.len();
+        while !block.is_empty() {
+            let take = (length - self.pending.len()).min(block.len());
+            self.pending.extend_from_slice(&block[..take]);
+            block = &block[take..];
+            if self.pending.len() == length {
+                for (scores, &mains) in self.scores.iter_mut().zip(&MAINS_HZ) {
+                    let frequencies = [
+                        mains,
+                        mains * (1.0 - NEIGHBOUR_SPACING),
+                        mains * (1.0 + NEIGHBOUR_SPACING),
+                    ];
+                    for (column, frequency) in frequencies.into_iter().enumerate() {
+                        let energies = harmonic_energies(
+                            &self.pending,
+                            &self.window,
+                            0,
+                            angular(frequency, self.sample_rate),
+                            scores.len(),
+                        );
+                        for (score, energy) in scores.iter_mut().zip(energies) {
+                            score[column] += energy;
+                        }
+                    }
+                }
+                self.pending.clear();
+            }
+        }
+    }
+
+    /// The mains frequency whose harmonics stand out most from their
+    /// neighbourhood, if they stand out clearly.
+    ///
+    /// Each harmonic is compared with its neighbours separately and the
+    /// second-highest ratio decides: a note may well fall on one harmonic,
+    /// but hum stands out on several.
+    pub(crate) fn fundamental(&self) -> Option<f32> {
+        let prominence = |scores: &Vec<[f64; 3]>| {
+            let mut ratios: Vec<f64> = scores
+                .iter()
+                .map(|&[mains, lower, upper]| {
+                    let neighbours = 0.5 * (lower + upper);
+                    if neighbours > 0.0 {
+                        mains / neighbours
+                    } else if mains > 0.0 {
+                        f64::INFINITY
+                    } else {
+                        0.0
+                    }
+                })
+                .collect();
+            ratios.sort_by(|a, b| b.total_cmp(a));
+            let second = ratios.get(1).or(ratios.first()).copied().unwrap_or(0.0);
+            if second > DOMINANCE {
+                second
+            } else {
+                0.0
+            }
+        };
+        let [fifty, sixty] = [prominence(&self.scores[0]), prominence(&self.scores[1])];
+        if fifty == 0.0 && sixty == 0.0 {
+            None
+        } else if fifty >= sixty {
+            Some(MAINS_HZ[0] as f32)
+        } else {
+            Some(MAINS_HZ[1] as f32)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The hum removal stage, set up for one signal.
+pub(crate) struct Dehummer {
+    fundamental: Option<f64>,
+    harmonics: usize,
+    max_drift: f64,
+    sample_rate: f64,
+    window: Vec<f64>,
+}
+
+impl Dehummer {
+    /// Prepares the stage to remove hum at `fundamental` Hz; `None` leaves
+    /// signals unchanged.
+    pub(crate) fn new(config: &DehumConfig, fundamental: Option<f32>, sample_rate: u32) -> Self {
+        Self {
+            fundamental: fundamental.map(f64::from),
+            harmonics: config.harmonics,
+            max_drift: f64::from(config.max_drift_hz.max(0.0)),
+            sample_rate: f64::from(sample_rate),
+            window: hann(window_length(config, sample_rate)),
+        }
+    }
+
+    /// Returns `signal` with hum removed.
+    ///
+    /// Windows are aligned to fixed positions of the complete signal
+    /// (`offset` is the position of `signal[0]`), so a block of a stream is
+    /// processed exactly like the whole signal given [`context`] samples on
+    /// either side.
+    pub(crate) fn process(&self, signal: &[f32], offset: usize) -> Vec<f32> {
+        let Some(nominal) = self.fundamental else {
+            return signal.to_vec();
+        };
+        if signal.is_empty() {
+            return Vec::new();
+        }
+        let length = self.window.len();
+        let hop = length / 2;
+
+        // Windows overlapping the signal; their fits are crossfaded with the
+        // Hann window, which sums to one at 50 % overlap.
+        let first = (offset as i64 - length as i64).div_euclid(hop as i64) + 1;
+        let last = (offset + signal.len() - 1) / hop;
+        let mut hum = vec![0.0_f64; signal.len()];
+        for window_index in first..=last as i64 {
+            let start = window_index * hop as i64 - offset as i64;
+            let from = start.max(0) as usize;
+            let to = ((start + length as i64).max(0) as usize).min(signal.len());
+            if from >= to {
+                continue;
+            }
+            let skipped = (from as i64 - start) as usize;
+            let samples = &signal[from..to];
+            let weights = &self.window[skipped..skipped + samples.len()];
+
+            let frequency = self.track(samples, weights, skipped, nominal);
+            let Some(fit) = self.fit(samples, weights, skipped, frequency) else {
+                continue;
+            };
+            for (index, (&weight, value)) in weights.iter().zip(&fit).enumerate() {
+                hum[from + index] += weight * value;
+            }
+        }
+
+        signal
+            .iter()
+            .zip(&hum)
+            .map(|(&sample, &hum)| (f64::from(sample) - hum) as f32)
+            .collect()
+    }
+
+    /// The fundamental within the drift range of `nominal` that carries the
+    /// most harmonic energy in `samples`, which start `skipped` samples into
+    /// the window.
+    fn track(&self, samples: &[f32], weights: &[f64], skipped: usize, nominal: f64) -> f64 {
+        if self.max_drift <= 0.0 {
+            return nominal;
+        }
+        let step = self.max_drift / DRIFT_STEPS as f64;
+        let energies: Vec<f64> = (0..=2 * DRIFT_STEPS)
+            .map(|k| {
+                let frequency = nominal + (k as f64 - DRIFT_STEPS as f64) * step;
+                let harmonics = audible_harmonics(frequency, self.harmonics, self.sample_rate);
+                let omega = angular(frequency, self.sample_rate);
+                harmonic_energies(samples, weights, skipped, omega, harmonics)
+                    .iter()
+                    .sum::<f64>()
+            })
+            .collect();
+        let best = (0..energies.len())
+            .max_by(|&a, &b| energies[a].total_cmp(&energies[b]))
+            .expect("at least one candidate");
+
+        // Refine between the grid points with a parabola through the best
+        // candidate and its neighbours.
+        let mut offset = 0.0;
+        if best > 0 && best < energies.len() - 1 {
+            let (left, centre, right) = (energies[best - 1], energies[best], energies[best + 1]);
+            let curvature = left - 2.0 * centre + right;
+            if curvature < 0.0 {
+                offset = (0.5 * (left - right) / curvature).clamp(-0.5, 0.5);
+            }
+        }
+        nominal + (best as f64 - DRIFT_STEPS as f64 + offset) * step
+    }
+
+    /// Least-squares fit of the harmonics of `frequency` to `samples`,
+    /// weighted by `weights`. Returns the fitted hum at every sample.
+    fn fit(
+        &self,
+        samples: &[f32],
+        weights: &[f64],
+        skipped: usize,
+        frequency: f64,
+    ) -> Option<Vec<f64>> {
+        let harmonics = audible_harmonics(frequency, self.harmonics, self.sample_rate);
+        let size = 2 * harmonics;
+        if size == 0 || samples.len() < size {
+            return None;
+        }
+
+        let mut basis = vec![0.0; size];
+        let mut matrix = vec![0.0; size * size];
+        let mut rhs = vec![0.0; size];
+        for (index, (&sample, &weight)) in samples.iter().zip(weights).enumerate() {
+            self.basis(frequency, skipped + index, &mut basis);
+            for row in 0..size {
+                let weighted = weight * basis[row];
+                rhs[row] += weighted * f64::from(sample);
+                for col in 0..=row {
+                    matrix[row * size + col] += weighted * basis[col];
+                }
+            }
+        }
+        let coefficients = solve_symmetric(&mut matrix, &mut rhs, size)?;
+
+        Some(
+            (0..samples.len())
+                .map(|index| {
+                    self.basis(frequency, skipped + index, &mut basis);
+                    basis.iter().zip(&coefficients).map(|(b, c)| b * c).sum()
+                })
+                .collect(),
+        )
+    }
+
+    /// Cosine and sine of each harmonic at `position` samples into the
+    /// window.
+    fn basis(&self, frequency: f64, position: usize, basis: &mut [f64]) {
+        let phase = 2.0 * PI * frequency * position as f64 / self.sample_rate;
+        for (harmonic, pair) in basis.chunks_exact_mut(2).enumerate() {
+            let (sin, cos) = (phase * (harmonic + 1) as f64).sin_cos();
+            pair[0] = cos;
+            pair[1] = sin;
+        }
+    }
+}
+
+/// Number of the first `harmonics` harmonics of `frequency` that lie safely
+/// below the Nyquist frequency.
+fn audible_harmonics(frequency: f64, harmonics: usize, sample_rate: f64) -> usize {
+    if frequency <= 0.0 {
+        return 0;
+    }
+    harmonics.min((0.45 * sample_rate / frequency) as usize)
+}
+
+/// Energy of the windowed `samples` at each harmonic of `frequency`, for
+/// samples that start `skipped` samples into the window, with `weights` the
+/// matching part of the window.
+fn harmonic_energies(
+    samples: &[f32],
+    weights: &[f64],
+    skipped: usize,
+    frequency: f64,
+    harmonics: usize,
+) -> Vec<f64> {
+    (1..=harmonics)
+        .map(|harmonic| {
+            let omega = frequency * harmonic as f64;
+            let (step_sin, step_cos) = omega.sin_cos();
+            let (mut sin, mut cos) = (omega * skipped as f64).sin_cos();
+            let (mut re, mut im) = (0.0, 0.0);
+            for (&sample, &weight) in samples.iter().zip(weights) {
+                let value = f64::from(sample) * weight;
+                re += value * cos;
+                im -= value * sin;
+                (sin, cos) = (
+                    sin * step_cos + cos * step_sin,
+                    cos * step_cos - sin * step_sin,
+                );
+            }
+            re * re + im * im
+        })
+        .collect()
+}
+
+/// Angular frequency, in radians per sample, of `frequency` Hz.
+fn angular(frequency: f64, sample_rate: f64) -> f64 {
+    2.0 * PI * frequency / sample_rate
+}
@@ -0,0 +1,775 @@
+//! Impulse detection stage of the pipeline.
+//!
+//! [`ImpulseDetector`] is the extension point for detection algorithms;
+//! [`BaselineDetector`] implements the amplitude and local-contrast heuristics
+//! the pipeline uses by default, and [`ResidualDetector`] the
+//! linear-prediction alternative.
+
+use std::fmt;
+
+use crate::ar;
+use crate::event::ImpulseEvent;
+use crate::pipeline::{BaselineConfig, ResidualConfig, SignalLevels, ThresholdLevel};
+
+#[derive(Debug, Clone, Copy)]
+/// Whole-signal information handed to an [`ImpulseDetector`] along with the
+/// samples to analyze.
+///
+/// Detectors may be run on a block of a longer signal (by
+/// [`StreamingCleaner`](crate::streaming::StreamingCleaner)), so anything that
+/// depends on the signal as a whole has to come from here rather than from the
+/// samples passed in.
+pub struct DetectionContext<'a> {
+    /// Level statistics of the complete input signal, before normalization.
+    pub levels: &'a SignalLevels,
+    /// Gain that normalization applied to the samples being analyzed.
+    pub gain: f32,
+    /// Position of the first analyzed sample within the complete signal;
+    /// non-zero when the detector runs on a block of a longer stream.
+    pub offset: usize,
+}
+
+impl DetectionContext<'_> {
+    /// Mean absolute level of the normalized signal.
+    pub fn mean_abs(&self) -> f32 {
+        self.levels.mean_abs() * self.gain
+    }
+
+    /// Mean absolute sample-to-sample difference of the normalized signal.
+    pub fn mean_abs_diff(&self) -> f32 {
+        self.levels.mean_abs_diff() * self.gain
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// What an [`ImpulseDetector`] found in a signal.
+pub struct Detection {
+    /// Detected impulses, in ascending order of position.
+    pub events: Vec<ImpulseEvent>,
+    /// Threshold the detector applied at each sample of the analyzed signal,
+    /// in the units it compares against: sample amplitude for
+    /// [`BaselineDetector`], prediction error for [`ResidualDetector`].
+    ///
+    /// Either empty or as long as the signal.
+    pub threshold: Vec<f32>,
+}
+
+impl From<Vec<ImpulseEvent>> for Detection {
+    /// Wraps the events of a detector that has no per-sample threshold.
+    fn from(events: Vec<ImpulseEvent>) -> Self {
+        Self {
+            events,
+            threshold: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Detection threshold over the course of a signal, decimated for display.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::{run_baseline_pipeline, BaselineConfig};
+///
+/// let samples: Vec<f32> = (0..10_000).map(|i| (i as f32 * 0.01).sin()).collect();
+/// let output = run_baseline_pipeline(&samples, &BaselineConfig::default());
+///
+/// let envelope = &output.threshold_envelope;
+/// assert_eq!(envelope.values.len(), samples.len().div_ceil(envelope.hop));
+/// assert!(envelope.at(5_000).unwrap() > 0.0);
+/// ```
+pub struct ThresholdEnvelope {
+    /// Distance in samples between consecutive values.
+    pub hop: usize,
+    /// `values[k]` is the threshold at sample `k * hop`.
+    pub values: Vec<f32>,
+}
+
+impl ThresholdEnvelope {
+    /// Spacing of the envelopes the pipeline produces.
+    const HOP: usize = 256;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            hop: Self::HOP,
+            values: Vec::new(),
+        }
+    }
+
+    /// Appends the envelope points of a per-sample `threshold` whose first
+    /// value belongs to sample `offset` of the signal.
+    pub(crate) fn extend(&mut self, threshold: &[f32], offset: usize) {
+        let first = offset.next_multiple_of(self.hop) - offset;
+        self.values
+            .extend(threshold.iter().skip(first).step_by(self.hop).copied());
+    }
+
+    /// Threshold at sample `index`, interpolated linearly between the
+    /// envelope points, or `None` if the envelope is empty.
+    pub fn at(&self, index: usize) -> Option<f32> {
+        let last = self.values.len().checked_sub(1)?;
+        let position = index / self.hop;
+        if position >= last {
+            return Some(self.values[last]);
+        }
+        let t = (index % self.hop) as f32 / self.hop as f32;
+        Some(self.values[position] + (self.values[position + 1] - self.values[position]) * t)
+    }
+}
+
+/// A click/pop detection algorithm.
+///
+/// Implementations receive the normalized signal and report every impulsive
+/// artifact they find as an [`ImpulseEvent`]; the pipeline hands those events
+/// to an [`ImpulseRepairer`](crate::repair::ImpulseRepairer). Combine a
+/// detector with a repairer through
+/// [`Pipeline::builder`](crate::pipeline::Pipeline::builder).
+///
+/// # Block processing
+/// To produce the same result for a block of a signal as for the whole
+/// signal, a detector must only look at samples within
+/// [`ImpulseDetector::context`] of an event's peak, and events must not extend
+/// further than that from their peak.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::detect::{Detection, DetectionContext, ImpulseDetector};
+/// use vinyl_engine::ImpulseEvent;
+///
+/// /// Flags every sample above a fixed level.
+/// #[derive(Debug)]
+/// struct FixedLevel(f32);
+///
+/// impl ImpulseDetector for FixedLevel {
+///     fn detect(&self, signal: &[f32], _context: &DetectionContext) -> Detection {
+///         let events: Vec<ImpulseEvent> = signal
+///             .iter()
+///             .enumerate()
+///             .filter(|(_, sample)| sample.abs() >= self.0)
+///             .map(|(index, &amplitude)| ImpulseEvent {
+///                 start: index,
+///                 end: index + 1,
+///                 peak_index: index,
+///                 amplitude,
+///                 contrast_ratio: 1.0,
+///                 confidence: 1.0,
+///             })
+///             .collect();
+///         events.into()
+///     }
+///
+///     fn context(&self) -> usize {
+///         0
+///     }
+/// }
+/// ```
+pub trait ImpulseDetector: fmt::Debug + Send + Sync {
+    /// Finds the impulses in `signal`, in ascending order of position.
+    fn detect(&self, signal: &[f32], context: &DetectionContext) -> Detection;
+
+    /// Number of samples on either side of an event's peak that the detector
+    /// reads, and that the event may span.
+    fn context(&self) -> usize;
+
+    /// Returns `true` if the sample at `index` looks impulsive enough to be
+    /// part of a click that is already known to be nearby, such as one found
+    /// on another channel of the same recording.
+    ///
+    /// The multichannel pipeline uses this to widen linked clicks. `index`
+    /// always has a neighbor on both sides. The default accepts nothing.
+    fn accepts_linked_sample(&self, signal: &[f32], index: usize) -> bool {
+        let _ = (signal, index);
+        false
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The pipeline's default detector: amplitude threshold, sample-to-sample
+/// step and local contrast gates, followed by region growing.
+///
+/// See [`BaselineConfig`] for the parameters involved.
+pub struct BaselineDetector {
+    config: BaselineConfig,
+}
+
+impl BaselineDetector {
+    /// Creates a detector using the detection parameters of `config`.
+    pub fn new(config: &BaselineConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    /// Detection thresholds for `signal` with the given context.
+    fn thresholds(&self, signal: &[f32], context: &DetectionContext) -> DetectionThresholds {
+        // The threshold is the larger of the adaptive threshold
+        // (level * impulse_threshold_multiplier) and the minimum absolute
+        // threshold (impulse_abs_min), ensuring detection is robust to both
+        // low-level signals and noise.
+        let scale = |level: f32| {
+            (level * self.config.impulse_threshold_multiplier).max(self.config.impulse_abs_min)
+        };
+        let impulse = match self.config.threshold_level {
+            ThresholdLevel::Global => vec![scale(context.mean_abs()); signal.len()],
+            ThresholdLevel::Windowed {
+                window_ms,
+                percentile,
+            } => {
+                let window = self.config.samples(window_ms);
+                let mut levels = windowed_levels(signal, context.offset, window, percentile);
+                for level in &mut levels {
+                    *level = scale(*level);
+                }
+                levels
+            }
+        };
+        let region = context.mean_abs_diff() * self.config.region_growth_multiplier;
+
+        DetectionThresholds { impulse, region }
+    }
+}
+
+/// Distance between the points at which [`windowed_levels`] evaluates its
+/// statistic.
+fn level_hop(window: usize) -> usize {
+    (window / 8).max(1)
+}
+
+/// Per-sample signal level for [`ThresholdLevel::Windowed`]: the given
+/// `percentile` of `|signal|` over a `window` centred on points every
+/// [`level_hop`] samples, interpolated linearly in between.
+///
+/// The points sit at fixed positions of the complete signal (`offset` is the
+/// position of `signal[0]`), so a block of a stream sees the same levels as
+/// the whole signal, given `window / 2 + level_hop(window)` samples of context.
+fn windowed_levels(signal: &[f32], offset: usize, window: usize, percentile: f32) -> Vec<f32> {
+    if signal.is_empty() {
+        return Vec::new();
+    }
+    let hop = level_hop(window);
+    let half = window / 2;
+    let rank = (percentile / 100.0).clamp(0.0, 1.0);
+
+    let level_at = |point: usize| {
+        // `point` is an absolute position; clamp its window to the block.
+        let from = (point.saturating_sub(half).max(offset) - offset).min(signal.len());
+        let to = (point + half + 1).saturating_sub(offset).min(signal.len());
+        if from >= to {
+            return 0.0;
+        }
+        let mut magnitudes: Vec<f32> = signal[from..to].iter().map(|sample| sample.abs()).collect();
+        let nth = ((magnitudes.len() - 1) as f32 * rank).round() as usize;
+        let (_, level, _) = magnitudes.select_nth_unstable_by(nth, f32::total_cmp);
+        *level
+    };
+
+    let first_point = offset / hop * hop;
+    let mut levels = Vec::with_capacity(signal.len());
+    let mut point = first_point;
+    let mut current = level_at(point);
+    while levels.len() < signal.len() {
+        let next = level_at(point + hop);
+        let from = point.max(offset);
+        let to = (point + hop).min(offset + signal.len());
+        for index in from..to {
+            let t = (index - point) as f32 / hop as f32;
+            levels.push(current + (next - current) * t);
+        }
+        point += hop;
+        current = next;
+    }
+    levels
+}
+
+impl ImpulseDetector for BaselineDetector {
+    fn detect(&self, signal: &[f32], context: &DetectionContext) -> Detection {
+        let thresholds = self.thresholds(signal, context);
+        let events = detect_impulses(signal, &thresholds, &self.config);
+        Detection {
+            events,
+            threshold: thresholds.impulse,
+        }
+    }
+
+    fn context(&self) -> usize {
+        let level_context = match self.config.threshold_level {
+            ThresholdLevel::Global => 0,
+            ThresholdLevel::Windowed { window_ms, .. } => {
+                let window = self.config.samples(window_ms);
+                window / 2 + level_hop(window)
+            }
+        };
+        let hold = self.config.samples(REGION_GROWTH_HOLD_MS);
+        self.config.max_click_width() + hold + 1 + level_context
+    }
+
+    fn accepts_linked_sample(&self, signal: &[f32], index: usize) -> bool {
+        passes_local_gates(signal, index, &self.config)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Detector that looks for clicks in the linear-prediction error of the
+/// signal rather than in its amplitude.
+///
+/// The signal is split into frames of [`ResidualConfig::frame_length`]
+/// samples, and each frame gets its own AR model of
+/// [`ResidualConfig::order`]. Music is largely predictable from its recent
+/// past, clicks are not, so the prediction residual of a click stands out
+/// against the frame's residual even when the music around it is loud.
+///
+/// The threshold for each frame is
+/// `threshold_multiplier * 1.4826 * MAD` of its residual, a robust estimate
+/// of the residual's standard deviation that a few clicks cannot inflate. The
+/// model is fitted a second time with the outliers of the first pass left out,
+/// so a strong click does not distort the model it is measured against.
+///
+/// A click corrupts the forward prediction of the samples at and after it,
+/// and the backward prediction of those at and before it; the click itself
+/// is where both are off. Outliers in only one direction, as left by a step
+/// or the abrupt onset of a note, are not reported.
+pub struct ResidualDetector {
+    settings: ResidualConfig,
+    max_click_width: usize,
+}
+
+impl ResidualDetector {
+    /// Creates a detector with the given settings whose events span at most
+    /// `max_click_width` samples.
+    pub fn new(settings: ResidualConfig, max_click_width: usize) -> Self {
+        Self {
+            settings,
+            max_click_width,
+        }
+    }
+
+    /// Computes the forward and backward residuals of `signal` and the
+    /// threshold that applies at each sample.
+    fn residuals(&self, signal: &[f32], offset: usize) -> Residuals {
+        // A frame the model cannot be fitted to, such as one shorter than
+        // the order, keeps zero residuals under the floor threshold.
+        let mut residuals = Residuals {
+            forward: vec![0.0; signal.len()],
+            backward: vec![0.0; signal.len()],
+            threshold: vec![self.settings.min_residual; signal.len()],
+        };
+        let frame_length = self.settings.frame_length.max(2 * self.settings.order + 1);
+
+        // Frames sit at fixed positions in the complete signal, so that a
+        // block of a stream is analyzed with the same frames as the whole.
+        let mut start = 0;
+        while start < signal.len() {
+            let end =
+                (((offset + start) / frame_length + 1) * frame_length - offset).min(signal.len());
+            self.analyze_frame(signal, start, end, &mut residuals);
+            start = end;
+        }
+        residuals
+    }
+
+    /// Fits the model for the frame `start..end` and fills in its part of
+    /// `residuals`.
+    fn analyze_frame(&self, signal: &[f32], start: usize, end: usize, residuals: &mut Residuals) {
+        let order = self.settings.order;
+        // The first predictions of the frame use the samples just before it.
+        let fit_start = start.saturating_sub(order);
+        let Some(mut filter) = ar::fit_ar(&[&signal[fit_start..end]], order) else {
+            return;
+        };
+
+        let mut forward = ar::forward_residual(signal, start..end, &filter);
+        let mut threshold = self.threshold(&forward);
+        let outliers: Vec<usize> = (start..end)
+            .filter(|&n| forward[n - start].abs() > threshold)
+            .collect();
+        if !outliers.is_empty() {
+            // Refit on the stretches of the frame that no outlier touches.
+            let mut segments: Vec<&[f32]> = Vec::new();
+            let mut clean_from = fit_start;
+            for &outlier in &outliers {
+                let clean_to = outlier.saturating_sub(order).max(clean_from);
+                if clean_to > clean_from {
+                    segments.push(&signal[clean_from..clean_to]);
+                }
+                clean_from = clean_from.max((outlier + order + 1).min(end));
+            }
+            if end > clean_from {
+                segments.push(&signal[clean_from..end]);
+            }
+
+            if let Some(refitted) = ar::fit_ar(&segments, order) {
+                filter = refitted;
+                forward = ar::forward_residual(signal, start..end, &filter);
+                threshold = self.threshold(&forward);
+            }
+        }
+
+        let backward = ar::backward_residual(signal, start..end, &filter);
+        residuals.forward[start..end].copy_from_slice(&forward);
+        residuals.backward[start..end].copy_from_slice(&backward);
+        residuals.threshold[start..end].fill(threshold);
+    }
+
+    /// Outlier threshold for a frame with the given residual.
+    fn threshold(&self, residual: &[f32]) -> f32 {
+        let mut deviations = residual.to_vec();
+        let center = median(&mut deviations);
+        for deviation in &mut deviations {
+            *deviation = (*deviation - center).abs();
+        }
+        let scale = 1.4826 * median(&mut deviations);
+
+        (self.settings.threshold_multiplier * scale).max(self.settings.min_residual)
+    }
+
+    /// Builds the event for the click region `start..end`, peaking at `peak`
+    /// or, if not given, where the residuals are largest.
+    fn event(
+        &self,
+        signal: &[f32],
+        residuals: &Residuals,
+        start: usize,
+        end: usize,
+        peak: Option<usize>,
+    ) -> ImpulseEvent {
+        let order = self.settings.order;
+        let strength = |n: usize| residuals.forward[n].abs() + residuals.backward[n].abs();
+        let peak = peak
+            .or_else(|| (start..end).max_by(|&a, &b| strength(a).total_cmp(&strength(b))))
+            .unwrap_or(start)
+            .clamp(1, signal.len() - 2);
+        let half_width = self.max_click_width / 2;
+        let start = start.max(peak.saturating_sub(half_width));
+        let end = end
+            .min(peak + self.max_click_width - half_width)
+            .max(start + 1);
+
+        let amplitude = signal[peak];
+        let local_mean = (signal[peak - 1].abs() + signal[peak + 1].abs()) * 0.5;
+        let contrast_ratio = amplitude.abs() / local_mean.max(f32::MIN_POSITIVE);
+
+        // Directions that have no residual this close to the edges do not
+        // limit the margin.
+        let mut margin = f32::INFINITY;
+        if peak >= order {
+            margin = margin.min(residuals.forward[peak].abs() / residuals.threshold[peak]);
+        }
+        if peak + order < signal.len() {
+            margin = margin.min(residuals.backward[peak].abs() / residuals.threshold[peak]);
+        }
+        let confidence = if margin.is_nan() {
+            0.0
+        } else {
+            (1.0 - 1.0 / margin).clamp(0.0, 1.0)
+        };
+
+        ImpulseEvent {
+            start,
+            end,
+            peak_index: peak,
+            amplitude,
+            contrast_ratio,
+            confidence,
+        }
+    }
+}
+
+impl ImpulseDetector for ResidualDetector {
+    fn detect(&self, signal: &[f32], context: &DetectionContext) -> Detection {
+        if signal.len() < 3 || self.settings.order == 0 {
+            return Detection::default();
+        }
+
+        let order = self.settings.order;
+        let residuals = self.residuals(signal, context.offset);
+        let forward_outlier = |n: usize| residuals.forward[n].abs() > residuals.threshold[n];
+        let backward_outlier = |n: usize| residuals.backward[n].abs() > residuals.threshold[n];
+
+        let mut events: Vec<ImpulseEvent> = Vec::new();
+        let mut index = 0;
+        while index < signal.len() {
+            if !forward_outlier(index) && !backward_outlier(index) {
+                index += 1;
+                continue;
+            }
+
+            // Outliers less than one model order apart belong to the same
+            // disturbance.
+            let group_start = index;
+            let mut group_end = index + 1;
+            let mut probe = index + 1;
+            while probe < signal.len() && probe < group_end + order {
+                if forward_outlier(probe) || backward_outlier(probe) {
+                    group_end = probe + 1;
+                }
+                probe += 1;
+            }
+            index = group_end;
+
+            // Near the ends of the signal only one prediction direction is
+            // available; the missing one cannot narrow the region.
+            let at_start = group_start < order;
+            let at_end = group_end + order > signal.len();
+            let start = if at_start {
+                Some(group_start)
+            } else {
+                (group_start..group_end).find(|&n| forward_outlier(n))
+            };
+            let end = if at_end {
+                Some(group_end)
+            } else {
+                (group_start..group_end)
+                    .rev()
+                    .find(|&n| backward_outlier(n))
+                    .map(|n| n + 1)
+            };
+            let (Some(start), Some(end)) = (start, end) else {
+                continue;
+            };
+            if end <= start {
+                continue;
+            }
+
+            if at_start || at_end {
+                // Without the second direction, separate clicks close to the
+                // edge cannot be told apart by their residuals; split the
+                // region between the peaks of the signal instead.
+                let peaks: Vec<usize> = (start.max(1)..end.min(signal.len() - 1))
+                    .filter(|&n| {
+                        signal[n].abs() >= signal[n - 1].abs()
+                            && signal[n].abs() > signal[n + 1].abs()
+                    })
+                    .collect();
+                let mut from = start;
+                for (position, &peak) in peaks.iter().enumerate() {
+                    let to = peaks
+                        .get(position + 1)
+                        .map_or(end, |&next| peak + (next - peak).div_ceil(2));
+                    events.push(self.event(signal, &residuals, from, to, Some(peak)));
+                    from = to;
+                }
+                if peaks.is_empty() {
+                    events.push(self.event(signal, &residuals, start, end, None));
+                }
+            } else {
+                events.push(self.event(signal, &residuals, start, end, None));
+            }
+        }
+
+        Detection {
+            events,
+            threshold: residuals.threshold,
+        }
+    }
+
+    fn context(&self) -> usize {
+        // Every sample of a frame influences its model, and an event's frame
+        // may start almost a frame before its peak.
+        2 * self.settings.frame_length.max(2 * self.settings.order + 1)
+            + self.max_click_width
+            + self.settings.order
+    }
+}
+
+/// Per-sample residuals and thresholds computed by [`ResidualDetector`].
+struct Residuals {
+    forward: Vec<f32>,
+    backward: Vec<f32>,
+    threshold: Vec<f32>,
+}
+
+/// Median of `values`, which are reordered in the process. `0.0` if empty.
+fn median(values: &mut [f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let middle = values.len() / 2;
+    let (_, median, _) = values.select_nth_unstable_by(middle, f32::total_cmp);
+    *median
+}
+
+#[derive(Debug, Clone)]
+/// Signal-dependent thresholds used by [`detect_impulses`].
+struct DetectionThresholds {
+    /// Minimum absolute amplitude of an impulse peak, per sample.
+    impulse: Vec<f32>,
+    /// Step size above which a sample next to a click still counts as part of
+    /// it.
+    region: f32,
+}
+
+/// Time, in milliseconds, of consecutive quiet steps region growing
+/// tolerates before it decides a click has ended (two samples at 44.1 kHz).
+/// Ringing clicks cross zero, and a small step at a zero crossing should not
+/// cut the region short.
+const REGION_GROWTH_HOLD_MS: f32 = 0.05;
+
+/// Detects impulsive artifacts in the input signal using adaptive thresholding
+/// and local-contrast gating.
+///
+/// This function identifies samples that stand out as impulses based on:
+/// - Absolute amplitude reaching `threshold`
+/// - Sample-to-sample difference exceeding a minimum delta
+/// - Local contrast relative to neighboring samples
+/// - Being a local peak compared to immediate neighbors
+///
+/// Each detected peak is then grown into the full disturbed region around it
+/// (see [`grow_region`]), so wide pops are reported and repaired as a whole.
+/// Weaker peaks inside an already grown region (the ringing of a pop) are
+/// folded into that region; regions of separate clicks closer together than
+/// their widths may overlap.
+///
+/// # Limitations
+/// **Edge samples are excluded from detection**: The algorithm requires access to both
+/// previous and next samples for neighbor-based checks, so impulses at the first sample
+/// (index 0) or last sample (index `len - 1`) cannot be detected. Only indices from
+/// `1` to `len - 2`, inclusive, are candidates for impulse detection.
+///
+/// # Parameters
+/// - `input`: The signal to analyze for impulses.
+/// - `thresholds`: Signal-dependent thresholds, usually from
+///   [`BaselineDetector::thresholds`].
+/// - `config`: Configuration controlling detection thresholds and sensitivity.
+///
+/// # Returns
+/// One [`ImpulseEvent`] per detected impulse, in ascending order of peak position.
+fn detect_impulses(
+    input: &[f32],
+    thresholds: &DetectionThresholds,
+    config: &BaselineConfig,
+) -> Vec<ImpulseEvent> {
+    if input.len() < 3 {
+        return Vec::new();
+    }
+
+    let mut events: Vec<ImpulseEvent> = Vec::new();
+    for index in 1..input.len() - 1 {
+        if input[index].abs() < thresholds.impulse[index]
+            || !passes_local_gates(input, index, config)
+        {
+            continue;
+        }
+        // Later ringing peaks of a pop fall inside the region already grown
+        // from its onset; they are part of that click, not new ones.
+        if let Some(previous) = events.last() {
+            if index < previous.end && input[index].abs() < previous.amplitude.abs() {
+                continue;
+            }
+        }
+
+        let mut event = measure_impulse(input, index, thresholds.impulse[index], config);
+        (event.start, event.end) = grow_region(
+            input,
+            index,
+            thresholds.region,
+            config.max_click_width(),
+            config.samples(REGION_GROWTH_HOLD_MS),
+        );
+        events.push(event);
+    }
+
+    events
+}
+
+/// Extends the click peaking at `peak` to the full span of disturbed samples
+/// around it, returned as a half-open `(start, end)` range.
+///
+/// A sample belongs to the click while the step into it (on the leading side)
+/// or out of it (on the trailing side) exceeds `region_threshold`. Growing
+/// stops after more than `hold` consecutive quiet steps, at the signal
+/// boundaries, or when the region reaches `max_width` samples.
+fn grow_region(
+    input: &[f32],
+    peak: usize,
+    region_threshold: f32,
+    max_width: usize,
+    hold: usize,
+) -> (usize, usize) {
+    let max_width = max_width.max(1);
+    let mut start = peak;
+    let mut end = peak + 1;
+
+    let mut quiet = 0;
+    let mut probe = start;
+    while probe > 1 && end - (probe - 1) <= max_width && quiet <= hold {
+        probe -= 1;
+        if (input[probe] - input[probe - 1]).abs() > region_threshold {
+            start = probe;
+            quiet = 0;
+        } else {
+            quiet += 1;
+        }
+    }
+
+    let mut quiet = 0;
+    let mut probe = end;
+    while probe + 1 < input.len() && probe + 1 - start <= max_width && quiet <= hold {
+        if (input[probe + 1] - input[probe]).abs() > region_threshold {
+            end = probe + 1;
+            quiet = 0;
+        } else {
+            quiet += 1;
+        }
+        probe += 1;
+    }
+
+    (start, end)
+}
+
+/// Builds the [`ImpulseEvent`] for a sample that passed detection, recording
+/// its contrast and how comfortably it cleared each gate.
+fn measure_impulse(
+    input: &[f32],
+    index: usize,
+    threshold: f32,
+    config: &BaselineConfig,
+) -> ImpulseEvent {
+    let amplitude = input[index];
+    let abs = amplitude.abs();
+    let prev = input[index - 1];
+    let next = input[index + 1];
+    let local_mean = (prev.abs() + next.abs()) * 0.5;
+    let contrast_ratio = abs / local_mean.max(f32::MIN_POSITIVE);
+
+    // A zero threshold makes its ratio infinite, so it never limits the margin.
+    let margin = (abs / threshold)
+        .min((amplitude - prev).abs() / config.diff_threshold)
+        .min(contrast_ratio / config.local_contrast_multiplier);
+    let confidence = if margin.is_nan() {
+        0.0
+    } else {
+        (1.0 - 1.0 / margin).clamp(0.0, 1.0)
+    };
+
+    ImpulseEvent {
+        start: index,
+        end: index + 1,
+        peak_index: index,
+        amplitude,
+        contrast_ratio,
+        confidence,
+    }
+}
+
+/// Applies the neighbor-based gates of [`detect_impulses`] to the sample at
+/// `index`: minimum step from the previous sample, local contrast against both
+/// neighbors, and being a local peak.
+///
+/// The amplitude threshold is deliberately not part of this check so that
+/// callers with extra evidence (such as a click already found on another
+/// channel) can accept quieter impulses.
+///
+/// `index` must have a neighbor on both sides.
+fn passes_local_gates(input: &[f32], index: usize, config: &BaselineConfig) -> bool {
+    let abs = input[index].abs();
+    let prev = input[index - 1];
+    let next = input[index + 1];
+    let diff = (input[index] - prev).abs();
+    let local_mean = (prev.abs() + next.abs()) * 0.5;
+
+    diff >= config.diff_threshold
+        && abs >= local_mean * config.local_contrast_multiplier
+        && abs >= prev.abs()
+        && abs >= next.abs()
+}
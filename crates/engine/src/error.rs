@@ -0,0 +1,124 @@
+//! Errors reported by the fallible entry points, such as
+//! [`try_run_pipeline`](crate::pipeline::try_run_pipeline).
+//!
+//! The infallible entry points trust their input: they panic on malformed
+//! calls and pass NaN and infinite samples through. The `try_` variants check
+//! everything up front and describe the first problem they find.
+
+use std::fmt;
+
+/// Lowest sample rate the engine accepts, in Hz.
+pub const MIN_SAMPLE_RATE: u32 = 8_000;
+/// Highest sample rate the engine accepts, in Hz.
+pub const MAX_SAMPLE_RATE: u32 = 384_000;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Why the engine refused to process a signal.
+pub enum Error {
+    /// A configuration value is out of range.
+    InvalidConfig {
+        /// Path of the setting, e.g. `"target_peak"` or
+        /// `"dehiss.max_reduction_db"`.
+        setting: String,
+        /// What is wrong with its value.
+        message: String,
+    },
+    /// [`BaselineConfig::sample_rate`](crate::BaselineConfig::sample_rate)
+    /// is outside [`MIN_SAMPLE_RATE`]`..=`[`MAX_SAMPLE_RATE`].
+    UnsupportedSampleRate {
+        /// The configured sample rate, in Hz.
+        sample_rate: u32,
+    },
+    /// The input holds a NaN or infinite sample.
+    NonFiniteInput {
+        /// Channel of the sample; `0` for mono input.
+        channel: usize,
+        /// Position of the first such sample in the channel.
+        index: usize,
+        /// The sample.
+        value: f32,
+    },
+    /// A multichannel signal has no channels.
+    NoChannels,
+    /// The channels of a multichannel signal differ in length.
+    ChannelLengthMismatch {
+        /// The first channel whose length differs from the first channel's.
+        channel: usize,
+        /// Length of the first channel.
+        expected: usize,
+        /// Length of `channel`.
+        found: usize,
+    },
+    /// A signal compared sample by sample with another one is shorter.
+    LengthMismatch {
+        /// Length of the signal it is compared with.
+        expected: usize,
+        /// Its length.
+        found: usize,
+    },
+    /// The run was stopped through its
+    /// [`CancellationToken`](crate::progress::CancellationToken).
+    Cancelled,
+}
+
+impl Error {
+    pub(crate) fn invalid_config(setting: impl Into<String>, message: impl Into<String>) -> Self {
+        Error::InvalidConfig {
+            setting: setting.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidConfig { setting, message } => {
+                write!(f, "invalid setting `{setting}`: {message}")
+            }
+            Error::UnsupportedSampleRate { sample_rate } => write!(
+                f,
+                "unsupported sample rate {sample_rate} Hz: expected {MIN_SAMPLE_RATE} to {MAX_SAMPLE_RATE} Hz"
+            ),
+            Error::NonFiniteInput {
+                channel,
+                index,
+                value,
+            } => write!(
+                f,
+                "input sample {index} of channel {channel} is {value}: the input must be finite"
+            ),
+            Error::NoChannels => write!(f, "the input has no channels"),
+            Error::ChannelLengthMismatch {
+                channel,
+                expected,
+                found,
+            } => write!(
+                f,
+                "channel {channel} has {found} samples, but channel 0 has {expected}: all channels must have the same length"
+            ),
+            Error::LengthMismatch { expected, found } => write!(
+                f,
+                "the signal has {found} samples, but the signal it is compared with has {expected}"
+            ),
+            Error::Cancelled => write!(f, "the run was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Result type of the fallible entry points.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Checks that every sample of `channel` is finite.
+pub(crate) fn check_finite(samples: &[f32], channel: usize) -> Result<()> {
+    match samples.iter().position(|sample| !sample.is_finite()) {
+        Some(index) => Err(Error::NonFiniteInput {
+            channel,
+            index,
+            value: samples[index],
+        }),
+        None => Ok(()),
+    }
+}
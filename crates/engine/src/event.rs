@@ -0,0 +1,83 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A single impulsive artifact found by the detector.
+///
+/// Besides the affected span, an event records what the detector measured at
+/// the impulse so that callers (UI click markers, QA tooling) can judge how
+/// clear-cut the detection was without re-analyzing the signal.
+pub struct ImpulseEvent {
+    /// First sample of the affected region.
+    pub start: usize,
+    /// One past the last sample of the affected region, so the event covers
+    /// `start..end`.
+    pub end: usize,
+    /// Sample with the largest absolute value inside the region.
+    pub peak_index: usize,
+    /// Signed sample value at `peak_index` in the analyzed (normalized)
+    /// signal. The sign gives the click's polarity.
+    pub amplitude: f32,
+    /// Ratio of the peak's absolute value to the mean absolute value of its
+    /// immediate neighbors.
+    pub contrast_ratio: f32,
+    /// Detection confidence in `[0.0, 1.0]`.
+    ///
+    /// Derived from how far the impulse cleared the weakest of the detector's
+    /// gates: `1 - 1 / margin`, where `margin` is the smallest ratio of a
+    /// measured value to its threshold. An impulse that only just passed
+    /// scores close to `0.0`; one that passed every gate by a factor of four or
+    /// more scores `0.75` or higher.
+    pub confidence: f32,
+}
+
+impl ImpulseEvent {
+    /// Number of samples covered by the event.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns `true` if the event covers no samples.
+    pub fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+
+    /// Returns the event moved `offset` samples later, for mapping
+    /// block-relative detections back to stream positions.
+    pub(crate) fn shifted(self, offset: usize) -> Self {
+        Self {
+            start: self.start + offset,
+            end: self.end + offset,
+            peak_index: self.peak_index + offset,
+            ..self
+        }
+    }
+}
+
+/// Anything that marks a position in the signal where an impulse was found.
+///
+/// Implemented for bare sample indices and for [`ImpulseEvent`], so that
+/// metrics such as [`click_precision_recall`](crate::click_precision_recall)
+/// accept either.
+pub trait ImpulseLocation {
+    /// Distance in samples from this impulse to `index`; `0` if the impulse
+    /// covers `index`.
+    fn distance_to(&self, index: usize) -> usize;
+}
+
+impl ImpulseLocation for usize {
+    fn distance_to(&self, index: usize) -> usize {
+        self.abs_diff(index)
+    }
+}
+
+impl ImpulseLocation for ImpulseEvent {
+    fn distance_to(&self, index: usize) -> usize {
+        if index < self.start {
+            self.start - index
+        } else if index >= self.end {
+            // An empty event still sits at `start`.
+            index + 1 - self.end.max(self.start + 1)
+        } else {
+            0
+        }
+    }
+}
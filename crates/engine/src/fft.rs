@@ -0,0 +1,103 @@
+//! Short-time Fourier analysis shared by the spectral stages.
+//!
+//! A plain iterative radix-2 FFT is all the frame sizes used here need, and
+//! keeps the engine free of dependencies. Arithmetic is done in `f64` so that
+//! an analysis/synthesis round trip with unit gain reproduces the input to
+//! well below the resolution of 24-bit audio.
+
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone)]
+/// Precomputed twiddle factors and bit-reversal table for one frame length.
+pub(crate) struct Fft {
+    len: usize,
+    twiddles: Vec<(f64, f64)>,
+    reversed: Vec<usize>,
+}
+
+impl Fft {
+    /// Prepares transforms of `len` points.
+    ///
+    /// # Panics
+    /// Panics if `len` is not a power of two.
+    pub(crate) fn new(len: usize) -> Self {
+        assert!(
+            len.is_power_of_two(),
+            "FFT length {len} is not a power of two"
+        );
+        let bits = len.trailing_zeros();
+        let reversed = (0..len)
+            .map(|index| {
+                if bits == 0 {
+                    0
+                } else {
+                    index.reverse_bits() >> (usize::BITS - bits)
+                }
+            })
+            .collect();
+        let twiddles = (0..len / 2)
+            .map(|k| {
+                let angle = -2.0 * PI * k as f64 / len as f64;
+                (angle.cos(), angle.sin())
+            })
+            .collect();
+        Self {
+            len,
+            twiddles,
+            reversed,
+        }
+    }
+
+    /// Number of points of the transform.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Transforms `re` + i`im` in place; `inverse` selects the inverse
+    /// transform, which includes the `1 / len` scaling.
+    pub(crate) fn transform(&self, re: &mut [f64], im: &mut [f64], inverse: bool) {
+        debug_assert!(re.len() == self.len && im.len() == self.len);
+        for index in 0..self.len {
+            let target = self.reversed[index];
+            if target > index {
+                re.swap(index, target);
+                im.swap(index, target);
+            }
+        }
+
+        let mut size = 2;
+        while size <= self.len {
+            let half = size / 2;
+            let stride = self.len / size;
+            for start in (0..self.len).step_by(size) {
+                for k in 0..half {
+                    let (cos, sin) = self.twiddles[k * stride];
+                    let sin = if inverse { -sin } else { sin };
+                    let (a, b) = (start + k, start + k + half);
+                    let odd_re = re[b] * cos - im[b] * sin;
+                    let odd_im = re[b] * sin + im[b] * cos;
+                    re[b] = re[a] - odd_re;
+                    im[b] = im[a] - odd_im;
+                    re[a] += odd_re;
+                    im[a] += odd_im;
+                }
+            }
+            size *= 2;
+        }
+
+        if inverse {
+            let scale = 1.0 / self.len as f64;
+            re.iter_mut().for_each(|value| *value *= scale);
+            im.iter_mut().for_each(|value| *value *= scale);
+        }
+    }
+}
+
+/// Periodic Hann window of `len` points. Squared and shifted by `len / 4`,
+/// it sums to a constant `1.5`, which makes it suitable for both analysis and
+/// synthesis at 75 % overlap.
+pub(crate) fn hann(len: usize) -> Vec<f64> {
+    (0..len)
+        .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f64 / len as f64).cos())
+        .collect()
+}
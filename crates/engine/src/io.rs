@@ -0,0 +1,438 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Tail of the `KSDATAFORMAT_SUBTYPE_*` GUIDs shared by PCM and IEEE float.
+/// The first two bytes of the GUID carry the plain format tag.
+const SUBFORMAT_GUID_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// Sample encoding of a WAV file's `data` chunk.
+pub enum SampleFormat {
+    /// Signed 16-bit integer PCM.
+    Int16,
+    /// Signed 24-bit integer PCM, packed into three bytes.
+    Int24,
+    /// Signed 32-bit integer PCM.
+    Int32,
+    /// 32-bit IEEE float.
+    Float32,
+    /// 64-bit IEEE float.
+    Float64,
+}
+
+impl SampleFormat {
+    /// Number of bits each sample occupies in the `data` chunk.
+    pub fn bits_per_sample(self) -> u16 {
+        match self {
+            SampleFormat::Int16 => 16,
+            SampleFormat::Int24 => 24,
+            SampleFormat::Int32 | SampleFormat::Float32 => 32,
+            SampleFormat::Float64 => 64,
+        }
+    }
+
+    fn bytes_per_sample(self) -> usize {
+        usize::from(self.bits_per_sample() / 8)
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, SampleFormat::Float32 | SampleFormat::Float64)
+    }
+
+    fn from_tag(tag: u16, bits: u16) -> io::Result<Self> {
+        match (tag, bits) {
+            (WAVE_FORMAT_PCM, 16) => Ok(SampleFormat::Int16),
+            (WAVE_FORMAT_PCM, 24) => Ok(SampleFormat::Int24),
+            (WAVE_FORMAT_PCM, 32) => Ok(SampleFormat::Int32),
+            (WAVE_FORMAT_IEEE_FLOAT, 32) => Ok(SampleFormat::Float32),
+            (WAVE_FORMAT_IEEE_FLOAT, 64) => Ok(SampleFormat::Float64),
+            _ => Err(invalid_data(format!(
+                "unsupported WAV sample format (format tag {tag:#06x}, {bits} bits per sample)"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Decoded contents of a WAV file.
+///
+/// Samples are de-interleaved into one buffer per channel and scaled to the
+/// nominal `[-1.0, 1.0]` range, so each entry of [`WavAudio::channels`] can be
+/// passed straight to [`run_baseline_pipeline`](crate::run_baseline_pipeline).
+pub struct WavAudio {
+    /// Sample rate in Hz, as declared in the `fmt ` chunk.
+    pub sample_rate: u32,
+    /// Sample encoding the file was stored in.
+    pub format: SampleFormat,
+    /// Per-channel sample buffers, all of the same length.
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl WavAudio {
+    /// Number of sample frames (samples per channel).
+    pub fn frames(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+
+    /// The mean of all channels, frame by frame: the mono signal
+    /// [`analyze`](crate::analyze()) derives a configuration for the whole
+    /// file from.
+    pub fn downmix(&self) -> Vec<f32> {
+        let channels = self.channels.len().max(1) as f32;
+        let mut mix = vec![0.0_f32; self.frames()];
+        for channel in &self.channels {
+            for (mixed, &sample) in mix.iter_mut().zip(channel) {
+                *mixed += sample / channels;
+            }
+        }
+        mix
+    }
+}
+
+/// Reads and decodes a RIFF/WAVE file from disk.
+///
+/// See [`decode_wav`] for the supported encodings.
+pub fn read_wav(path: impl AsRef<Path>) -> io::Result<WavAudio> {
+    let bytes = fs::read(path)?;
+    decode_wav(&bytes)
+}
+
+/// Decodes an in-memory RIFF/WAVE file.
+///
+/// Integer PCM at 16, 24 and 32 bits and IEEE float at 32 and 64 bits are
+/// supported, both with a plain `fmt ` chunk and with `WAVE_FORMAT_EXTENSIBLE`.
+/// Unknown chunks (`LIST`, `fact`, `bext`, ...) are skipped.
+///
+/// # Errors
+/// Returns an [`io::ErrorKind::InvalidData`] error if the RIFF structure is
+/// malformed, the `fmt ` or `data` chunk is missing, or the sample encoding is
+/// not one of the formats listed above.
+pub fn decode_wav(bytes: &[u8]) -> io::Result<WavAudio> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(invalid_data("not a RIFF/WAVE file"));
+    }
+
+    let mut fmt: Option<FmtChunk> = None;
+    let mut data: Option<&[u8]> = None;
+    let mut offset = 12;
+
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let declared = read_u32(bytes, offset + 4) as usize;
+        let body_start = offset + 8;
+        // Streaming writers often leave the size of the last chunk unset, so
+        // clamp it to whatever is actually present.
+        let body_end = body_start.saturating_add(declared).min(bytes.len());
+        let body = &bytes[body_start..body_end];
+
+        match id {
+            b"fmt " => fmt = Some(FmtChunk::parse(body)?),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+
+        // Chunks are word-aligned: odd-sized bodies carry a pad byte.
+        offset = body_end + (declared & 1);
+    }
+
+    let fmt = fmt.ok_or_else(|| invalid_data("WAV file has no fmt chunk"))?;
+    let data = data.ok_or_else(|| invalid_data("WAV file has no data chunk"))?;
+
+    let channel_count = usize::from(fmt.channels);
+    let frame_size = channel_count * fmt.format.bytes_per_sample();
+    let frames = data.len() / frame_size;
+    let mut channels = vec![Vec::with_capacity(frames); channel_count];
+
+    for frame in data.chunks_exact(frame_size) {
+        for (channel, sample) in channels
+            .iter_mut()
+            .zip(frame.chunks_exact(fmt.format.bytes_per_sample()))
+        {
+            channel.push(decode_sample(sample, fmt.format));
+        }
+    }
+
+    Ok(WavAudio {
+        sample_rate: fmt.sample_rate,
+        format: fmt.format,
+        channels,
+    })
+}
+
+/// Encodes `channels` as a WAV file and writes it to `path`.
+///
+/// A typical use is writing [`BaselineOutput::repaired`](crate::BaselineOutput::repaired)
+/// back to disk:
+///
+/// ```no_run
+/// use vinyl_engine::io::{read_wav, write_wav, SampleFormat};
+/// use vinyl_engine::{run_baseline_pipeline, BaselineConfig};
+///
+/// let audio = read_wav("side_a.wav")?;
+/// let output = run_baseline_pipeline(&audio.channels[0], &BaselineConfig::default());
+/// write_wav("side_a_clean.wav", &[&output.repaired], audio.sample_rate, SampleFormat::Int24)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// The file is written under a temporary name next to `path` (`path` with
+/// `.partial` appended) and renamed to `path` once complete, so a failed or
+/// interrupted write never leaves a truncated file at `path`.
+///
+/// See [`encode_wav`] for the encoding rules.
+pub fn write_wav<C: AsRef<[f32]>>(
+    path: impl AsRef<Path>,
+    channels: &[C],
+    sample_rate: u32,
+    format: SampleFormat,
+) -> io::Result<()> {
+    write_atomically(path.as_ref(), |file| {
+        encode_wav(file, channels, sample_rate, format)
+    })
+}
+
+/// Writes a file at `path` through `write`, under a temporary name next to it
+/// (`path` with `.partial` appended) that is renamed to `path` once complete.
+/// The temporary file is removed if `write` fails.
+pub(crate) fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut io::BufWriter<fs::File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = Path::new(&partial);
+
+    let written = fs::File::create(partial).and_then(|file| {
+        let mut file = io::BufWriter::new(file);
+        write(&mut file)?;
+        file.flush()
+    });
+    match written.and_then(|()| fs::rename(partial, path)) {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            // Best effort: the original error is the one worth reporting.
+            let _ = fs::remove_file(partial);
+            Err(error)
+        }
+    }
+}
+
+/// Encodes `channels` as a WAV stream into `writer`.
+///
+/// Channels are interleaved in the order given. Integer formats clamp samples
+/// to `[-1.0, 1.0]` before quantizing; float formats store samples unchanged.
+/// Files with more than two channels are written with
+/// `WAVE_FORMAT_EXTENSIBLE`, as the WAV specification requires.
+///
+/// # Errors
+/// Returns an [`io::ErrorKind::InvalidInput`] error if `channels` is empty,
+/// the channels differ in length, the block alignment or byte rate does not
+/// fit its header field, or the resulting file would exceed the 4 GiB RIFF
+/// size limit. Errors from `writer` are passed through.
+pub fn encode_wav<W: Write, C: AsRef<[f32]>>(
+    writer: &mut W,
+    channels: &[C],
+    sample_rate: u32,
+    format: SampleFormat,
+) -> io::Result<()> {
+    let channel_count = u16::try_from(channels.len())
+        .ok()
+        .filter(|&count| count > 0)
+        .ok_or_else(|| invalid_input("WAV output needs between 1 and 65535 channels"))?;
+    let frames = channels[0].as_ref().len();
+    if channels
+        .iter()
+        .any(|channel| channel.as_ref().len() != frames)
+    {
+        return Err(invalid_input(
+            "all WAV output channels must have the same length",
+        ));
+    }
+
+    let extensible = channel_count > 2;
+    let bits = format.bits_per_sample();
+    let block_align = channel_count
+        .checked_mul(bits / 8)
+        .ok_or_else(|| invalid_input("WAV output has too many channels for its sample format"))?;
+    let byte_rate = sample_rate
+        .checked_mul(u32::from(block_align))
+        .ok_or_else(|| invalid_input("WAV output byte rate exceeds the format's 32-bit limit"))?;
+    let fmt_size: u32 = if extensible { 40 } else { 16 };
+    let data_size = u32::try_from(frames * usize::from(block_align))
+        .ok()
+        .filter(|size| size.checked_add(fmt_size + 21).is_some())
+        .ok_or_else(|| invalid_input("WAV output exceeds the 4 GiB RIFF size limit"))?;
+    let tag = if format.is_float() {
+        WAVE_FORMAT_IEEE_FLOAT
+    } else {
+        WAVE_FORMAT_PCM
+    };
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(4 + 8 + fmt_size + 8 + data_size + (data_size & 1)).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&fmt_size.to_le_bytes())?;
+    writer.write_all(
+        &(if extensible {
+            WAVE_FORMAT_EXTENSIBLE
+        } else {
+            tag
+        })
+        .to_le_bytes(),
+    )?;
+    writer.write_all(&channel_count.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&bits.to_le_bytes())?;
+    if extensible {
+        writer.write_all(&22_u16.to_le_bytes())?;
+        writer.write_all(&bits.to_le_bytes())?;
+        // No speaker positions are assigned; channel order is left to the reader.
+        writer.write_all(&0_u32.to_le_bytes())?;
+        writer.write_all(&tag.to_le_bytes())?;
+        writer.write_all(&SUBFORMAT_GUID_TAIL)?;
+    }
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    let mut frame = Vec::with_capacity(usize::from(block_align));
+    for index in 0..frames {
+        frame.clear();
+        for channel in channels {
+            encode_sample(channel.as_ref()[index], format, &mut frame);
+        }
+        writer.write_all(&frame)?;
+    }
+    if data_size & 1 == 1 {
+        writer.write_all(&[0])?;
+    }
+
+    Ok(())
+}
+
+struct FmtChunk {
+    channels: u16,
+    sample_rate: u32,
+    format: SampleFormat,
+}
+
+impl FmtChunk {
+    fn parse(body: &[u8]) -> io::Result<Self> {
+        if body.len() < 16 {
+            return Err(invalid_data("WAV fmt chunk is truncated"));
+        }
+
+        let mut tag = read_u16(body, 0);
+        let channels = read_u16(body, 2);
+        let sample_rate = read_u32(body, 4);
+        let bits = read_u16(body, 14);
+
+        if tag == WAVE_FORMAT_EXTENSIBLE {
+            if body.len() < 40 {
+                return Err(invalid_data(
+                    "WAVE_FORMAT_EXTENSIBLE fmt chunk is truncated",
+                ));
+            }
+            if body[26..40] != SUBFORMAT_GUID_TAIL {
+                return Err(invalid_data(
+                    "unsupported WAVE_FORMAT_EXTENSIBLE sub-format",
+                ));
+            }
+            // Samples are left-justified in their container, so decoding by
+            // container size is correct even when fewer bits are valid.
+            tag = read_u16(body, 24);
+        }
+
+        if channels == 0 {
+            return Err(invalid_data("WAV fmt chunk declares zero channels"));
+        }
+
+        Ok(Self {
+            channels,
+            sample_rate,
+            format: SampleFormat::from_tag(tag, bits)?,
+        })
+    }
+}
+
+fn decode_sample(bytes: &[u8], format: SampleFormat) -> f32 {
+    match format {
+        SampleFormat::Int16 => f32::from(i16::from_le_bytes([bytes[0], bytes[1]])) / 32_768.0,
+        SampleFormat::Int24 => {
+            // Place the three bytes in the top of an i32 and shift back down to
+            // sign-extend.
+            let value = i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8;
+            value as f32 / 8_388_608.0
+        }
+        SampleFormat::Int32 => {
+            let value = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            (f64::from(value) / 2_147_483_648.0) as f32
+        }
+        SampleFormat::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        SampleFormat::Float64 => {
+            let mut raw = [0_u8; 8];
+            raw.copy_from_slice(&bytes[..8]);
+            f64::from_le_bytes(raw) as f32
+        }
+    }
+}
+
+fn encode_sample(sample: f32, format: SampleFormat, out: &mut Vec<u8>) {
+    match format {
+        SampleFormat::Int16 => {
+            let value = quantize(sample, i16::MAX.into()) as i16;
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        SampleFormat::Int24 => {
+            let value = quantize(sample, 8_388_607.0) as i32;
+            out.extend_from_slice(&value.to_le_bytes()[..3]);
+        }
+        SampleFormat::Int32 => {
+            let value = quantize(sample, i32::MAX.into()) as i32;
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        SampleFormat::Float32 => out.extend_from_slice(&sample.to_le_bytes()),
+        SampleFormat::Float64 => out.extend_from_slice(&f64::from(sample).to_le_bytes()),
+    }
+}
+
+/// Scales a nominal `[-1.0, 1.0]` sample to an integer range of `±max`,
+/// clamping out-of-range input. `NaN` is written as silence.
+fn quantize(sample: f32, max: f64) -> i64 {
+    if sample.is_nan() {
+        return 0;
+    }
+    (f64::from(sample.clamp(-1.0, 1.0)) * max).round() as i64
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+pub(crate) fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn invalid_input(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
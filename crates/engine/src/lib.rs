@@ -1,5 +1,67 @@
+pub mod analyze;
+mod ar;
+pub mod cleanliness;
+pub mod decrackle;
+pub mod dehiss;
+pub mod dehum;
+pub mod detect;
+pub mod error;
+pub mod event;
+mod fft;
+pub mod io;
 pub mod metrics;
+pub mod multichannel;
 pub mod pipeline;
+pub mod preset;
+pub mod progress;
+pub mod repair;
+pub mod report;
+pub mod rumble;
+pub mod spectrogram;
+pub mod streaming;
+pub mod waveform;
+pub mod wow;
 
-pub use metrics::{click_precision_recall, transient_preservation, ClickMetrics};
-pub use pipeline::{run_baseline_pipeline, BaselineConfig, BaselineOutput, ValidationResult};
+pub use analyze::{analyze, AutoProfile, SignalAnalysis};
+pub use cleanliness::{Cleanliness, CleanlinessBand};
+pub use decrackle::{DecrackleConfig, DecrackleReport};
+pub use dehiss::{DehissConfig, DehissReport, NoiseProfile, NoiseSource};
+pub use dehum::{DehumConfig, DehumReport, HumFundamental};
+pub use detect::{
+    BaselineDetector, Detection, DetectionContext, ImpulseDetector, ResidualDetector,
+    ThresholdEnvelope,
+};
+pub use error::{Error, Result, MAX_SAMPLE_RATE, MIN_SAMPLE_RATE};
+pub use event::{ImpulseEvent, ImpulseLocation};
+pub use io::{read_wav, write_wav, SampleFormat, WavAudio};
+pub use metrics::{
+    click_precision_recall, snr_improvement, transient_preservation, try_transient_preservation,
+    ClickMetrics, ClickTolerance, TimeTolerance,
+};
+pub use multichannel::{
+    run_interleaved_pipeline, run_multichannel_pipeline, try_run_multichannel_pipeline,
+    MultichannelOutput,
+};
+pub use pipeline::{
+    run_baseline_pipeline, try_run_pipeline, BaselineConfig, BaselineOutput, DetectionMode,
+    Pipeline, PipelineBuilder, RepairMode, ResidualConfig, SignalLevels, ThresholdLevel,
+    ValidationResult,
+};
+#[cfg(feature = "serde")]
+pub use preset::PresetError;
+pub use preset::{Aggressiveness, Preset, PresetRegistry};
+pub use progress::{CancellationToken, Progress, ProgressSink, Stage};
+pub use repair::{BaselineRepairer, ImpulseRepairer};
+pub use report::{RunReport, RunTiming, REPORT_FORMAT_VERSION};
+pub use rumble::{FilterPhase, LowFrequencyLevels, RumbleConfig};
+pub use spectrogram::{
+    Colormap, FrequencyScale, Spectrogram, SpectrogramConfig, SpectrogramTile, SpectrogramView,
+    WindowFunction,
+};
+pub use streaming::{StreamingCleaner, StreamingSummary};
+pub use waveform::{
+    PeakBucket, WaveformCache, WaveformPeaks, WaveformPyramid, WaveformSignal, DEFAULT_BASE_BUCKET,
+};
+pub use wow::{
+    correct_wow_flutter, measure_wow_flutter, ToneReference, WowFlutterConfig, WowFlutterReport,
+};
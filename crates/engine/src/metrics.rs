@@ -1,4 +1,8 @@
+use crate::error::{Error, Result};
+use crate::event::ImpulseLocation;
+
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClickMetrics {
     pub recall: f32,
     pub precision: f32,
@@ -8,16 +12,17 @@ pub struct ClickMetrics {
 ///
 /// This function evaluates the quality of impulse detection by comparing detected
 /// impulse positions against expected (ground truth) positions. A detection is
-/// considered a true positive if it falls within `tolerance` samples of an expected
-/// impulse.
+/// considered a true positive if it falls within `tolerance` of an expected impulse.
 ///
 /// # Parameters
-/// - `detected`: Indices of samples classified as impulses by the detector.
+/// - `detected`: Impulses reported by the detector, either as bare sample indices or
+///   as [`ImpulseEvent`](crate::ImpulseEvent)s. An event matches an expected impulse
+///   anywhere inside its `start..end` span, or within `tolerance` of it.
 /// - `expected`: Ground truth indices of actual impulses in the signal.
-/// - `tolerance`: Maximum allowed distance (in samples) between a detected impulse
-///   and an expected impulse for them to be considered a match. For example,
-///   `tolerance = 1` means a detection at index 100 will match an expected impulse
-///   at index 99, 100, or 101.
+/// - `tolerance`: Maximum allowed distance between a detected impulse and an
+///   expected impulse for them to be considered a match, either in samples or as a
+///   [`TimeTolerance`]. For example, `tolerance = 1` means a detection at index 100
+///   will match an expected impulse at index 99, 100, or 101.
 ///
 /// # Returns
 /// A [`ClickMetrics`] struct containing:
@@ -32,11 +37,12 @@ pub struct ClickMetrics {
 /// - If only `expected` is empty but `detected` is not, returns recall=1.0 (no true
 ///   impulses were missed) and precision=0.0 (all detections are false positives).
 /// - If only `detected` is empty, returns recall=0.0 and precision=0.0.
-pub fn click_precision_recall(
-    detected: &[usize],
+pub fn click_precision_recall<D: ImpulseLocation, T: ClickTolerance>(
+    detected: &[D],
     expected: &[usize],
-    tolerance: usize,
+    tolerance: T,
 ) -> ClickMetrics {
+    let tolerance = tolerance.samples();
     if expected.is_empty() && detected.is_empty() {
         return ClickMetrics {
             recall: 1.0,
@@ -48,13 +54,9 @@ pub fn click_precision_recall(
     let mut matched = vec![false; expected.len()];
 
     for detection in detected {
-        if let Some((index, _)) = expected
-            .iter()
-            .enumerate()
-            .find(|(idx, &expected_index)| {
-                !matched[*idx] && detection.abs_diff(expected_index) <= tolerance
-            })
-        {
+        if let Some((index, _)) = expected.iter().enumerate().find(|(idx, &expected_index)| {
+            !matched[*idx] && detection.distance_to(expected_index) <= tolerance
+        }) {
             matched[index] = true;
             true_positive += 1;
         }
@@ -74,6 +76,54 @@ pub fn click_precision_recall(
     ClickMetrics { recall, precision }
 }
 
+/// Matching tolerance of [`click_precision_recall`]: a number of samples
+/// (`usize`) or a [`TimeTolerance`].
+pub trait ClickTolerance {
+    /// The tolerance in samples.
+    fn samples(&self) -> usize;
+}
+
+impl ClickTolerance for usize {
+    fn samples(&self) -> usize {
+        *self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A [`click_precision_recall`] tolerance given as a time, for a signal at a
+/// known sample rate.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::metrics::{click_precision_recall, TimeTolerance};
+///
+/// // At 48 kHz, half a millisecond is 24 samples.
+/// let tolerance = TimeTolerance::new(0.5, 48_000);
+/// let metrics = click_precision_recall(&[1_024], &[1_000], tolerance);
+/// assert_eq!(metrics.recall, 1.0);
+/// ```
+pub struct TimeTolerance {
+    /// Tolerance in milliseconds.
+    pub ms: f32,
+    /// Sample rate of the signal, in Hz.
+    pub sample_rate: u32,
+}
+
+impl TimeTolerance {
+    /// A tolerance of `ms` milliseconds in a signal sampled at `sample_rate`.
+    pub fn new(ms: f32, sample_rate: u32) -> Self {
+        Self { ms, sample_rate }
+    }
+}
+
+impl ClickTolerance for TimeTolerance {
+    /// The tolerance rounded to the nearest sample.
+    fn samples(&self) -> usize {
+        (f64::from(self.ms.max(0.0)) / 1000.0 * f64::from(self.sample_rate)).round() as usize
+    }
+}
+
 /// Measures how well transient energy is preserved after signal repair.
 ///
 /// This metric quantifies the similarity between the original and repaired signals
@@ -102,7 +152,8 @@ pub fn click_precision_recall(
 ///
 /// # Panics
 /// Panics if `repaired.len()` is less than `original.len()`, as this would cause out-of-bounds
-/// access when iterating through transient regions.
+/// access when iterating through transient regions. Use
+/// [`try_transient_preservation`] to get an error instead.
 ///
 /// # Implementation Note
 /// The score is computed as `1.0 - (squared_error / original_energy)`, where
@@ -148,3 +199,83 @@ pub fn transient_preservation(
         1.0 - (squared_error / original_energy).min(1.0)
     }
 }
+
+/// Measures transient preservation like [`transient_preservation`], after
+/// checking that `repaired` is at least as long as `original`.
+///
+/// # Errors
+/// Returns [`Error::LengthMismatch`] if `repaired` is shorter than
+/// `original`.
+pub fn try_transient_preservation(
+    original: &[f32],
+    repaired: &[f32],
+    transient_regions: &[(usize, usize)],
+) -> Result<f32> {
+    if repaired.len() < original.len() {
+        return Err(Error::LengthMismatch {
+            expected: original.len(),
+            found: repaired.len(),
+        });
+    }
+    Ok(transient_preservation(
+        original,
+        repaired,
+        transient_regions,
+    ))
+}
+
+/// Measures how much a processing step improved the signal-to-noise ratio.
+///
+/// The noise of a signal is taken to be its difference from the clean
+/// `reference`, so the metric is meant for synthetic tests where the clean
+/// signal is known, e.g. a tone with added hiss. All three signals must be at
+/// the same level; scale `reference` by the pipeline's normalization gain
+/// before comparing it with a normalized output.
+///
+/// # Parameters
+/// - `reference`: The clean signal.
+/// - `noisy`: The signal before processing, i.e. `reference` plus noise.
+/// - `processed`: The signal after processing.
+///
+/// # Returns
+/// `SNR(processed) - SNR(noisy)` in dB, where
+/// `SNR(x) = 10 * log10(energy(reference) / energy(x - reference))`.
+/// Positive values mean the noise was reduced; values far below zero mean
+/// the processing damaged the reference signal more than it removed noise.
+/// Only the first `reference.len()` samples are compared.
+///
+/// # Edge Cases
+/// - If `noisy` carries no noise, there is nothing to improve: returns `0.0`
+///   when `processed` carries none either, negative infinity otherwise.
+/// - If `processed` carries no noise but `noisy` does, returns positive
+///   infinity.
+///
+/// # Panics
+/// Panics if `noisy` or `processed` is shorter than `reference`.
+pub fn snr_improvement(reference: &[f32], noisy: &[f32], processed: &[f32]) -> f32 {
+    assert!(
+        noisy.len() >= reference.len() && processed.len() >= reference.len(),
+        "noisy ({}) and processed ({}) signals must be at least as long as the reference ({})",
+        noisy.len(),
+        processed.len(),
+        reference.len()
+    );
+
+    let noise_energy = |signal: &[f32]| -> f64 {
+        reference
+            .iter()
+            .zip(signal)
+            .map(|(&clean, &sample)| f64::from(sample - clean).powi(2))
+            .sum()
+    };
+    let before = noise_energy(noisy);
+    let after = noise_energy(processed);
+
+    match (before == 0.0, after == 0.0) {
+        (true, true) => 0.0,
+        (true, false) => f32::NEG_INFINITY,
+        (false, true) => f32::INFINITY,
+        // The reference energy cancels out of the SNR difference.
+        (false, false) => (10.0 * (before / after).log10()) as f32,
+    }
+}
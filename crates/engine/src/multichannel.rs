@@ -0,0 +1,460 @@
+use crate::cleanliness;
+use crate::detect::{Detection, DetectionContext, ImpulseDetector, ThresholdEnvelope};
+use crate::error::{check_finite, Error, Result};
+use crate::event::ImpulseEvent;
+use crate::pipeline::{
+    corrects_wow_flutter, detect_in_blocks, normalize_to_peak, prepare_input, repair_in_blocks,
+    validate_output, wow_tracker, BaselineConfig, BaselineOutput, Pipeline, Restoration,
+    SignalLevels,
+};
+use crate::progress::{CancellationToken, Monitor, ProgressSink, Stage};
+use crate::wow::{correct_wow_flutter, ToneDetector, ToneReference};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Output of the multichannel processing pipeline.
+///
+/// Each entry of [`MultichannelOutput::channels`] is a regular
+/// [`BaselineOutput`] for one channel, in input order. All channels share a
+/// single normalization gain and are repaired over the same linked click
+/// spans, so the relative level and stereo image of the input are preserved.
+pub struct MultichannelOutput {
+    /// Per-channel results.
+    ///
+    /// `detected_impulses` lists what the detector found on that channel
+    /// alone; `repaired` reflects the linked repair across all channels.
+    pub channels: Vec<BaselineOutput>,
+    /// One event per physical click, merged across channels.
+    ///
+    /// Detections on different channels that fall within
+    /// [`BaselineConfig::channel_link_window_ms`] of each other are reported
+    /// once.
+    /// The event spans every linked sample (the region that was repaired on
+    /// all channels) and takes its peak, amplitude and contrast from the
+    /// strongest detection. Sorted in ascending order.
+    pub combined_impulses: Vec<ImpulseEvent>,
+}
+
+impl MultichannelOutput {
+    /// Returns the repaired channels interleaved frame by frame
+    /// (`L R L R ...` for stereo).
+    pub fn interleaved_repaired(&self) -> Vec<f32> {
+        let repaired: Vec<&[f32]> = self
+            .channels
+            .iter()
+            .map(|channel| channel.repaired.as_slice())
+            .collect();
+        interleave(&repaired)
+    }
+}
+
+/// Runs the baseline pipeline on a planar multichannel signal.
+///
+/// Compared with running [`run_baseline_pipeline`](crate::run_baseline_pipeline)
+/// once per channel, this:
+/// 1. Normalizes all channels with one gain derived from the loudest channel.
+/// 2. Detects impulses on each channel independently.
+/// 3. Links detections that fall within `config.channel_link_window_ms`
+///    of each other across channels into a single click, then widens each
+///    click to cover quieter counterparts on the other channels: within the
+///    link window, a sample only has to pass the detector's local contrast
+///    gates, not its amplitude threshold.
+/// 4. Repairs **every** channel over the full span of each linked click, so a
+///    click that is detected on only one channel (or a few samples apart on
+///    each) does not leave the channels repaired differently.
+/// 5. Measures wow and flutter on the average of the restored channels and,
+///    if enabled, corrects every channel with the same speed track.
+/// 6. Validates each repaired channel.
+///
+/// # Parameters
+/// - `channels`: One buffer per channel, all of the same length.
+/// - `config`: Pipeline configuration shared by all channels.
+///
+/// # Panics
+/// Panics if the channels differ in length.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::multichannel::run_multichannel_pipeline;
+/// use vinyl_engine::BaselineConfig;
+///
+/// let mut left = vec![0.1_f32; 256];
+/// let mut right = vec![0.1_f32; 256];
+/// left[100] = 0.9;
+/// right[101] = 0.8;
+///
+/// let output = run_multichannel_pipeline(&[left, right], &BaselineConfig::default());
+/// assert_eq!(output.combined_impulses.len(), 1);
+/// assert_eq!(output.combined_impulses[0].peak_index, 100);
+/// ```
+pub fn run_multichannel_pipeline<C: AsRef<[f32]>>(
+    channels: &[C],
+    config: &BaselineConfig,
+) -> MultichannelOutput {
+    Pipeline::new(config).run_multichannel(channels)
+}
+
+/// Runs the multichannel pipeline like [`run_multichannel_pipeline`], after
+/// checking the configuration and the channels.
+///
+/// # Errors
+/// Returns the errors of [`BaselineConfig::validate`], [`Error::NoChannels`]
+/// if `channels` is empty, [`Error::ChannelLengthMismatch`] if the channels
+/// differ in length, and [`Error::NonFiniteInput`] if a channel holds a NaN or
+/// infinite sample.
+pub fn try_run_multichannel_pipeline<C: AsRef<[f32]>>(
+    channels: &[C],
+    config: &BaselineConfig,
+) -> Result<MultichannelOutput> {
+    Pipeline::new(config).try_run_multichannel(channels)
+}
+
+impl Pipeline {
+    /// Runs the pipeline like [`Pipeline::run_multichannel`], after checking
+    /// the configuration and the channels.
+    ///
+    /// # Errors
+    /// See [`try_run_multichannel_pipeline`].
+    pub fn try_run_multichannel<C: AsRef<[f32]>>(
+        &self,
+        channels: &[C],
+    ) -> Result<MultichannelOutput> {
+        self.check_multichannel(channels)?;
+        Ok(self.run_multichannel(channels))
+    }
+
+    /// Checks the configuration and the channels for
+    /// [`Pipeline::try_run_multichannel`].
+    fn check_multichannel<C: AsRef<[f32]>>(&self, channels: &[C]) -> Result<()> {
+        self.config().validate()?;
+        let expected = channels.first().ok_or(Error::NoChannels)?.as_ref().len();
+        for (index, channel) in channels.iter().enumerate() {
+            let channel = channel.as_ref();
+            if channel.len() != expected {
+                return Err(Error::ChannelLengthMismatch {
+                    channel: index,
+                    expected,
+                    found: channel.len(),
+                });
+            }
+        }
+        for (index, channel) in channels.iter().enumerate() {
+            check_finite(channel.as_ref(), index)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the pipeline on a planar multichannel signal.
+    ///
+    /// See [`run_multichannel_pipeline`] for the processing steps; clicks are
+    /// widened to quieter counterparts with
+    /// [`ImpulseDetector::accepts_linked_sample`].
+    ///
+    /// # Panics
+    /// Panics if the channels differ in length.
+    pub fn run_multichannel<C: AsRef<[f32]>>(&self, channels: &[C]) -> MultichannelOutput {
+        self.run_multichannel_monitored(channels, &mut Monitor::silent())
+            .expect("a run without a cancellation token always finishes")
+    }
+
+    /// Runs the pipeline like [`Pipeline::try_run_multichannel`], reporting
+    /// to `progress` as it goes and stopping early if `cancel` is cancelled.
+    ///
+    /// The output is identical to [`Pipeline::run_multichannel`]. Each stage
+    /// runs on every channel before the next one starts.
+    ///
+    /// # Errors
+    /// Returns the errors of [`Pipeline::try_run_multichannel`], and
+    /// [`Error::Cancelled`] if the run was cancelled before it finished.
+    pub fn run_multichannel_with_progress<C: AsRef<[f32]>>(
+        &self,
+        channels: &[C],
+        progress: &mut dyn ProgressSink,
+        cancel: &CancellationToken,
+    ) -> Result<MultichannelOutput> {
+        self.check_multichannel(channels)?;
+        let mut monitor = Monitor::new(self.config(), progress, cancel);
+        self.run_multichannel_monitored(channels, &mut monitor)
+    }
+
+    fn run_multichannel_monitored<C: AsRef<[f32]>>(
+        &self,
+        channels: &[C],
+        monitor: &mut Monitor,
+    ) -> Result<MultichannelOutput> {
+        let config = self.config();
+        let frames = channels.first().map_or(0, |channel| channel.as_ref().len());
+        assert!(
+            channels
+                .iter()
+                .all(|channel| channel.as_ref().len() == frames),
+            "all channels must have the same length ({frames} samples expected)"
+        );
+
+        let count = channels.len() as f32;
+        let mut filtered = Vec::with_capacity(channels.len());
+        let mut levels = Vec::with_capacity(channels.len());
+        for (index, channel) in channels.iter().enumerate() {
+            monitor.advance(Stage::Prepare, index as f32 / count)?;
+            let (channel_filtered, channel_levels) = prepare_input(config, channel.as_ref());
+            filtered.push(channel_filtered);
+            levels.push(channel_levels);
+        }
+        let peak = levels
+            .iter()
+            .map(SignalLevels::peak)
+            .fold(0.0_f32, f32::max);
+        let normalized: Vec<Vec<f32>> = filtered
+            .iter()
+            .map(|channel| normalize_to_peak(channel, peak, config.target_peak))
+            .collect();
+        let gain = if peak <= 0.0 {
+            1.0
+        } else {
+            config.target_peak / peak
+        };
+        let margin = self.context_margin();
+        let mut detections: Vec<Detection> = Vec::with_capacity(channels.len());
+        for (index, (channel, levels)) in normalized.iter().zip(&levels).enumerate() {
+            let context = DetectionContext {
+                levels,
+                gain,
+                offset: 0,
+            };
+            detections.push(detect_in_blocks(
+                self.detector(),
+                channel,
+                &context,
+                margin,
+                |fraction| monitor.advance(Stage::Detect, (index as f32 + fraction) / count),
+            )?);
+        }
+
+        let events: Vec<&[ImpulseEvent]> = detections
+            .iter()
+            .map(|detection| detection.events.as_slice())
+            .collect();
+        let link_window = config.channel_link_window();
+        let mut combined_impulses = link_detections(&events, link_window);
+        for click in &mut combined_impulses {
+            extend_to_counterparts(click, &normalized, self.detector(), link_window);
+        }
+
+        let mut repaired = Vec::with_capacity(channels.len());
+        for (index, channel) in normalized.iter().enumerate() {
+            repaired.push(repair_in_blocks(
+                self.repairer(),
+                channel,
+                &combined_impulses,
+                margin,
+                |fraction| monitor.advance(Stage::Repair, (index as f32 + fraction) / count),
+            )?);
+        }
+        let restoration_margin = self.restoration_margin();
+        let mut restored: Vec<(Restoration, Vec<f32>)> = Vec::with_capacity(channels.len());
+        for (index, (repaired, levels)) in repaired.into_iter().zip(&levels).enumerate() {
+            let mut restoration = Restoration::new(config, levels, gain);
+            let repaired =
+                restoration.apply_in_blocks(repaired, restoration_margin, |fraction| {
+                    monitor.advance(Stage::Restore, (index as f32 + fraction) / count)
+                })?;
+            restored.push((restoration, repaired));
+        }
+
+        // The speed is the same on every channel: measure it once, on the
+        // channels' average, and correct all channels alike.
+        monitor.advance(Stage::WowFlutter, 0.0)?;
+        let tone = config
+            .wow_flutter
+            .as_ref()
+            .filter(|settings| settings.reference == ToneReference::Auto)
+            .map(|_| {
+                let mut tone = ToneDetector::new(config.sample_rate);
+                tone.accumulate(&average(&filtered));
+                tone
+            });
+        let wow_flutter = wow_tracker(config, tone.as_ref()).and_then(|mut tracker| {
+            let repaired: Vec<&[f32]> = restored
+                .iter()
+                .map(|(_, repaired)| repaired.as_slice())
+                .collect();
+            tracker.accumulate(&average(&repaired));
+            tracker.report()
+        });
+
+        let mut outputs = Vec::with_capacity(channels.len());
+        let channels = normalized.into_iter().zip(detections).zip(restored);
+        for (index, ((normalized, detection), (restoration, repaired))) in channels.enumerate() {
+            let levels = &levels[index];
+            let mut threshold_envelope = ThresholdEnvelope::new();
+            threshold_envelope.extend(&detection.threshold, 0);
+            let repaired = match &wow_flutter {
+                Some(report) if corrects_wow_flutter(config) => {
+                    correct_wow_flutter(&repaired, report)
+                }
+                _ => repaired,
+            };
+            let validation = validate_output(&repaired, levels, gain, config);
+            let context = DetectionContext {
+                levels,
+                gain,
+                offset: 0,
+            };
+            let residual =
+                detect_in_blocks(self.detector(), &repaired, &context, margin, |fraction| {
+                    monitor.advance(Stage::Validate, (index as f32 + fraction) / count)
+                })?;
+            let cleanliness = cleanliness::assess(
+                &normalized,
+                &repaired,
+                &detection.events,
+                &combined_impulses,
+                &residual.events,
+                config.sample_rate,
+            );
+            outputs.push(BaselineOutput {
+                normalized,
+                detected_impulses: detection.events,
+                threshold_envelope,
+                repaired,
+                decrackle: restoration.decrackle_report(),
+                dehum: restoration.dehum_report(),
+                dehiss: restoration.dehiss_report(),
+                wow_flutter: wow_flutter.clone(),
+                validation,
+                cleanliness,
+            });
+        }
+        monitor.finish();
+
+        Ok(MultichannelOutput {
+            channels: outputs,
+            combined_impulses,
+        })
+    }
+}
+
+/// Runs the multichannel pipeline on an interleaved buffer.
+///
+/// `samples` holds `channel_count` channels interleaved frame by frame, as
+/// produced by most audio APIs. Trailing samples that do not form a complete
+/// frame are ignored. See [`run_multichannel_pipeline`] for the processing
+/// steps; use [`MultichannelOutput::interleaved_repaired`] to get the result
+/// back in interleaved form.
+///
+/// # Panics
+/// Panics if `channel_count` is zero.
+pub fn run_interleaved_pipeline(
+    samples: &[f32],
+    channel_count: usize,
+    config: &BaselineConfig,
+) -> MultichannelOutput {
+    run_multichannel_pipeline(&deinterleave(samples, channel_count), config)
+}
+
+/// Splits an interleaved buffer into one buffer per channel.
+///
+/// Trailing samples that do not form a complete frame are dropped.
+///
+/// # Panics
+/// Panics if `channel_count` is zero.
+pub fn deinterleave(samples: &[f32], channel_count: usize) -> Vec<Vec<f32>> {
+    assert!(channel_count > 0, "channel_count must be at least 1");
+
+    let frames = samples.len() / channel_count;
+    let mut channels = vec![Vec::with_capacity(frames); channel_count];
+    for frame in samples.chunks_exact(channel_count) {
+        for (channel, &sample) in channels.iter_mut().zip(frame) {
+            channel.push(sample);
+        }
+    }
+    channels
+}
+
+/// Interleaves planar channels frame by frame.
+///
+/// # Panics
+/// Panics if the channels differ in length.
+pub fn interleave<C: AsRef<[f32]>>(channels: &[C]) -> Vec<f32> {
+    let frames = channels.first().map_or(0, |channel| channel.as_ref().len());
+    assert!(
+        channels
+            .iter()
+            .all(|channel| channel.as_ref().len() == frames),
+        "all channels must have the same length ({frames} samples expected)"
+    );
+
+    let mut interleaved = Vec::with_capacity(frames * channels.len());
+    for index in 0..frames {
+        interleaved.extend(channels.iter().map(|channel| channel.as_ref()[index]));
+    }
+    interleaved
+}
+
+/// Averages planar channels of equal length sample by sample.
+fn average<C: AsRef<[f32]>>(channels: &[C]) -> Vec<f32> {
+    let frames = channels.first().map_or(0, |channel| channel.as_ref().len());
+    let scale = 1.0 / channels.len().max(1) as f32;
+    (0..frames)
+        .map(|index| {
+            channels
+                .iter()
+                .map(|channel| channel.as_ref()[index])
+                .sum::<f32>()
+                * scale
+        })
+        .collect()
+}
+
+/// Widens `click` to include samples on any channel, within the link window,
+/// that the detector accepts as part of a known click without them reaching
+/// its own detection criteria.
+fn extend_to_counterparts(
+    click: &mut ImpulseEvent,
+    normalized: &[Vec<f32>],
+    detector: &dyn ImpulseDetector,
+    window: usize,
+) {
+    for channel in normalized {
+        if channel.len() < 3 {
+            continue;
+        }
+        let first = click.start.saturating_sub(window).max(1);
+        let last = (click.end - 1 + window).min(channel.len() - 2);
+        for index in first..=last {
+            if detector.accepts_linked_sample(channel, index) {
+                click.start = click.start.min(index);
+                click.end = click.end.max(index + 1);
+            }
+        }
+    }
+}
+
+/// Groups per-channel detections into linked clicks.
+///
+/// Detections from all channels are merged in position order; a detection
+/// joins the current click when it starts within `window` samples of the
+/// click's last sample.
+fn link_detections(detections: &[&[ImpulseEvent]], window: usize) -> Vec<ImpulseEvent> {
+    let mut all: Vec<ImpulseEvent> = detections.iter().copied().flatten().copied().collect();
+    all.sort_unstable_by_key(|event| event.start);
+
+    let mut clicks: Vec<ImpulseEvent> = Vec::new();
+    for event in all {
+        match clicks.last_mut() {
+            Some(click) if event.start < click.end + window => {
+                let confidence = click.confidence.max(event.confidence);
+                if event.amplitude.abs() > click.amplitude.abs() {
+                    click.peak_index = event.peak_index;
+                    click.amplitude = event.amplitude;
+                    click.contrast_ratio = event.contrast_ratio;
+                }
+                click.end = click.end.max(event.end);
+                click.confidence = confidence;
+            }
+            _ => clicks.push(event),
+        }
+    }
+
+    clicks
+}
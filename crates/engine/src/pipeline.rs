@@ -1,24 +1,90 @@
-#[derive(Debug, Clone)]
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::cleanliness::{self, Cleanliness};
+use crate::decrackle::{self, DecrackleConfig, DecrackleReport};
+use crate::dehiss::{self, DehissConfig, DehissReport, Dehisser, NoiseProfile, NoiseProfiler};
+use crate::dehum::{self, DehumConfig, DehumReport, Dehummer, HumDetector, HumFundamental};
+use crate::detect::{
+    BaselineDetector, Detection, DetectionContext, ImpulseDetector, ResidualDetector,
+    ThresholdEnvelope,
+};
+use crate::error::{check_finite, Error, Result, MAX_SAMPLE_RATE, MIN_SAMPLE_RATE};
+use crate::event::ImpulseEvent;
+use crate::progress::{CancellationToken, Monitor, ProgressSink, Stage};
+use crate::repair::{BaselineRepairer, ImpulseRepairer};
+use crate::rumble::{self, LowFrequencyLevels, LowFrequencyMeter, RumbleConfig, RumbleFilter};
+use crate::wow::{
+    correct_wow_flutter, ToneDetector, ToneReference, WowFlutterConfig, WowFlutterReport,
+    WowTracker,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
 /// Configuration parameters for the baseline normalization and impulse-detection pipeline.
 ///
 /// These values control how the input signal is normalized and how impulsive artifacts
 /// are detected and filtered. Adjust them to trade off between sensitivity to impulses
 /// and robustness to normal signal variation.
+///
+/// # Serialization
+/// With the `serde` feature, configurations serialize to JSON, TOML and any
+/// other serde format. Settings appear under their field names, enum values
+/// in `snake_case`, and optional stages are left out (or `null` in JSON)
+/// when disabled. Missing settings take their [`BaselineConfig::default`]
+/// values, so a file only needs the settings it changes:
+///
+/// ```toml
+/// sample_rate = 96000
+/// impulse_abs_min = 0.2
+/// threshold_level = { windowed = { window_ms = 500.0, percentile = 40.0 } }
+/// repair_mode = { autoregressive = { order = 32, context = 512 } }
+///
+/// [rumble]
+/// cutoff_hz = 20.0
+/// order = 4
+/// phase = "minimum_phase"
+/// ```
+///
+/// Field names and their meaning stay stable; new settings are only added
+/// with defaults that keep the previous behaviour.
 pub struct BaselineConfig {
+    /// Sample rate of the signal, in Hz.
+    ///
+    /// Settings given as durations (in milliseconds) or frequencies are
+    /// converted with it, so a configuration behaves the same at 44.1 kHz
+    /// and 192 kHz. The low-frequency measures in [`ValidationResult`] also
+    /// depend on it.
+    ///
+    /// Settings that describe a signal model rather than the music, such as
+    /// AR orders and the frames and context they are fitted over, stay in
+    /// samples.
+    pub sample_rate: u32,
+    /// Settings of the rumble filter, which removes sub-sonic content and DC
+    /// offset before normalization and detection; `None` (the default)
+    /// skips the stage.
+    pub rumble: Option<RumbleConfig>,
     /// Target absolute peak level after normalization.
     ///
     /// The input is scaled so that its maximum absolute sample value is close to this
     /// value (provided the original peak is non-zero). Typical values are in the range
     /// `[0.0, 1.0]`.
     pub target_peak: f32,
-    /// Multiplier applied to the mean absolute signal level to form the impulse
-    /// detection threshold.
+    /// Multiplier applied to the signal level to form the impulse detection
+    /// threshold.
     ///
-    /// The mean absolute value of the input is multiplied by this factor, and the
+    /// The level (see `threshold_level`) is multiplied by this factor, and the
     /// result is combined with `impulse_abs_min` to obtain the effective threshold
     /// for considering a sample as a potential impulse:  
-    /// `threshold = max(mean_abs * impulse_threshold_multiplier, impulse_abs_min)`.
+    /// `threshold = max(level * impulse_threshold_multiplier, impulse_abs_min)`.
     pub impulse_threshold_multiplier: f32,
+    /// How the signal level behind the impulse threshold is measured.
+    ///
+    /// With [`ThresholdLevel::Windowed`] (the default) the threshold follows
+    /// the programme level through the recording; [`ThresholdLevel::Global`]
+    /// uses one level for the whole signal.
+    pub threshold_level: ThresholdLevel,
     /// Minimum absolute amplitude that a sample must have to be considered as an
     /// impulse candidate.
     ///
@@ -42,21 +108,400 @@ pub struct BaselineConfig {
     /// true impulsive clicks. Higher values reduce false positives but may miss
     /// smaller clicks; lower values increase sensitivity but may flag normal transients.
     pub local_contrast_multiplier: f32,
+    /// Maximum distance, in milliseconds, between detections on different
+    /// channels for them to be treated as the same physical click.
+    ///
+    /// Only used by the multichannel pipeline
+    /// ([`run_multichannel_pipeline`](crate::multichannel::run_multichannel_pipeline)).
+    /// Detections that fall within this window of each other are merged and
+    /// every channel is repaired over the merged span, which keeps the stereo
+    /// image stable at click sites.
+    pub channel_link_window_ms: f32,
+    /// Maximum duration, in milliseconds, that a single detected click may
+    /// grow to.
+    ///
+    /// Each detection starts at the impulse peak and is extended on both sides
+    /// for as long as the signal stays disturbed (see
+    /// `region_growth_multiplier`), so that the repair replaces the whole
+    /// pop rather than only its peak sample. Real pops are 1–3 ms long; the
+    /// default leaves room for the ringing of the stylus after a large one.
+    pub max_click_duration_ms: f32,
+    /// Multiplier applied to the mean absolute sample-to-sample difference of
+    /// the signal to decide where a click ends.
+    ///
+    /// While growing a detection, a neighboring sample is considered part of
+    /// the click if the step across it exceeds
+    /// `mean_abs_diff * region_growth_multiplier`. Lower values produce wider
+    /// repairs; higher values keep repairs close to the peak.
+    pub region_growth_multiplier: f32,
+    /// Algorithm used to find clicks.
+    ///
+    /// Selects the detector of pipelines built with the default stages;
+    /// ignored by pipelines built with a different [`ImpulseDetector`].
+    pub detection_mode: DetectionMode,
+    /// Settings of the decrackle stage, which removes dense low-level
+    /// crackle after click repair; `None` (the default) skips the stage.
+    pub decrackle: Option<DecrackleConfig>,
+    /// Settings of the hum removal stage, which runs after decrackling;
+    /// `None` (the default) skips the stage.
+    pub dehum: Option<DehumConfig>,
+    /// Settings of the hiss reduction stage, which runs after hum removal;
+    /// `None` (the default) skips the stage.
+    pub dehiss: Option<DehissConfig>,
+    /// Settings of wow and flutter measurement, which tracks the speed of
+    /// the restored signal and can resample it to correct the speed
+    /// variations; `None` (the default) skips the stage.
+    pub wow_flutter: Option<WowFlutterConfig>,
+    /// Strategy used to fill in the samples of each detected click.
+    ///
+    /// Configures the default [`BaselineRepairer`]; ignored by pipelines
+    /// built with a different [`ImpulseRepairer`].
+    pub repair_mode: RepairMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// Signal level measure that [`BaselineConfig::impulse_threshold_multiplier`]
+/// scales into the impulse detection threshold.
+pub enum ThresholdLevel {
+    /// Mean absolute level of the whole signal.
+    ///
+    /// A recording with a quiet intro and a loud finale gets a threshold that
+    /// is too high for the intro and too low for the finale.
+    Global,
+    /// A percentile of the absolute sample values in a window centred on each
+    /// sample, so the threshold rises and falls with the music.
+    ///
+    /// The statistic is evaluated every eighth of a window and interpolated
+    /// linearly in between. A percentile rather than the mean keeps the
+    /// clicks themselves from raising the threshold around them. The default,
+    /// the 40th percentile over 370 ms, lands close to the mean
+    /// absolute level of typical material, so thresholds stay comparable with
+    /// [`ThresholdLevel::Global`].
+    Windowed {
+        /// Window length in milliseconds.
+        window_ms: f32,
+        /// Percentile of the absolute sample values, in `[0.0, 100.0]`;
+        /// `50.0` is the median.
+        percentile: f32,
+    },
+}
+
+impl Default for ThresholdLevel {
+    fn default() -> Self {
+        ThresholdLevel::Windowed {
+            window_ms: 370.0,
+            percentile: 40.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// How clicks are found in the normalized signal.
+pub enum DetectionMode {
+    #[default]
+    /// Amplitude threshold relative to the signal level measured by
+    /// [`BaselineConfig::threshold_level`], gated by the sample-to-sample
+    /// step and local contrast ([`BaselineDetector`]).
+    ///
+    /// Cheap and reliable. With the default sliding-window level the
+    /// threshold follows the music, but a click riding on a loud passage
+    /// must still stand out in amplitude from the music around it.
+    Threshold,
+    /// Linear-prediction residual compared against a locally estimated
+    /// robust scale ([`ResidualDetector`]).
+    ///
+    /// Clicks are unpredictable from the surrounding music, so they show up
+    /// in the prediction error regardless of how loud the music is, while
+    /// predictable content such as bass notes does not.
+    Residual(ResidualConfig),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+/// Parameters of [`DetectionMode::Residual`].
+pub struct ResidualConfig {
+    /// Order of the AR model fitted to each frame.
+    pub order: usize,
+    /// Length, in samples, of the frames the signal is modelled in. Each
+    /// frame gets its own model and its own residual scale.
+    pub frame_length: usize,
+    /// A sample is an outlier when its prediction error exceeds this many
+    /// robust standard deviations (`1.4826 * MAD`) of the frame's residual.
+    pub threshold_multiplier: f32,
+    /// Floor on the residual threshold, so that numerical noise in silent or
+    /// perfectly predictable frames is not flagged.
+    pub min_residual: f32,
+}
+
+impl Default for ResidualConfig {
+    fn default() -> Self {
+        Self {
+            order: 16,
+            frame_length: 1024,
+            threshold_multiplier: 8.0,
+            min_residual: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// How detected click regions are filled in.
+pub enum RepairMode {
+    #[default]
+    /// Straight-line interpolation between the samples on either side.
+    ///
+    /// Fast and transparent for clicks a few samples wide, but leaves an
+    /// audible flat spot in the waveform across wider gaps.
+    Linear,
+    /// Least-squares autoregressive (LSAR) interpolation, as used in classic
+    /// declicking.
+    ///
+    /// An AR model is fitted to the clean signal on both sides of the region
+    /// and the missing samples are chosen to be as predictable as possible
+    /// under that model, which continues tonal content through the gap.
+    /// Regions without `order` clean samples on both sides fall back to
+    /// [`RepairMode::Linear`].
+    Autoregressive {
+        /// Number of AR coefficients. Higher orders model more complex
+        /// spectra but need more context and computation.
+        order: usize,
+        /// Maximum number of clean samples used on each side of the region
+        /// to fit the model.
+        context: usize,
+    },
+}
+
+impl RepairMode {
+    /// Autoregressive repair with settings suited to clicks up to a few
+    /// hundred samples wide.
+    pub const fn autoregressive() -> Self {
+        RepairMode::Autoregressive {
+            order: 32,
+            context: 512,
+        }
+    }
+}
+
+impl BaselineConfig {
+    /// Number of samples that `ms` milliseconds last at
+    /// [`BaselineConfig::sample_rate`], rounded to the nearest sample.
+    pub fn samples(&self, ms: f32) -> usize {
+        (f64::from(ms.max(0.0)) / 1000.0 * f64::from(self.sample_rate)).round() as usize
+    }
+
+    /// [`BaselineConfig::max_click_duration_ms`] in samples.
+    pub fn max_click_width(&self) -> usize {
+        self.samples(self.max_click_duration_ms)
+    }
+
+    /// [`BaselineConfig::channel_link_window_ms`] in samples.
+    pub fn channel_link_window(&self) -> usize {
+        self.samples(self.channel_link_window_ms)
+    }
+}
+
+impl BaselineConfig {
+    /// Checks that every setting is in range for the pipeline.
+    ///
+    /// # Errors
+    /// Returns [`Error::UnsupportedSampleRate`] if `sample_rate` is outside
+    /// [`MIN_SAMPLE_RATE`]`..=`[`MAX_SAMPLE_RATE`], and otherwise
+    /// [`Error::InvalidConfig`] naming the first setting that is out of
+    /// range, such as a negative threshold, a zero `target_peak` or a rumble
+    /// cutoff above the Nyquist frequency.
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&self.sample_rate) {
+            return Err(Error::UnsupportedSampleRate {
+                sample_rate: self.sample_rate,
+            });
+        }
+        let nyquist = self.sample_rate as f32 / 2.0;
+
+        positive("target_peak", self.target_peak)?;
+        non_negative(
+            "impulse_threshold_multiplier",
+            self.impulse_threshold_multiplier,
+        )?;
+        non_negative("impulse_abs_min", self.impulse_abs_min)?;
+        non_negative("diff_threshold", self.diff_threshold)?;
+        non_negative("local_contrast_multiplier", self.local_contrast_multiplier)?;
+        non_negative("channel_link_window_ms", self.channel_link_window_ms)?;
+        positive("max_click_duration_ms", self.max_click_duration_ms)?;
+        non_negative("region_growth_multiplier", self.region_growth_multiplier)?;
+        if let ThresholdLevel::Windowed {
+            window_ms,
+            percentile,
+        } = self.threshold_level
+        {
+            positive("threshold_level.window_ms", window_ms)?;
+            within("threshold_level.percentile", percentile, 0.0, 100.0)?;
+        }
+        if let DetectionMode::Residual(settings) = &self.detection_mode {
+            at_least("detection_mode.order", settings.order, 1)?;
+            at_least(
+                "detection_mode.frame_length",
+                settings.frame_length,
+                settings.order + 1,
+            )?;
+            non_negative(
+                "detection_mode.threshold_multiplier",
+                settings.threshold_multiplier,
+            )?;
+            non_negative("detection_mode.min_residual", settings.min_residual)?;
+        }
+        if let RepairMode::Autoregressive { order, context } = self.repair_mode {
+            at_least("repair_mode.order", order, 1)?;
+            at_least("repair_mode.context", context, order)?;
+        }
+
+        if let Some(rumble) = &self.rumble {
+            positive("rumble.cutoff_hz", rumble.cutoff_hz)?;
+            within("rumble.cutoff_hz", rumble.cutoff_hz, 0.0, nyquist)?;
+            at_least("rumble.order", rumble.order, 1)?;
+            at_most("rumble.order", rumble.order, 8)?;
+        }
+        if let Some(decrackle) = &self.decrackle {
+            at_least("decrackle.median_length", decrackle.median_length, 1)?;
+            non_negative(
+                "decrackle.threshold_multiplier",
+                decrackle.threshold_multiplier,
+            )?;
+            non_negative("decrackle.min_amplitude", decrackle.min_amplitude)?;
+            within(
+                "decrackle.max_amplitude",
+                decrackle.max_amplitude,
+                decrackle.min_amplitude,
+                f32::MAX,
+            )?;
+            at_least("decrackle.block_length", decrackle.block_length, 1)?;
+            within("decrackle.min_density", decrackle.min_density, 0.0, 1.0)?;
+        }
+        if let Some(dehum) = &self.dehum {
+            if let HumFundamental::Fixed(frequency) = dehum.fundamental {
+                positive("dehum.fundamental", frequency)?;
+                within("dehum.fundamental", frequency, 0.0, nyquist)?;
+            }
+            at_least("dehum.harmonics", dehum.harmonics, 1)?;
+            non_negative("dehum.max_drift_hz", dehum.max_drift_hz)?;
+            positive("dehum.window_ms", dehum.window_ms)?;
+        }
+        if let Some(dehiss) = &self.dehiss {
+            non_negative("dehiss.over_subtraction", dehiss.over_subtraction)?;
+            non_negative("dehiss.max_reduction_db", dehiss.max_reduction_db)?;
+        }
+        if let Some(wow_flutter) = &self.wow_flutter {
+            if let ToneReference::Fixed(frequency) = wow_flutter.reference {
+                positive("wow_flutter.reference", frequency)?;
+                within("wow_flutter.reference", frequency, 0.0, nyquist)?;
+            }
+            positive(
+                "wow_flutter.max_deviation_percent",
+                wow_flutter.max_deviation_percent,
+            )?;
+            within(
+                "wow_flutter.max_deviation_percent",
+                wow_flutter.max_deviation_percent,
+                0.0,
+                50.0,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn positive(setting: &str, value: f32) -> Result<()> {
+    if value > 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(Error::invalid_config(
+            setting,
+            format!("must be greater than zero, got {value}"),
+        ))
+    }
+}
+
+pub(crate) fn non_negative(setting: &str, value: f32) -> Result<()> {
+    if value >= 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(Error::invalid_config(
+            setting,
+            format!("must not be negative, got {value}"),
+        ))
+    }
+}
+
+pub(crate) fn within(setting: &str, value: f32, low: f32, high: f32) -> Result<()> {
+    if (low..=high).contains(&value) {
+        Ok(())
+    } else if high == f32::MAX {
+        Err(Error::invalid_config(
+            setting,
+            format!("must be at least {low}, got {value}"),
+        ))
+    } else {
+        Err(Error::invalid_config(
+            setting,
+            format!("must be between {low} and {high}, got {value}"),
+        ))
+    }
+}
+
+pub(crate) fn at_least(setting: &str, value: usize, minimum: usize) -> Result<()> {
+    if value >= minimum {
+        Ok(())
+    } else {
+        Err(Error::invalid_config(
+            setting,
+            format!("must be at least {minimum}, got {value}"),
+        ))
+    }
+}
+
+pub(crate) fn at_most(setting: &str, value: usize, maximum: usize) -> Result<()> {
+    if value <= maximum {
+        Ok(())
+    } else {
+        Err(Error::invalid_config(
+            setting,
+            format!("must be at most {maximum}, got {value}"),
+        ))
+    }
 }
 
 impl Default for BaselineConfig {
     fn default() -> Self {
         Self {
+            sample_rate: 44_100,
+            rumble: None,
             target_peak: 0.95,
             impulse_threshold_multiplier: 6.0,
+            threshold_level: ThresholdLevel::default(),
             impulse_abs_min: 0.25,
             diff_threshold: 0.2,
             local_contrast_multiplier: 2.5,
+            channel_link_window_ms: 0.1,
+            max_click_duration_ms: 8.7,
+            region_growth_multiplier: 4.0,
+            detection_mode: DetectionMode::default(),
+            decrackle: None,
+            dehum: None,
+            dehiss: None,
+            wow_flutter: None,
+            repair_mode: RepairMode::default(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Summary of validation checks performed on a processed audio buffer.
 ///
 /// This is typically produced by [`validate_output`] and attached to
@@ -80,42 +525,78 @@ pub struct ValidationResult {
     /// in earlier processing stages, and downstream consumers should treat
     /// the output as invalid.
     pub has_nan: bool,
+    /// DC offset and sub-sonic level of the input, after normalization but
+    /// before the rumble filter.
+    ///
+    /// Only measured when the input levels were gathered with
+    /// [`SignalLevels::for_config`], as the pipeline itself does; zero
+    /// otherwise.
+    pub input_low_frequency: LowFrequencyLevels,
+    /// DC offset and sub-sonic level of the validated buffer.
+    pub output_low_frequency: LowFrequencyLevels,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Output of the baseline processing pipeline.
 ///
 /// This contains the normalized signal, information about detected impulse
 /// artifacts, a repaired version of the signal, and a [`ValidationResult`]
 /// that callers can inspect for numerical issues (clipping, `NaN`s, etc.).
 pub struct BaselineOutput {
-    /// Input signal after peak normalization using [`BaselineConfig::target_peak`].
+    /// Input signal after the rumble filter (if enabled) and peak
+    /// normalization using [`BaselineConfig::target_peak`].
     pub normalized: Vec<f32>,
-    /// Indices (in samples) where impulses/outliers were detected in the
-    /// normalized signal.
-    pub detected_impulses: Vec<usize>,
-    /// Signal after repairing/removing the detected impulses.
+    /// Impulses/outliers detected in the normalized signal, ordered by
+    /// position.
+    pub detected_impulses: Vec<ImpulseEvent>,
+    /// Detection threshold over the course of the signal, for plotting
+    /// alongside the waveform. Empty if the detector does not report one.
+    pub threshold_envelope: ThresholdEnvelope,
+    /// Signal after repairing/removing the detected impulses (and crackle,
+    /// hum and hiss, if those stages are enabled), resampled to correct wow
+    /// and flutter if [`WowFlutterConfig::correct`] is set.
     ///
     /// This is typically the buffer that downstream processing should use.
     pub repaired: Vec<f32>,
+    /// What the decrackle stage removed, if it ran.
+    pub decrackle: Option<DecrackleReport>,
+    /// What the hum removal stage removed, if it ran.
+    pub dehum: Option<DehumReport>,
+    /// What the hiss reduction stage removed, if it ran.
+    pub dehiss: Option<DehissReport>,
+    /// Speed variations measured on the restored signal, before any
+    /// correction; `None` if the stage did not run or found no tone to
+    /// track.
+    pub wow_flutter: Option<WowFlutterReport>,
     /// Validation metrics computed from the repaired signal.
     ///
     /// Callers should check this before trusting the output, in particular
     /// [`ValidationResult::has_nan`] and [`ValidationResult::clipped_samples`].
     pub validation: ValidationResult,
+    /// Confidence that the repaired signal is free of defects, for a
+    /// cleanliness meter.
+    pub cleanliness: Cleanliness,
 }
 
 /// Runs the baseline processing pipeline on a single-channel signal.
 ///
-/// This pipeline performs four main steps:
-/// 1. **Normalization** – Scales the input so that its peak amplitude matches
-///    `config.target_peak`.
+/// This pipeline performs seven main steps:
+/// 1. **Normalization** – Optionally removes rumble (`config.rumble`), then
+///    scales the input so that its peak amplitude matches `config.target_peak`.
 /// 2. **Impulse detection** – Identifies impulsive artifacts in the normalized
-///    signal using the thresholds defined in `BaselineConfig`.
+///    signal with the detector selected by `config.detection_mode`.
 /// 3. **Impulse repair** – Produces a repaired version of the signal where
 ///    detected impulses have been mitigated.
-/// 4. **Validation** – Computes basic quality metrics (such as peak level,
-///    clipped samples, and NaN presence) on the repaired signal.
+/// 4. **Restoration** – Optionally removes crackle (`config.decrackle`), hum
+///    (`config.dehum`) and then hiss (`config.dehiss`) from the repaired
+///    signal.
+/// 5. **Wow and flutter** – Optionally measures the speed variations of the
+///    restored signal (`config.wow_flutter`) and resamples it to remove them.
+/// 6. **Validation** – Computes basic quality metrics (such as peak level,
+///    clipped samples, NaN presence and DC offset) on the repaired signal.
+/// 7. **Cleanliness** – Runs the detector again on the repaired signal and
+///    scores how clean the result is likely to be.
 ///
 /// # Parameters
 /// - `input`: Input samples as a slice of `f32`, typically a mono
@@ -126,9 +607,13 @@ pub struct BaselineOutput {
 /// # Returns
 /// A [`BaselineOutput`] struct containing:
 /// - `normalized`: The normalized version of `input`.
-/// - `detected_impulses`: Indices of samples classified as impulses.
-/// - `repaired`: The signal after impulse repair.
+/// - `detected_impulses`: One [`ImpulseEvent`] per detected impulse.
+/// - `threshold_envelope`: The detection threshold applied along the signal.
+/// - `repaired`: The signal after impulse repair and restoration.
+/// - `decrackle`, `dehum`, `dehiss`: What the restoration stages removed.
+/// - `wow_flutter`: The measured speed variations.
 /// - `validation`: Summary metrics describing the repaired signal.
+/// - `cleanliness`: The confidence score of the result.
 ///
 /// # Examples
 /// ```
@@ -148,170 +633,801 @@ pub struct BaselineOutput {
 /// assert_eq!(output.repaired.len(), samples.len());
 /// ```
 pub fn run_baseline_pipeline(input: &[f32], config: &BaselineConfig) -> BaselineOutput {
-    let normalized = normalize(input, config.target_peak);
-    let detected_impulses = detect_impulses(&normalized, config);
-    let repaired = repair_impulses(&normalized, &detected_impulses);
-    let validation = validate_output(&repaired);
-
-    BaselineOutput {
-        normalized,
-        detected_impulses,
-        repaired,
-        validation,
-    }
+    Pipeline::new(config).run(input)
 }
 
-fn normalize(input: &[f32], target_peak: f32) -> Vec<f32> {
-    let peak = input
-        .iter()
-        .map(|sample| sample.abs())
-        .fold(0.0_f32, f32::max);
-
-    if peak <= 0.0 {
-        return input.to_vec();
-    }
-
-    let scale = target_peak / peak;
-    input.iter().map(|sample| sample * scale).collect()
+/// Runs the baseline pipeline like [`run_baseline_pipeline`], after checking
+/// the configuration and the input.
+///
+/// # Errors
+/// Returns the errors of [`BaselineConfig::validate`], and
+/// [`Error::NonFiniteInput`] if `input` holds a NaN or infinite sample.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::{try_run_pipeline, BaselineConfig, Error};
+///
+/// let config = BaselineConfig::default();
+/// let output = try_run_pipeline(&[0.0, 0.5, -0.4, 1.2], &config).unwrap();
+/// assert_eq!(output.repaired.len(), 4);
+///
+/// let error = try_run_pipeline(&[0.0, f32::NAN], &config).unwrap_err();
+/// assert!(matches!(error, Error::NonFiniteInput { index: 1, .. }));
+///
+/// let silent = BaselineConfig {
+///     target_peak: 0.0,
+///     ..BaselineConfig::default()
+/// };
+/// let error = try_run_pipeline(&[0.0, 0.5], &silent).unwrap_err();
+/// assert_eq!(
+///     error.to_string(),
+///     "invalid setting `target_peak`: must be greater than zero, got 0"
+/// );
+/// ```
+pub fn try_run_pipeline(input: &[f32], config: &BaselineConfig) -> Result<BaselineOutput> {
+    Pipeline::new(config).try_run(input)
 }
 
-/// Detects impulsive artifacts in the input signal using adaptive thresholding
-/// and local-contrast gating.
+#[derive(Debug, Clone)]
+/// A processing pipeline assembled from a pluggable [`ImpulseDetector`] and
+/// [`ImpulseRepairer`].
 ///
-/// This function identifies samples that stand out as impulses based on:
-/// - Absolute amplitude exceeding an adaptive threshold
-/// - Sample-to-sample difference exceeding a minimum delta
-/// - Local contrast relative to neighboring samples
-/// - Being a local peak compared to immediate neighbors
+/// [`Pipeline::new`] uses the stages selected by the [`BaselineConfig`] and
+/// behaves exactly like [`run_baseline_pipeline`];
+/// [`Pipeline::builder`] swaps in other stages. Normalization and validation
+/// are the same for every pipeline.
 ///
-/// # Limitations
-/// **Edge samples are excluded from detection**: The algorithm requires access to both
-/// previous and next samples for neighbor-based checks, so impulses at the first sample
-/// (index 0) or last sample (index `len - 1`) cannot be detected. Only indices from
-/// `1` to `len - 2`, inclusive, are candidates for impulse detection.
+/// The same pipeline can run on mono signals ([`Pipeline::run`]), planar
+/// multichannel signals ([`Pipeline::run_multichannel`]) and block by block
+/// ([`StreamingCleaner::with_pipeline`](crate::streaming::StreamingCleaner::with_pipeline)).
+/// Cloning is cheap: the stages are shared.
 ///
-/// # Parameters
-/// - `input`: The signal to analyze for impulses.
-/// - `config`: Configuration controlling detection thresholds and sensitivity.
+/// # Examples
+/// ```
+/// use vinyl_engine::pipeline::{BaselineConfig, Pipeline, RepairMode};
+/// use vinyl_engine::repair::BaselineRepairer;
 ///
-/// # Returns
-/// A vector of sample indices where impulses were detected, in ascending order.
-fn detect_impulses(input: &[f32], config: &BaselineConfig) -> Vec<usize> {
-    if input.is_empty() {
-        return Vec::new();
-    }
-
-    let mean_abs = input.iter().map(|sample| sample.abs()).sum::<f32>() / input.len() as f32;
-    let threshold = (mean_abs * config.impulse_threshold_multiplier).max(config.impulse_abs_min);
-    let mut impulses = Vec::new();
-
-    if input.len() < 3 {
-        return impulses;
-    }
-
-    for index in 1..input.len() - 1 {
-        let sample = input[index];
-        let prev = input[index - 1];
-        let next = input[index + 1];
-        let diff = (sample - prev).abs();
-        let local_mean = (prev.abs() + next.abs()) * 0.5;
-        let abs = sample.abs();
-        // The threshold is the larger of the adaptive threshold (mean_abs * impulse_threshold_multiplier)
-        // and the minimum absolute threshold (impulse_abs_min), ensuring detection is robust to both
-        // low-level signals and noise.
-        if abs >= threshold
-            && diff >= config.diff_threshold
-            && abs >= local_mean * config.local_contrast_multiplier
-            && abs >= prev.abs()
-            && abs >= next.abs()
+/// let config = BaselineConfig::default();
+/// let pipeline = Pipeline::builder(&config)
+///     .repairer(BaselineRepairer::new(RepairMode::autoregressive()))
+///     .build();
+///
+/// let mut samples = vec![0.1_f32; 2_000];
+/// samples[1_000] = 0.9;
+/// let output = pipeline.run(&samples);
+/// assert_eq!(output.detected_impulses[0].peak_index, 1_000);
+/// ```
+pub struct Pipeline {
+    config: BaselineConfig,
+    detector: Arc<dyn ImpulseDetector>,
+    repairer: Arc<dyn ImpulseRepairer>,
+}
+
+impl Pipeline {
+    /// Creates a pipeline with the default stages configured from `config`.
+    pub fn new(config: &BaselineConfig) -> Self {
+        Self::builder(config).build()
+    }
+
+    /// Starts building a pipeline around `config`.
+    ///
+    /// `config` provides the normalization and multichannel settings, and
+    /// configures whichever default stages are not replaced.
+    pub fn builder(config: &BaselineConfig) -> PipelineBuilder {
+        PipelineBuilder {
+            config: config.clone(),
+            detector: None,
+            repairer: None,
+        }
+    }
+
+    /// Configuration the pipeline was built with.
+    pub fn config(&self) -> &BaselineConfig {
+        &self.config
+    }
+
+    /// The detection stage.
+    pub fn detector(&self) -> &dyn ImpulseDetector {
+        self.detector.as_ref()
+    }
+
+    /// The repair stage.
+    pub fn repairer(&self) -> &dyn ImpulseRepairer {
+        self.repairer.as_ref()
+    }
+
+    /// Runs the pipeline on a single-channel signal.
+    ///
+    /// See [`run_baseline_pipeline`] for the processing steps.
+    pub fn run(&self, input: &[f32]) -> BaselineOutput {
+        self.run_monitored(input, &mut Monitor::silent())
+            .expect("a run without a cancellation token always finishes")
+    }
+
+    /// Runs the pipeline like [`Pipeline::run`], after checking the
+    /// configuration and the input.
+    ///
+    /// # Errors
+    /// See [`try_run_pipeline`].
+    pub fn try_run(&self, input: &[f32]) -> Result<BaselineOutput> {
+        self.config.validate()?;
+        check_finite(input, 0)?;
+        Ok(self.run(input))
+    }
+
+    /// Runs the pipeline like [`Pipeline::try_run`], reporting to `progress`
+    /// as it goes and stopping early if `cancel` is cancelled.
+    ///
+    /// The output is identical to [`Pipeline::run`]. See the
+    /// [`progress`](crate::progress) module for when progress is reported.
+    ///
+    /// # Errors
+    /// Returns the errors of [`Pipeline::try_run`], and [`Error::Cancelled`]
+    /// if the run was cancelled before it finished.
+    pub fn run_with_progress(
+        &self,
+        input: &[f32],
+        progress: &mut dyn ProgressSink,
+        cancel: &CancellationToken,
+    ) -> Result<BaselineOutput> {
+        self.config.validate()?;
+        check_finite(input, 0)?;
+        self.run_monitored(input, &mut Monitor::new(&self.config, progress, cancel))
+    }
+
+    fn run_monitored(&self, input: &[f32], monitor: &mut Monitor) -> Result<BaselineOutput> {
+        monitor.advance(Stage::Prepare, 0.0)?;
+        let (filtered, levels) = prepare_input(&self.config, input);
+        let normalized = normalize_to_peak(&filtered, levels.peak(), self.config.target_peak);
+        let context = DetectionContext {
+            levels: &levels,
+            gain: levels.gain(self.config.target_peak),
+            offset: 0,
+        };
+        let margin = self.context_margin();
+        let detection =
+            detect_in_blocks(self.detector(), &normalized, &context, margin, |fraction| {
+                monitor.advance(Stage::Detect, fraction)
+            })?;
+        let mut threshold_envelope = ThresholdEnvelope::new();
+        threshold_envelope.extend(&detection.threshold, 0);
+        let detected_impulses = detection.events;
+        let repaired = repair_in_blocks(
+            self.repairer(),
+            &normalized,
+            &detected_impulses,
+            margin,
+            |fraction| monitor.advance(Stage::Repair, fraction),
+        )?;
+        let mut restoration = Restoration::new(&self.config, &levels, context.gain);
+        let mut repaired =
+            restoration.apply_in_blocks(repaired, self.restoration_margin(), |fraction| {
+                monitor.advance(Stage::Restore, fraction)
+            })?;
+        monitor.advance(Stage::WowFlutter, 0.0)?;
+        let wow_flutter =
+            wow_tracker(&self.config, levels.tone_detector()).and_then(|mut tracker| {
+                tracker.accumulate(&repaired);
+                tracker.report()
+            });
+        if let Some(report) = wow_flutter
+            .as_ref()
+            .filter(|_| corrects_wow_flutter(&self.config))
         {
-            impulses.push(index);
+            repaired = correct_wow_flutter(&repaired, report);
         }
+        monitor.advance(Stage::Validate, 0.0)?;
+        let validation = validate_output(&repaired, &levels, context.gain, &self.config);
+        let residual =
+            detect_in_blocks(self.detector(), &repaired, &context, margin, |fraction| {
+                monitor.advance(Stage::Validate, fraction)
+            })?;
+        let cleanliness = cleanliness::assess(
+            &normalized,
+            &repaired,
+            &detected_impulses,
+            &detected_impulses,
+            &residual.events,
+            self.config.sample_rate,
+        );
+        monitor.finish();
+
+        Ok(BaselineOutput {
+            normalized,
+            detected_impulses,
+            threshold_envelope,
+            repaired,
+            decrackle: restoration.decrackle_report(),
+            dehum: restoration.dehum_report(),
+            dehiss: restoration.dehiss_report(),
+            wow_flutter,
+            validation,
+            cleanliness,
+        })
+    }
+
+    /// Number of samples of look-behind and look-ahead a block of the signal
+    /// needs for detection and repair inside it to match a whole-signal pass.
+    ///
+    /// An event reaches up to the detector's context from its peak, two
+    /// overlapping events are repaired as one, repair reads its context on
+    /// both sides, and the restoration stages read the repaired signal around
+    /// each sample; the margin covers all of that with room to spare.
+    pub(crate) fn context_margin(&self) -> usize {
+        2 * self.detector.context() + self.repairer.context() + self.restoration_margin()
     }
 
-    impulses
+    /// Number of samples of look-behind and look-ahead a block of the
+    /// repaired signal needs for the restoration stages inside it to match a
+    /// whole-signal pass.
+    pub(crate) fn restoration_margin(&self) -> usize {
+        let decrackle_context = self.config.decrackle.as_ref().map_or(0, decrackle::context);
+        let dehum_context = self.config.dehum.as_ref().map_or(0, |settings| {
+            dehum::context(settings, self.config.sample_rate)
+        });
+        let dehiss_context = self.config.dehiss.as_ref().map_or(0, dehiss::context);
+        decrackle_context + dehum_context + dehiss_context + 64
+    }
 }
 
-/// Repairs detected impulses by interpolating over them using surrounding samples.
+/// Number of samples the restoration stages process between two progress
+/// reports in [`Pipeline::run_with_progress`].
+const RESTORATION_BLOCK: usize = 1 << 16;
+
+/// Number of samples detection and repair process between two progress
+/// reports in [`Pipeline::run_with_progress`].
+const CLICK_BLOCK: usize = 1 << 16;
+
+/// Runs `detector` over `signal` [`CLICK_BLOCK`] samples at a time, each
+/// block with `margin` samples of context on either side, and calls `step`
+/// with the fraction done before every block.
 ///
-/// This function replaces impulse samples with interpolated values based on the nearest
-/// non-impulse samples on either side. Consecutive impulses are grouped together and
-/// treated as a single region to repair.
+/// The result matches a single pass over the whole signal as long as
+/// `margin` covers the detector's context, as
+/// [`Pipeline::context_margin`] does.
 ///
-/// # Parameters
-/// - `input`: The signal containing impulses to repair.
-/// - `impulses`: Indices of samples identified as impulses. **This vector will be sorted
-///   internally**, so the order of indices in the input is not preserved.
+/// # Errors
+/// Stops at the first error `step` returns.
+pub(crate) fn detect_in_blocks(
+    detector: &dyn ImpulseDetector,
+    signal: &[f32],
+    context: &DetectionContext,
+    margin: usize,
+    mut step: impl FnMut(f32) -> Result<()>,
+) -> Result<Detection> {
+    let length = signal.len();
+    if length <= CLICK_BLOCK {
+        step(0.0)?;
+        return Ok(detector.detect(signal, context));
+    }
+
+    let mut detection = Detection::default();
+    for start in (0..length).step_by(CLICK_BLOCK) {
+        step(start as f32 / length as f32)?;
+        let end = (start + CLICK_BLOCK).min(length);
+        let window_start = start.saturating_sub(margin);
+        let window_end = (end + margin).min(length);
+        let window_context = DetectionContext {
+            levels: context.levels,
+            gain: context.gain,
+            offset: context.offset + window_start,
+        };
+        let window = detector.detect(&signal[window_start..window_end], &window_context);
+        let block = start - window_start..end - window_start;
+        detection.events.extend(
+            window
+                .events
+                .into_iter()
+                .filter(|event| block.contains(&event.peak_index))
+                .map(|event| event.shifted(window_start)),
+        );
+        if let Some(threshold) = window.threshold.get(block) {
+            detection.threshold.extend_from_slice(threshold);
+        }
+    }
+    Ok(detection)
+}
+
+/// Repairs `impulses` in `signal` with `repairer` [`CLICK_BLOCK`] samples at
+/// a time, each block with `margin` samples of context on either side, and
+/// calls `step` with the fraction done before every block.
 ///
-/// # Returns
-/// A new signal with impulses replaced by linear interpolation between the nearest
-/// non-impulse samples on either side of each impulse or impulse group.
-///
-/// # Implementation Details
-/// - Consecutive impulses are grouped into regions and repaired as a unit.
-/// - Interpolation uses linear blending between the samples immediately before and after
-///   each impulse region.
-/// - The function handles edge cases where impulses are near the signal boundaries by
-///   using `saturating_sub` and `min` to clamp indices.
-fn repair_impulses(input: &[f32], impulses: &[usize]) -> Vec<f32> {
-    if impulses.is_empty() {
-        return input.to_vec();
+/// The result matches a single pass over the whole signal as long as
+/// `margin` covers the detector's and the repairer's context, as
+/// [`Pipeline::context_margin`] does.
+///
+/// # Errors
+/// Stops at the first error `step` returns.
+pub(crate) fn repair_in_blocks(
+    repairer: &dyn ImpulseRepairer,
+    signal: &[f32],
+    impulses: &[ImpulseEvent],
+    margin: usize,
+    mut step: impl FnMut(f32) -> Result<()>,
+) -> Result<Vec<f32>> {
+    let length = signal.len();
+    if length <= CLICK_BLOCK {
+        step(0.0)?;
+        return Ok(repairer.repair(signal, impulses));
+    }
+
+    let mut repaired = Vec::with_capacity(length);
+    for start in (0..length).step_by(CLICK_BLOCK) {
+        step(start as f32 / length as f32)?;
+        let end = (start + CLICK_BLOCK).min(length);
+        let window_start = start.saturating_sub(margin);
+        let window_end = (end + margin).min(length);
+        let window_impulses: Vec<ImpulseEvent> = impulses
+            .iter()
+            .filter(|event| event.start >= window_start && event.end <= window_end)
+            .map(|event| ImpulseEvent {
+                start: event.start - window_start,
+                end: event.end - window_start,
+                peak_index: event.peak_index - window_start,
+                ..*event
+            })
+            .collect();
+        let window = repairer.repair(&signal[window_start..window_end], &window_impulses);
+        repaired.extend_from_slice(&window[start - window_start..end - window_start]);
     }
+    Ok(repaired)
+}
+
+#[derive(Debug, Clone)]
+/// The stages that follow click repair, set up for one signal, together with
+/// what they have removed so far.
+pub(crate) struct Restoration {
+    decrackle: Option<(DecrackleConfig, DecrackleReport)>,
+    dehum: Option<(Dehummer, DehumReport)>,
+    dehiss: Option<(Dehisser, DehissReport)>,
+}
 
-    let mut repaired = input.to_vec();
-    let mut sorted = impulses.to_vec();
-    sorted.sort_unstable();
+impl Restoration {
+    /// Sets up the stages enabled in `config` for a signal with the given
+    /// whole-signal `levels`, normalized with `gain`.
+    ///
+    /// # Panics
+    /// Panics if hiss reduction or hum removal with
+    /// [`HumFundamental::Auto`] is enabled but `levels` did not gather what
+    /// they need (see [`SignalLevels::for_config`]).
+    pub(crate) fn new(config: &BaselineConfig, levels: &SignalLevels, gain: f32) -> Self {
+        let dehum = config.dehum.as_ref().map(|settings| {
+            let fundamental = match settings.fundamental {
+                HumFundamental::Fixed(frequency) => Some(frequency),
+                HumFundamental::Auto => levels
+                    .hum
+                    .as_ref()
+                    .expect("hum detection needs the whole signal: gather the levels with SignalLevels::for_config")
+                    .fundamental(),
+            };
+            let report = DehumReport {
+                fundamental_hz: fundamental,
+                ..DehumReport::default()
+            };
+            (Dehummer::new(settings, fundamental, config.sample_rate), report)
+        });
+        let dehiss = config.dehiss.as_ref().map(|settings| {
+            let profile = levels.noise_profile().expect(
+                "hiss reduction needs a noise profile: gather the levels with SignalLevels::for_config",
+            );
+            let report = DehissReport {
+                noise_frames: profile.frames,
+                ..DehissReport::default()
+            };
+            (Dehisser::new(settings, &profile, gain), report)
+        });
+        Self {
+            decrackle: config
+                .decrackle
+                .map(|settings| (settings, DecrackleReport::default())),
+            dehum,
+            dehiss,
+        }
+    }
 
-    let mut start = 0;
-    while start < sorted.len() {
-        let mut end = start;
-        while end + 1 < sorted.len() && sorted[end + 1] == sorted[end] + 1 {
-            end += 1;
+    /// Runs the stages on a complete signal [`RESTORATION_BLOCK`] samples at
+    /// a time, each with `margin` samples of context on either side, and
+    /// calls `step` with the fraction done before every block. The result
+    /// matches a single pass over the whole signal.
+    ///
+    /// # Errors
+    /// Stops at the first error `step` returns.
+    pub(crate) fn apply_in_blocks(
+        &mut self,
+        repaired: Vec<f32>,
+        margin: usize,
+        mut step: impl FnMut(f32) -> Result<()>,
+    ) -> Result<Vec<f32>> {
+        if self.decrackle.is_none() && self.dehum.is_none() && self.dehiss.is_none() {
+            return Ok(repaired);
         }
 
-        let left_index = sorted[start].saturating_sub(1);
-        let right_index = (sorted[end] + 1).min(input.len() - 1);
-        let left_value = input[left_index];
-        let right_value = input[right_index];
-        let span = (right_index - left_index) as f32;
+        let length = repaired.len();
+        let mut restored = Vec::with_capacity(length);
+        while restored.len() < length {
+            step(restored.len() as f32 / length as f32)?;
+            let start = restored.len();
+            let end = (start + RESTORATION_BLOCK).min(length);
+            let window_start = start.saturating_sub(margin);
+            let window_end = (end + margin).min(length);
+            let block = start - window_start..end - window_start;
+            let window = self.apply(
+                repaired[window_start..window_end].to_vec(),
+                window_start,
+                block.clone(),
+            );
+            restored.extend_from_slice(&window[block]);
+        }
+        Ok(restored)
+    }
 
-        // Guard against edge case where right_index <= left_index, which would cause
-        // right_index - 1 to underflow in the range expression below. This can occur
-        // when repairing impulses at signal boundaries in very short signals.
-        if right_index > left_index + 1 {
-            for (offset, index) in (left_index + 1..=right_index - 1).enumerate() {
-                let t = (offset + 1) as f32 / span;
-                repaired[index] = left_value + (right_value - left_value) * t;
-            }
+    /// Runs the stages on `repaired`, whose first sample is sample `offset`
+    /// of the complete signal, and adds the samples in `report` to the
+    /// reports.
+    pub(crate) fn apply(
+        &mut self,
+        repaired: Vec<f32>,
+        offset: usize,
+        report: Range<usize>,
+    ) -> Vec<f32> {
+        let mut signal = repaired;
+        if let Some((settings, totals)) = &mut self.decrackle {
+            let decrackled = decrackle::decrackle(&signal, offset, settings);
+            totals.accumulate(&signal[report.clone()], &decrackled[report.clone()]);
+            signal = decrackled;
+        }
+        if let Some((dehummer, totals)) = &mut self.dehum {
+            let dehummed = dehummer.process(&signal, offset);
+            totals.accumulate(&signal[report.clone()], &dehummed[report.clone()]);
+            signal = dehummed;
         }
+        if let Some((dehisser, totals)) = &mut self.dehiss {
+            let dehissed = dehisser.process(&signal, offset);
+            totals.accumulate(&signal[report.clone()], &dehissed[report.clone()]);
+            signal = dehissed;
+        }
+        signal
+    }
+
+    pub(crate) fn decrackle_report(&self) -> Option<DecrackleReport> {
+        self.decrackle.map(|(_, report)| report)
+    }
+
+    pub(crate) fn dehum_report(&self) -> Option<DehumReport> {
+        self.dehum.as_ref().map(|(_, report)| *report)
+    }
 
-        start = end + 1;
+    pub(crate) fn dehiss_report(&self) -> Option<DehissReport> {
+        self.dehiss.as_ref().map(|(_, report)| *report)
     }
+}
 
-    repaired
+#[derive(Debug, Clone)]
+/// Builder for a [`Pipeline`], created by [`Pipeline::builder`].
+///
+/// Stages that are not set explicitly default to the detector selected by
+/// [`BaselineConfig::detection_mode`] and to [`BaselineRepairer`], configured
+/// from the builder's [`BaselineConfig`].
+pub struct PipelineBuilder {
+    config: BaselineConfig,
+    detector: Option<Arc<dyn ImpulseDetector>>,
+    repairer: Option<Arc<dyn ImpulseRepairer>>,
 }
 
-fn validate_output(output: &[f32]) -> ValidationResult {
-    let mut peak = 0.0_f32;
-    let mut clipped_samples = 0;
-    let mut has_nan = false;
+impl PipelineBuilder {
+    /// Uses `detector` to find impulses.
+    pub fn detector(mut self, detector: impl ImpulseDetector + 'static) -> Self {
+        self.detector = Some(Arc::new(detector));
+        self
+    }
+
+    /// Uses `repairer` to fill in detected impulses.
+    pub fn repairer(mut self, repairer: impl ImpulseRepairer + 'static) -> Self {
+        self.repairer = Some(Arc::new(repairer));
+        self
+    }
+
+    /// Assembles the pipeline.
+    pub fn build(self) -> Pipeline {
+        let detector = self
+            .detector
+            .unwrap_or_else(|| match self.config.detection_mode {
+                DetectionMode::Threshold => Arc::new(BaselineDetector::new(&self.config)),
+                DetectionMode::Residual(residual) => Arc::new(ResidualDetector::new(
+                    residual,
+                    self.config.max_click_width(),
+                )),
+            });
+        let repairer = self
+            .repairer
+            .unwrap_or_else(|| Arc::new(BaselineRepairer::new(self.config.repair_mode)));
 
-    for sample in output {
-        if sample.is_nan() {
-            has_nan = true;
+        Pipeline {
+            config: self.config,
+            detector,
+            repairer,
         }
-        let abs = sample.abs();
-        if abs > peak {
-            peak = abs;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Whole-signal level statistics the pipeline needs before it can process any
+/// sample: the absolute peak (for normalization), the mean absolute level (for
+/// the impulse detection threshold) and the mean absolute sample-to-sample
+/// difference (for deciding how far each click extends). Levels created with
+/// [`SignalLevels::for_config`] measure the signal after the rumble filter,
+/// and also learn the noise profile for hiss reduction, the reference tone for
+/// wow and flutter measurement and the low-frequency content of the
+/// unfiltered input.
+///
+/// [`run_baseline_pipeline`] computes these itself and passes them to the
+/// detector through a [`DetectionContext`]. Streaming callers build
+/// them with a cheap first pass over the file using [`SignalLevels::accumulate`]
+/// and hand them to [`StreamingCleaner`](crate::streaming::StreamingCleaner),
+/// which then reproduces the offline result exactly.
+pub struct SignalLevels {
+    peak: f32,
+    abs_sum: f64,
+    abs_diff_sum: f64,
+    last_sample: Option<f32>,
+    samples: usize,
+    noise: Option<NoiseProfiler>,
+    hum: Option<HumDetector>,
+    tone: Option<ToneDetector>,
+    rumble: Option<RumbleFilter>,
+    input_low_frequency: Option<LowFrequencyMeter>,
+}
+
+impl SignalLevels {
+    /// Creates empty statistics, ready for [`SignalLevels::accumulate`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates empty statistics for the pipeline that `config` describes.
+    ///
+    /// Blocks are passed through the rumble filter, when
+    /// [`BaselineConfig::rumble`] is set, before they are measured; the
+    /// filter always runs with [`FilterPhase::MinimumPhase`] here, as in
+    /// [`StreamingCleaner`](crate::StreamingCleaner). The statistics also
+    /// gather the noise profile, when [`BaselineConfig::dehiss`] is set, the
+    /// mains frequency, when [`BaselineConfig::dehum`] detects it
+    /// automatically, the reference tone, when
+    /// [`BaselineConfig::wow_flutter`] picks it automatically, and the
+    /// low-frequency content of the unfiltered input.
+    ///
+    /// [`FilterPhase::MinimumPhase`]: crate::rumble::FilterPhase::MinimumPhase
+    pub fn for_config(config: &BaselineConfig) -> Self {
+        Self {
+            noise: config.dehiss.as_ref().map(NoiseProfiler::new),
+            hum: config
+                .dehum
+                .as_ref()
+                .filter(|settings| settings.fundamental == HumFundamental::Auto)
+                .map(|settings| HumDetector::new(settings, config.sample_rate)),
+            tone: config
+                .wow_flutter
+                .as_ref()
+                .filter(|settings| settings.reference == ToneReference::Auto)
+                .map(|_| ToneDetector::new(config.sample_rate)),
+            rumble: config
+                .rumble
+                .as_ref()
+                .map(|settings| RumbleFilter::new(settings, config.sample_rate)),
+            input_low_frequency: Some(LowFrequencyMeter::new(config.sample_rate)),
+            ..Self::default()
+        }
+    }
+
+    /// Computes the statistics of a complete signal.
+    pub fn scan(input: &[f32]) -> Self {
+        let mut levels = Self::new();
+        levels.accumulate(input);
+        levels
+    }
+
+    /// Adds a block of samples to the statistics.
+    ///
+    /// Blocks must be passed in signal order for the result to match
+    /// [`SignalLevels::scan`] on the concatenated signal bit for bit.
+    pub fn accumulate(&mut self, block: &[f32]) {
+        if let Some(meter) = &mut self.input_low_frequency {
+            meter.accumulate(block);
+        }
+        match &mut self.rumble {
+            Some(filter) => {
+                let mut filtered = block.to_vec();
+                filter.process(&mut filtered);
+                self.measure(&filtered);
+            }
+            None => self.measure(block),
+        }
+    }
+
+    fn measure(&mut self, block: &[f32]) {
+        for &sample in block {
+            let abs = sample.abs();
+            self.peak = self.peak.max(abs);
+            self.abs_sum += f64::from(abs);
+            if let Some(last) = self.last_sample {
+                self.abs_diff_sum += f64::from((sample - last).abs());
+            }
+            self.last_sample = Some(sample);
+        }
+        self.samples += block.len();
+        if let Some(noise) = &mut self.noise {
+            noise.accumulate(block);
+        }
+        if let Some(hum) = &mut self.hum {
+            hum.accumulate(block);
         }
-        if abs > 1.0 {
-            clipped_samples += 1;
+        if let Some(tone) = &mut self.tone {
+            tone.accumulate(block);
         }
     }
 
-    ValidationResult {
-        peak,
-        clipped_samples,
-        has_nan,
+    /// Maximum absolute sample value seen so far.
+    pub fn peak(&self) -> f32 {
+        self.peak
+    }
+
+    /// Mean absolute sample value seen so far, or `0.0` for an empty signal.
+    pub fn mean_abs(&self) -> f32 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            (self.abs_sum / self.samples as f64) as f32
+        }
+    }
+
+    /// Mean absolute difference between consecutive samples seen so far, or
+    /// `0.0` for signals shorter than two samples.
+    pub fn mean_abs_diff(&self) -> f32 {
+        if self.samples < 2 {
+            0.0
+        } else {
+            (self.abs_diff_sum / (self.samples - 1) as f64) as f32
+        }
+    }
+
+    /// Number of samples accumulated.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Noise profile learned so far, at the level of the input signal, or
+    /// `None` if the levels were not created for hiss reduction.
+    pub fn noise_profile(&self) -> Option<NoiseProfile> {
+        self.noise.as_ref().map(NoiseProfiler::profile)
+    }
+
+    /// Mains hum frequency detected so far, or `None` if no hum stands out or
+    /// the levels were not created for automatic hum detection.
+    pub fn hum_fundamental(&self) -> Option<f32> {
+        self.hum.as_ref().and_then(HumDetector::fundamental)
+    }
+
+    /// Frequency of the tone that wow and flutter are measured on, found so
+    /// far, or `None` if no tone stands out or the levels were not created
+    /// for picking it automatically.
+    pub fn tone_reference(&self) -> Option<f32> {
+        self.tone.as_ref().and_then(ToneDetector::reference)
+    }
+
+    /// DC offset and sub-sonic level of the input before the rumble filter,
+    /// or `None` if the levels were not created with
+    /// [`SignalLevels::for_config`].
+    pub fn input_low_frequency(&self) -> Option<LowFrequencyLevels> {
+        self.input_low_frequency
+            .as_ref()
+            .map(LowFrequencyMeter::levels)
+    }
+
+    pub(crate) fn tone_detector(&self) -> Option<&ToneDetector> {
+        self.tone.as_ref()
+    }
+
+    /// Gain that normalization applies to reach `target_peak`.
+    pub(crate) fn gain(&self, target_peak: f32) -> f32 {
+        if self.peak <= 0.0 {
+            1.0
+        } else {
+            target_peak / self.peak
+        }
+    }
+}
+
+/// Runs the rumble filter over a complete input, with the phase response the
+/// configuration asks for, and measures the result like
+/// [`SignalLevels::for_config`] does.
+pub(crate) fn prepare_input(config: &BaselineConfig, input: &[f32]) -> (Vec<f32>, SignalLevels) {
+    let mut levels = SignalLevels::for_config(config);
+    let Some(settings) = &config.rumble else {
+        levels.accumulate(input);
+        return (input.to_vec(), levels);
+    };
+
+    let filtered = rumble::filter(input, settings, config.sample_rate);
+    if let Some(meter) = &mut levels.input_low_frequency {
+        meter.accumulate(input);
+    }
+    levels.measure(&filtered);
+    (filtered, levels)
+}
+
+/// Sets up wow and flutter measurement, if `config` enables it, tracking the
+/// tone that `tone` found for [`ToneReference::Auto`]. Returns `None` if the
+/// stage is disabled or there is no tone to track.
+///
+/// # Panics
+/// Panics if the reference is [`ToneReference::Auto`] and `tone` is `None`.
+pub(crate) fn wow_tracker(
+    config: &BaselineConfig,
+    tone: Option<&ToneDetector>,
+) -> Option<WowTracker> {
+    let settings = config.wow_flutter.as_ref()?;
+    let reference = match settings.reference {
+        ToneReference::Fixed(frequency) => frequency,
+        ToneReference::Auto => tone
+            .expect("finding the reference tone needs the whole signal: gather the levels with SignalLevels::for_config")
+            .reference()?,
+    };
+    Some(WowTracker::new(reference, settings, config.sample_rate))
+}
+
+/// Whether `config` asks for the measured wow and flutter to be corrected.
+pub(crate) fn corrects_wow_flutter(config: &BaselineConfig) -> bool {
+    config
+        .wow_flutter
+        .as_ref()
+        .is_some_and(|settings| settings.correct)
+}
+
+/// Scales `input` by `target_peak / peak`, where `peak` may have been measured
+/// over a larger signal (e.g. all channels of a stereo file) so that several
+/// buffers share one gain.
+pub(crate) fn normalize_to_peak(input: &[f32], peak: f32, target_peak: f32) -> Vec<f32> {
+    if peak <= 0.0 {
+        return input.to_vec();
+    }
+
+    let scale = target_peak / peak;
+    input.iter().map(|sample| sample * scale).collect()
+}
+
+/// Validates a complete output signal that was normalized with `gain` from
+/// an input with the given `levels`.
+pub(crate) fn validate_output(
+    output: &[f32],
+    levels: &SignalLevels,
+    gain: f32,
+    config: &BaselineConfig,
+) -> ValidationResult {
+    let mut validation = ValidationResult::default();
+    validation.accumulate(output);
+    validation.input_low_frequency = levels
+        .input_low_frequency()
+        .unwrap_or_default()
+        .scaled(gain);
+    validation.output_low_frequency = LowFrequencyMeter::measure(output, config.sample_rate);
+    validation
+}
+
+impl ValidationResult {
+    /// Folds a further block of output samples into the result, so that
+    /// block-wise validation matches validating the concatenated signal.
+    pub(crate) fn accumulate(&mut self, block: &[f32]) {
+        for sample in block {
+            if sample.is_nan() {
+                self.has_nan = true;
+            }
+            let abs = sample.abs();
+            if abs > self.peak {
+                self.peak = abs;
+            }
+            if abs > 1.0 {
+                self.clipped_samples += 1;
+            }
+        }
     }
 }
@@ -0,0 +1,453 @@
+//! Named starting points for [`BaselineConfig`].
+//!
+//! Most users never see a threshold. They pick how hard the cleaner should
+//! work ([`Aggressiveness`]) or what kind of record they are transferring,
+//! and the preset sets the detector thresholds, the repair mode and the
+//! optional stages consistently. The [`PresetRegistry`] lists the built-in
+//! presets under stable ids, so front ends can offer them by name and store
+//! the user's choice.
+//!
+//! Presets are configured for 44.1 kHz; set [`BaselineConfig::sample_rate`]
+//! to the rate of the material afterwards. Every setting that depends on
+//! it is given in physical units, so nothing else needs to change.
+
+use crate::decrackle::DecrackleConfig;
+use crate::dehiss::DehissConfig;
+use crate::dehum::DehumConfig;
+use crate::pipeline::{BaselineConfig, RepairMode};
+use crate::rumble::RumbleConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// How readily the cleaner treats something as a defect, trading precision
+/// (leaving the music alone) for recall (catching every click).
+pub enum Aggressiveness {
+    /// Only clear, loud clicks are repaired; crackle is left alone. For
+    /// clean pressings and material with many sharp transients.
+    Low,
+    #[default]
+    /// Suits most records.
+    Standard,
+    /// Quiet clicks and heavier crackle are removed too, at the risk of
+    /// softening some sharp musical attacks. For worn records.
+    High,
+}
+
+impl Aggressiveness {
+    /// The preset with this aggressiveness.
+    pub fn config(self) -> BaselineConfig {
+        match self {
+            Aggressiveness::Low => BaselineConfig::low(),
+            Aggressiveness::Standard => BaselineConfig::standard(),
+            Aggressiveness::High => BaselineConfig::high(),
+        }
+    }
+}
+
+impl BaselineConfig {
+    /// [`Aggressiveness::Low`]: a higher amplitude floor and stricter step
+    /// and contrast gates, autoregressive repair and a rumble filter; no
+    /// crackle removal.
+    pub fn low() -> Self {
+        Self {
+            impulse_abs_min: 0.4,
+            diff_threshold: 0.3,
+            local_contrast_multiplier: 3.5,
+            decrackle: None,
+            ..Self::standard()
+        }
+    }
+
+    /// [`Aggressiveness::Standard`]: the default detection gates with
+    /// autoregressive repair, crackle removal and a rumble filter.
+    ///
+    /// [`BaselineConfig::default`] keeps every optional stage off; this is
+    /// the configuration a front end should start from.
+    pub fn standard() -> Self {
+        Self {
+            rumble: Some(RumbleConfig::belt_drive()),
+            decrackle: Some(DecrackleConfig::default()),
+            repair_mode: RepairMode::autoregressive(),
+            ..Self::default()
+        }
+    }
+
+    /// [`Aggressiveness::High`]: looser detection gates, longer clicks and
+    /// more sensitive crackle removal.
+    pub fn high() -> Self {
+        Self {
+            impulse_threshold_multiplier: 4.5,
+            impulse_abs_min: 0.15,
+            diff_threshold: 0.12,
+            local_contrast_multiplier: 2.0,
+            max_click_duration_ms: 12.0,
+            decrackle: Some(DecrackleConfig {
+                threshold_multiplier: 3.0,
+                min_density: 0.001,
+                ..DecrackleConfig::default()
+            }),
+            ..Self::standard()
+        }
+    }
+
+    /// Worn records and 78s: [`Aggressiveness::High`] cleaning with gentle
+    /// hiss reduction that keeps the warmth of the recording.
+    pub fn warm_vinyl() -> Self {
+        Self {
+            dehiss: Some(DehissConfig {
+                max_reduction_db: 6.0,
+                ..DehissConfig::default()
+            }),
+            ..Self::high()
+        }
+    }
+
+    /// Recent, clean pressings: [`Aggressiveness::Low`] cleaning without a
+    /// rumble filter, so the low bass of a modern cut is left untouched.
+    pub fn modern_reissue() -> Self {
+        Self {
+            rumble: None,
+            ..Self::low()
+        }
+    }
+
+    /// Audience and desk recordings: [`Aggressiveness::Standard`] cleaning
+    /// plus hum and hiss removal.
+    pub fn live_bootleg() -> Self {
+        Self {
+            dehum: Some(DehumConfig::default()),
+            dehiss: Some(DehissConfig::default()),
+            ..Self::standard()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A named configuration.
+pub struct Preset {
+    /// Stable identifier, e.g. `"warm-vinyl"`, for storing a choice.
+    pub id: String,
+    /// Name to show to users, e.g. `"Warm vinyl"`.
+    pub name: String,
+    /// One sentence on what the preset is for.
+    pub description: String,
+    /// The configuration the preset stands for.
+    pub config: BaselineConfig,
+}
+
+impl Preset {
+    /// Creates a preset.
+    pub fn new(id: &str, name: &str, description: &str, config: BaselineConfig) -> Self {
+        Self {
+            id: id.to_owned(),
+            name: name.to_owned(),
+            description: description.to_owned(),
+            config,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// An ordered collection of presets, looked up by id.
+///
+/// [`PresetRegistry::default`] holds the built-in presets: the three
+/// [`Aggressiveness`] levels followed by the record profiles.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::preset::PresetRegistry;
+/// use vinyl_engine::BaselineConfig;
+///
+/// let registry = PresetRegistry::default();
+/// let preset = registry.get("warm-vinyl").unwrap();
+/// assert_eq!(preset.name, "Warm vinyl");
+///
+/// let config = BaselineConfig {
+///     sample_rate: 96_000,
+///     ..preset.config.clone()
+/// };
+/// assert_eq!(config.max_click_width(), 1_152);
+/// ```
+pub struct PresetRegistry {
+    presets: Vec<Preset>,
+}
+
+impl PresetRegistry {
+    /// A registry without any presets.
+    pub fn empty() -> Self {
+        Self {
+            presets: Vec::new(),
+        }
+    }
+
+    /// The preset with the given id, if any.
+    pub fn get(&self, id: &str) -> Option<&Preset> {
+        self.presets.iter().find(|preset| preset.id == id)
+    }
+
+    /// The presets in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = &Preset> {
+        self.presets.iter()
+    }
+
+    /// Adds `preset` at the end, or replaces the preset with the same id in
+    /// place.
+    pub fn insert(&mut self, preset: Preset) {
+        match self.presets.iter_mut().find(|known| known.id == preset.id) {
+            Some(known) => *known = preset,
+            None => self.presets.push(preset),
+        }
+    }
+}
+
+impl Default for PresetRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        for preset in [
+            Preset::new(
+                "low",
+                "Low",
+                "Repairs only clear, loud clicks.",
+                BaselineConfig::low(),
+            ),
+            Preset::new(
+                "standard",
+                "Standard",
+                "Balanced cleaning for most records.",
+                BaselineConfig::standard(),
+            ),
+            Preset::new(
+                "high",
+                "High",
+                "Removes quiet clicks and heavy crackle from worn records.",
+                BaselineConfig::high(),
+            ),
+            Preset::new(
+                "warm-vinyl",
+                "Warm vinyl",
+                "Thorough cleaning with gentle hiss reduction for old records and 78s.",
+                BaselineConfig::warm_vinyl(),
+            ),
+            Preset::new(
+                "modern-reissue",
+                "Modern reissue",
+                "Light-touch cleaning for clean, recent pressings.",
+                BaselineConfig::modern_reissue(),
+            ),
+            Preset::new(
+                "live-bootleg",
+                "Live bootleg",
+                "Click, hum and hiss removal for audience and desk recordings.",
+                BaselineConfig::live_bootleg(),
+            ),
+        ] {
+            registry.insert(preset);
+        }
+        registry
+    }
+}
+
+#[cfg(feature = "serde")]
+mod toml_files {
+    use std::fmt;
+    use std::io;
+    use std::path::Path;
+
+    use serde::Deserialize;
+
+    use super::{Preset, PresetRegistry};
+    use crate::pipeline::BaselineConfig;
+
+    #[derive(Debug)]
+    /// Why presets could not be loaded from TOML.
+    pub enum PresetError {
+        /// The file could not be read.
+        Io(io::Error),
+        /// The text is not valid TOML, or does not describe presets.
+        Toml(toml::de::Error),
+        /// A preset names a base that is neither registered nor defined
+        /// earlier in the file.
+        UnknownBase {
+            /// Id of the preset.
+            preset: String,
+            /// The base it names.
+            base: String,
+        },
+    }
+
+    impl fmt::Display for PresetError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                PresetError::Io(error) => write!(f, "cannot read presets: {error}"),
+                PresetError::Toml(error) => write!(f, "invalid preset file: {error}"),
+                PresetError::UnknownBase { preset, base } => {
+                    write!(
+                        f,
+                        "preset \"{preset}\" is based on unknown preset \"{base}\""
+                    )
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for PresetError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                PresetError::Io(error) => Some(error),
+                PresetError::Toml(error) => Some(error),
+                PresetError::UnknownBase { .. } => None,
+            }
+        }
+    }
+
+    /// Settings of [`BaselineConfig`] that hold an optional stage, which a
+    /// preset file switches off with `false`.
+    const OPTIONAL_STAGES: [&str; 5] = ["rumble", "decrackle", "dehum", "dehiss", "wow_flutter"];
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct PresetFile {
+        #[serde(default)]
+        preset: Vec<PresetEntry>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct PresetEntry {
+        id: String,
+        name: String,
+        #[serde(default)]
+        description: String,
+        base: Option<String>,
+        #[serde(default)]
+        config: toml::Table,
+    }
+
+    impl PresetRegistry {
+        /// Adds the presets of a TOML document, replacing registered
+        /// presets with the same id.
+        ///
+        /// Each `[[preset]]` table has an `id`, a `name`, an optional
+        /// `description` and a `[preset.config]` table with the settings of
+        /// [`BaselineConfig`], under their field names. With
+        /// `base = "<id>"`, the settings that the table leaves out are taken
+        /// from that preset, registered or defined earlier in the document;
+        /// without, from [`BaselineConfig::default`]. A stage's table only
+        /// needs the settings it changes, while a setting that names another
+        /// variant, such as `[preset.config.dehiss.noise_source.lead_in]`,
+        /// replaces the base's value as a whole.
+        ///
+        /// TOML has no null, so an optional stage that the base enables is
+        /// switched off with `false`, e.g. `decrackle = false`. This works
+        /// for `rumble`, `decrackle`, `dehum`, `dehiss` and `wow_flutter`.
+        ///
+        /// Nothing is added if the document has an error.
+        ///
+        /// # Returns
+        /// The number of presets in the document.
+        ///
+        /// # Errors
+        /// Returns [`PresetError::Toml`] for invalid TOML, unknown fields and
+        /// settings of the wrong type, and [`PresetError::UnknownBase`] for a
+        /// base that cannot be found.
+        ///
+        /// # Examples
+        /// ```
+        /// use vinyl_engine::preset::PresetRegistry;
+        ///
+        /// let mut registry = PresetRegistry::default();
+        /// registry.load_toml(r#"
+        ///     [[preset]]
+        ///     id = "shellac"
+        ///     name = "Shellac 78s"
+        ///     base = "warm-vinyl"
+        ///
+        ///     [preset.config]
+        ///     max_click_duration_ms = 15.0
+        ///     rumble = { cutoff_hz = 40.0, order = 4, phase = "zero_phase" }
+        /// "#).unwrap();
+        ///
+        /// let shellac = &registry.get("shellac").unwrap().config;
+        /// let warm = &registry.get("warm-vinyl").unwrap().config;
+        /// assert_eq!(shellac.max_click_duration_ms, 15.0);
+        /// assert_eq!(shellac.dehiss, warm.dehiss);
+        /// ```
+        pub fn load_toml(&mut self, source: &str) -> Result<usize, PresetError> {
+            let file: PresetFile = toml::from_str(source).map_err(PresetError::Toml)?;
+            let mut loaded = self.clone();
+            for entry in &file.preset {
+                let mut settings = match &entry.base {
+                    Some(base) => {
+                        let preset = loaded.get(base).ok_or_else(|| PresetError::UnknownBase {
+                            preset: entry.id.clone(),
+                            base: base.clone(),
+                        })?;
+                        toml::Table::try_from(&preset.config)
+                            .expect("a configuration always serializes")
+                    }
+                    None => toml::Table::new(),
+                };
+                let mut overrides = entry.config.clone();
+                for stage in OPTIONAL_STAGES {
+                    if overrides.get(stage) == Some(&toml::Value::Boolean(false)) {
+                        overrides.remove(stage);
+                        settings.remove(stage);
+                    }
+                }
+                merge(&mut settings, &overrides);
+                let config = BaselineConfig::deserialize(settings).map_err(PresetError::Toml)?;
+                loaded.insert(Preset::new(
+                    &entry.id,
+                    &entry.name,
+                    &entry.description,
+                    config,
+                ));
+            }
+            *self = loaded;
+            Ok(file.preset.len())
+        }
+
+        /// Reads a TOML file with [`PresetRegistry::load_toml`].
+        ///
+        /// # Errors
+        /// Returns [`PresetError::Io`] if the file cannot be read, and the
+        /// errors of [`PresetRegistry::load_toml`].
+        pub fn load_toml_file(&mut self, path: impl AsRef<Path>) -> Result<usize, PresetError> {
+            let source = std::fs::read_to_string(path).map_err(PresetError::Io)?;
+            self.load_toml(&source)
+        }
+    }
+
+    /// Overwrites the settings in `base` with those in `overrides`, merging
+    /// nested tables so that a stage's settings can be changed one by one.
+    ///
+    /// An enum value with settings is a table with the variant as its only
+    /// key; one naming another variant replaces the base value instead of
+    /// joining it.
+    fn merge(base: &mut toml::Table, overrides: &toml::Table) {
+        for (key, value) in overrides {
+            match (base.get_mut(key), value) {
+                (Some(toml::Value::Table(base)), toml::Value::Table(overrides))
+                    if !is_other_variant(base, overrides) =>
+                {
+                    merge(base, overrides)
+                }
+                _ => {
+                    base.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    /// Whether `base` and `overrides` are values of an enum naming different
+    /// variants. No configuration struct has fewer than two settings, so a
+    /// table with one key is always a variant.
+    fn is_other_variant(base: &toml::Table, overrides: &toml::Table) -> bool {
+        base.len() == 1 && overrides.len() == 1 && base.keys().ne(overrides.keys())
+    }
+}
+
+#[cfg(feature = "serde")]
+pub use toml_files::PresetError;
@@ -0,0 +1,224 @@
+//! Progress reporting and cancellation for long runs.
+//!
+//! [`Pipeline::run_with_progress`](crate::Pipeline::run_with_progress) and
+//! [`Pipeline::run_multichannel_with_progress`](crate::Pipeline::run_multichannel_with_progress)
+//! report to a [`ProgressSink`] at the start of every stage and before every
+//! block of click detection, click repair, the restoration stages and the
+//! search for clicks left in the output, and stop with [`Error::Cancelled`]
+//! at the next of those points once their [`CancellationToken`] is
+//! cancelled. Blocks are 65,536 samples long, under 1.5 s at 44.1 kHz.
+//!
+//! The overall fraction weights each stage by its typical share of the run
+//! time, so the estimated time remaining is only as good as that guess; it
+//! settles once the slow restoration stages are under way.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+use crate::pipeline::BaselineConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// A step of the pipeline. Stages compare in the order the pipeline runs
+/// them.
+pub enum Stage {
+    /// Rumble filtering and level measurement.
+    Prepare,
+    /// Click detection.
+    Detect,
+    /// Click repair.
+    Repair,
+    /// Decrackle, hum removal and hiss reduction.
+    Restore,
+    /// Wow and flutter measurement and correction.
+    WowFlutter,
+    /// Output validation and the cleanliness assessment.
+    Validate,
+}
+
+impl Stage {
+    /// Every stage, in the order the pipeline runs them.
+    pub const ALL: [Stage; 6] = [
+        Stage::Prepare,
+        Stage::Detect,
+        Stage::Repair,
+        Stage::Restore,
+        Stage::WowFlutter,
+        Stage::Validate,
+    ];
+
+    /// Name of the stage to show to users.
+    pub fn label(self) -> &'static str {
+        match self {
+            Stage::Prepare => "Preparing",
+            Stage::Detect => "Detecting clicks",
+            Stage::Repair => "Repairing clicks",
+            Stage::Restore => "Restoring",
+            Stage::WowFlutter => "Correcting speed",
+            Stage::Validate => "Validating",
+        }
+    }
+
+    /// Rough share of the run time the stage takes with `config`; zero for
+    /// stages `config` disables.
+    fn weight(self, config: &BaselineConfig) -> f32 {
+        let enabled = |on: bool, weight: f32| if on { weight } else { 0.0 };
+        match self {
+            Stage::Prepare => 1.0,
+            Stage::Detect => 2.0,
+            Stage::Repair => 2.0,
+            Stage::Restore => {
+                enabled(config.decrackle.is_some(), 1.0)
+                    + enabled(config.dehum.is_some(), 8.0)
+                    + enabled(config.dehiss.is_some(), 14.0)
+            }
+            Stage::WowFlutter => enabled(config.wow_flutter.is_some(), 1.0),
+            Stage::Validate => 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How far a run has got.
+pub struct Progress {
+    /// The stage being run.
+    pub stage: Stage,
+    /// Fraction of `stage` done, in `[0.0, 1.0]`.
+    pub stage_fraction: f32,
+    /// Fraction of the whole run done, in `[0.0, 1.0]`.
+    pub fraction: f32,
+    /// Time since the run started.
+    pub elapsed: Duration,
+    /// Estimated time until the run finishes, once there is progress to
+    /// extrapolate from.
+    pub eta: Option<Duration>,
+}
+
+/// Receives [`Progress`] updates from a run.
+///
+/// Implemented for closures taking a `&Progress`. Updates arrive on the
+/// thread that runs the pipeline, and the pipeline waits for each call, so a
+/// sink should hand the update off rather than do slow work itself.
+pub trait ProgressSink {
+    /// Called with the progress of the run.
+    fn report(&mut self, progress: &Progress);
+}
+
+impl<F: FnMut(&Progress)> ProgressSink for F {
+    fn report(&mut self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Asks a run to stop.
+///
+/// Clones share one flag: keep a clone, hand the token to the run, and call
+/// [`CancellationToken::cancel`] from any thread.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::progress::{CancellationToken, Progress};
+/// use vinyl_engine::{BaselineConfig, Error, Pipeline};
+///
+/// let cancel = CancellationToken::new();
+/// let mut stop_after_first_update = |progress: &Progress| {
+///     println!("{} {:.0}%", progress.stage.label(), progress.fraction * 100.0);
+///     cancel.cancel();
+/// };
+/// let pipeline = Pipeline::new(&BaselineConfig::default());
+/// let result = pipeline.run_with_progress(&[0.0; 1_000], &mut stop_after_first_update, &cancel);
+/// assert_eq!(result.unwrap_err(), Error::Cancelled);
+/// ```
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every run holding a clone of this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`CancellationToken::cancel`] was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Tracks a run for its [`ProgressSink`] and [`CancellationToken`].
+pub(crate) struct Monitor<'a> {
+    sink: Option<&'a mut dyn ProgressSink>,
+    cancel: Option<&'a CancellationToken>,
+    weights: [f32; Stage::ALL.len()],
+    started: Instant,
+}
+
+impl<'a> Monitor<'a> {
+    /// A monitor that reports nowhere and never cancels.
+    pub(crate) fn silent() -> Self {
+        Self {
+            sink: None,
+            cancel: None,
+            weights: [1.0; Stage::ALL.len()],
+            started: Instant::now(),
+        }
+    }
+
+    /// A monitor for a run of `config`.
+    pub(crate) fn new(
+        config: &BaselineConfig,
+        sink: &'a mut dyn ProgressSink,
+        cancel: &'a CancellationToken,
+    ) -> Self {
+        Self {
+            sink: Some(sink),
+            cancel: Some(cancel),
+            weights: Stage::ALL.map(|stage| stage.weight(config)),
+            started: Instant::now(),
+        }
+    }
+
+    /// Reports that `fraction` of `stage` is done.
+    ///
+    /// # Errors
+    /// Returns [`Error::Cancelled`] if the run was cancelled.
+    pub(crate) fn advance(&mut self, stage: Stage, fraction: f32) -> Result<()> {
+        if self.cancel.is_some_and(CancellationToken::is_cancelled) {
+            return Err(Error::Cancelled);
+        }
+        self.report(stage, fraction.clamp(0.0, 1.0));
+        Ok(())
+    }
+
+    /// Reports that the run is complete.
+    pub(crate) fn finish(&mut self) {
+        self.report(Stage::Validate, 1.0);
+    }
+
+    fn report(&mut self, stage: Stage, stage_fraction: f32) {
+        let Some(sink) = &mut self.sink else {
+            return;
+        };
+        let index = stage as usize;
+        let total: f32 = self.weights.iter().sum();
+        let done: f32 =
+            self.weights[..index].iter().sum::<f32>() + self.weights[index] * stage_fraction;
+        let fraction = (done / total).clamp(0.0, 1.0);
+        let elapsed = self.started.elapsed();
+        let eta = (fraction > 0.0).then(|| elapsed.mul_f64(f64::from((1.0 - fraction) / fraction)));
+        sink.report(&Progress {
+            stage,
+            stage_fraction,
+            fraction,
+            elapsed,
+            eta,
+        });
+    }
+}
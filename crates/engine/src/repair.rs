@@ -0,0 +1,155 @@
+//! Impulse repair stage of the pipeline.
+//!
+//! [`ImpulseRepairer`] is the extension point for repair algorithms;
+//! [`BaselineRepairer`] implements the interpolation methods selected by
+//! [`RepairMode`] and is what the pipeline uses by default.
+
+use std::fmt;
+
+use crate::ar;
+use crate::event::ImpulseEvent;
+use crate::pipeline::RepairMode;
+
+/// A click/pop repair algorithm.
+///
+/// Implementations replace the samples covered by the detected events and
+/// leave the rest of the signal untouched. Combine a repairer with a detector
+/// through [`Pipeline::builder`](crate::pipeline::Pipeline::builder).
+///
+/// # Block processing
+/// To produce the same result for a block of a signal as for the whole
+/// signal, the repair of a region must only depend on samples within
+/// [`ImpulseRepairer::context`] of it.
+pub trait ImpulseRepairer: fmt::Debug + Send + Sync {
+    /// Returns a copy of `signal` with every event in `impulses` repaired.
+    ///
+    /// `impulses` may be in any order and may overlap.
+    fn repair(&self, signal: &[f32], impulses: &[ImpulseEvent]) -> Vec<f32>;
+
+    /// Number of samples on either side of a region that the repair reads.
+    fn context(&self) -> usize;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// The pipeline's default repairer: interpolation across each merged click
+/// region, linear or autoregressive depending on its [`RepairMode`].
+pub struct BaselineRepairer {
+    mode: RepairMode,
+}
+
+impl BaselineRepairer {
+    /// Creates a repairer that fills regions according to `mode`.
+    pub fn new(mode: RepairMode) -> Self {
+        Self { mode }
+    }
+}
+
+impl ImpulseRepairer for BaselineRepairer {
+    fn repair(&self, signal: &[f32], impulses: &[ImpulseEvent]) -> Vec<f32> {
+        repair_impulses(signal, impulses, self.mode)
+    }
+
+    fn context(&self) -> usize {
+        match self.mode {
+            RepairMode::Linear => 1,
+            RepairMode::Autoregressive { context, .. } => context,
+        }
+    }
+}
+
+/// Repairs detected impulses by interpolating over them using surrounding samples.
+///
+/// This function replaces the samples covered by each event with values
+/// interpolated from the unaffected samples on either side. Events that overlap or
+/// touch are merged and treated as a single region to repair.
+///
+/// # Parameters
+/// - `input`: The signal containing impulses to repair.
+/// - `impulses`: Events to repair, in any order.
+/// - `mode`: How to fill each region; see [`RepairMode`].
+///
+/// # Returns
+/// A new signal with every impulse region replaced.
+///
+/// # Implementation Details
+/// - Overlapping or adjacent events are merged into regions and repaired as a unit.
+/// - Autoregressive repair only uses context up to the neighboring regions, so each
+///   region's repair depends on clean input samples alone.
+/// - Regions without enough clean context for the AR model fall back to linear
+///   interpolation.
+fn repair_impulses(input: &[f32], impulses: &[ImpulseEvent], mode: RepairMode) -> Vec<f32> {
+    if impulses.is_empty() || input.is_empty() {
+        return input.to_vec();
+    }
+
+    let regions = merge_regions(impulses);
+    let mut repaired = input.to_vec();
+    for (position, &(start, end)) in regions.iter().enumerate() {
+        if let RepairMode::Autoregressive { order, context } = mode {
+            let clean_from = if position == 0 {
+                0
+            } else {
+                regions[position - 1].1
+            };
+            let clean_to = regions.get(position + 1).map_or(input.len(), |next| next.0);
+            let left = &input[start.saturating_sub(context).max(clean_from)..start];
+            let right = &input[end..(end + context).min(clean_to)];
+
+            if left.len() >= order && right.len() >= order {
+                if let Some(filled) = ar::fit_ar(&[left, right], order)
+                    .and_then(|filter| ar::lsar_interpolate(input, start, end, &filter))
+                {
+                    repaired[start..end].copy_from_slice(&filled);
+                    continue;
+                }
+            }
+        }
+
+        interpolate_linear(input, &mut repaired, start, end);
+    }
+
+    repaired
+}
+
+/// Sorts event spans and merges those that overlap or touch into half-open
+/// `(start, end)` regions.
+fn merge_regions(impulses: &[ImpulseEvent]) -> Vec<(usize, usize)> {
+    let mut spans: Vec<(usize, usize)> = impulses
+        .iter()
+        .filter(|event| !event.is_empty())
+        .map(|event| (event.start, event.end))
+        .collect();
+    spans.sort_unstable();
+
+    let mut regions: Vec<(usize, usize)> = Vec::with_capacity(spans.len());
+    for (start, end) in spans {
+        match regions.last_mut() {
+            Some(region) if start <= region.1 => region.1 = region.1.max(end),
+            _ => regions.push((start, end)),
+        }
+    }
+    regions
+}
+
+/// Replaces `start..end` in `repaired` with a straight line between the samples
+/// of `input` just outside the region.
+///
+/// The function handles edge cases where impulses are near the signal boundaries by
+/// using `saturating_sub` and `min` to clamp indices.
+fn interpolate_linear(input: &[f32], repaired: &mut [f32], start: usize, end: usize) {
+    let left_index = start.saturating_sub(1);
+    let right_index = end.min(input.len() - 1);
+    let left_value = input[left_index];
+    let right_value = input[right_index];
+    let span = (right_index - left_index) as f32;
+
+    // Guard against edge case where right_index <= left_index, which would cause
+    // right_index - 1 to underflow in the range expression below. This can occur
+    // when repairing impulses at signal boundaries in very short signals.
+    if right_index > left_index + 1 {
+        for (offset, index) in (left_index + 1..=right_index - 1).enumerate() {
+            let t = (offset + 1) as f32 / span;
+            repaired[index] = left_value + (right_value - left_value) * t;
+        }
+    }
+}
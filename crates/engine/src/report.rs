@@ -0,0 +1,134 @@
+//! A record of one run of the pipeline, for logs, batch scripts and front
+//! ends.
+//!
+//! With the `serde` feature, a [`RunReport`] is written as JSON with
+//! `RunReport::to_json`. The format is versioned by
+//! [`RunReport::format_version`]: fields may be added within a version, but
+//! are never renamed or removed.
+
+use std::time::{Duration, Instant};
+
+use crate::cleanliness::Cleanliness;
+use crate::decrackle::DecrackleReport;
+use crate::dehiss::DehissReport;
+use crate::dehum::DehumReport;
+use crate::event::ImpulseEvent;
+use crate::pipeline::{BaselineConfig, BaselineOutput, Pipeline, ValidationResult};
+use crate::wow::WowFlutterReport;
+
+/// Current value of [`RunReport::format_version`].
+pub const REPORT_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What a run was configured with, what it found and how long it took.
+///
+/// Holds everything in a [`BaselineOutput`] except the sample buffers.
+pub struct RunReport {
+    /// Version of the report format, [`REPORT_FORMAT_VERSION`] for reports
+    /// written by this version of the engine.
+    pub format_version: u32,
+    /// The configuration the run used.
+    pub config: BaselineConfig,
+    /// Number of input samples.
+    pub samples: usize,
+    /// The detected clicks, ordered by position.
+    pub impulses: Vec<ImpulseEvent>,
+    /// What the decrackle stage removed, if it ran.
+    pub decrackle: Option<DecrackleReport>,
+    /// What the hum removal stage removed, if it ran.
+    pub dehum: Option<DehumReport>,
+    /// What the hiss reduction stage removed, if it ran.
+    pub dehiss: Option<DehissReport>,
+    /// The measured speed variations, if the stage ran and found a tone.
+    pub wow_flutter: Option<WowFlutterReport>,
+    /// Validation of the output.
+    pub validation: ValidationResult,
+    /// Confidence that the output is clean.
+    pub cleanliness: Cleanliness,
+    /// How long the run took.
+    pub timing: RunTiming,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Processing time of a run.
+pub struct RunTiming {
+    /// Wall-clock time the run took, in milliseconds.
+    pub elapsed_ms: f64,
+    /// Duration of the audio divided by the processing time; above `1.0`
+    /// the run was faster than real time. `0.0` if nothing was timed.
+    pub realtime_factor: f64,
+}
+
+impl RunTiming {
+    /// Timing of a run over `samples` samples at `sample_rate` that took
+    /// `elapsed`.
+    pub fn new(elapsed: Duration, samples: usize, sample_rate: u32) -> Self {
+        let seconds = elapsed.as_secs_f64();
+        let audio_seconds = samples as f64 / f64::from(sample_rate.max(1));
+        Self {
+            elapsed_ms: seconds * 1000.0,
+            realtime_factor: if seconds > 0.0 {
+                audio_seconds / seconds
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+impl RunReport {
+    /// Builds the report of a run of `config` over `samples` input samples
+    /// that produced `output` in `elapsed`.
+    pub fn new(
+        config: &BaselineConfig,
+        samples: usize,
+        output: &BaselineOutput,
+        elapsed: Duration,
+    ) -> Self {
+        Self {
+            format_version: REPORT_FORMAT_VERSION,
+            config: config.clone(),
+            samples,
+            impulses: output.detected_impulses.clone(),
+            decrackle: output.decrackle,
+            dehum: output.dehum,
+            dehiss: output.dehiss,
+            wow_flutter: output.wow_flutter.clone(),
+            validation: output.validation.clone(),
+            cleanliness: output.cleanliness,
+            timing: RunTiming::new(elapsed, samples, config.sample_rate),
+        }
+    }
+
+    /// The report as pretty-printed JSON.
+    ///
+    /// Non-finite numbers, which JSON cannot represent, are written as
+    /// `null`; such a report cannot be read back with
+    /// [`RunReport::from_json`].
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a report always serializes")
+    }
+
+    /// Reads a report written by [`RunReport::to_json`].
+    ///
+    /// # Errors
+    /// Returns the parse error if `json` is not a report.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+impl Pipeline {
+    /// Runs the pipeline like [`Pipeline::run`] and also returns the
+    /// report of the run.
+    pub fn run_with_report(&self, input: &[f32]) -> (BaselineOutput, RunReport) {
+        let started = Instant::now();
+        let output = self.run(input);
+        let report = RunReport::new(self.config(), input.len(), &output, started.elapsed());
+        (output, report)
+    }
+}
@@ -0,0 +1,353 @@
+//! Rumble removal: a Butterworth high-pass filter ahead of click detection.
+//!
+//! Turntable motors and bearings add rumble in the 10–30 Hz range, and warped
+//! records add large, slow excursions below 10 Hz. Neither is audible, but
+//! both eat headroom and lift the signal level that the impulse threshold is
+//! derived from. The filter removes them, together with any DC offset, before
+//! the signal is normalized and searched for clicks.
+
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+/// Parameters of the rumble filter ([`BaselineConfig::rumble`]).
+///
+/// The presets cover typical turntables; [`RumbleConfig::default`] is
+/// [`RumbleConfig::belt_drive`].
+///
+/// [`BaselineConfig::rumble`]: crate::BaselineConfig::rumble
+pub struct RumbleConfig {
+    /// Cutoff frequency in Hz, where a single pass of the filter attenuates
+    /// by 3 dB.
+    pub cutoff_hz: f32,
+    /// Order of the Butterworth filter, from 1 to 8. Each order adds 6 dB
+    /// per octave of attenuation below the cutoff.
+    pub order: usize,
+    /// Whether the filter runs forwards only or forwards and backwards.
+    pub phase: FilterPhase,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// Phase response of the rumble filter.
+pub enum FilterPhase {
+    #[default]
+    /// The filter runs forwards and then backwards over the whole signal, so
+    /// the phase shifts cancel and bass transients keep their shape. The
+    /// magnitude response is applied twice: the filter attenuates 6 dB at
+    /// the cutoff and rolls off twice as steeply.
+    ///
+    /// Needs the complete signal, so it is only available offline;
+    /// [`StreamingCleaner`](crate::StreamingCleaner) runs
+    /// [`FilterPhase::MinimumPhase`] instead.
+    ZeroPhase,
+    /// A causal filter, run forwards only. It shifts the phase of the bass
+    /// slightly, but each output sample depends only on the past, so it
+    /// streams exactly.
+    MinimumPhase,
+}
+
+impl RumbleConfig {
+    /// Direct-drive turntables have little motor rumble; a gentle filter
+    /// removes DC and warp energy without touching the low bass.
+    pub const fn direct_drive() -> Self {
+        Self {
+            cutoff_hz: 15.0,
+            order: 2,
+            phase: FilterPhase::ZeroPhase,
+        }
+    }
+
+    /// Belt-drive turntables leak some motor and bearing rumble below
+    /// 20 Hz.
+    pub const fn belt_drive() -> Self {
+        Self {
+            cutoff_hz: 20.0,
+            order: 4,
+            phase: FilterPhase::ZeroPhase,
+        }
+    }
+
+    /// Idler-wheel turntables rumble up to around 30 Hz.
+    pub const fn idler_wheel() -> Self {
+        Self {
+            cutoff_hz: 30.0,
+            order: 4,
+            phase: FilterPhase::ZeroPhase,
+        }
+    }
+
+    /// Warped records put large excursions a few hertz above DC; a steep
+    /// filter keeps them out of the low bass.
+    pub const fn warped() -> Self {
+        Self {
+            cutoff_hz: 20.0,
+            order: 8,
+            phase: FilterPhase::ZeroPhase,
+        }
+    }
+
+    /// The same filter run with the given `phase`.
+    pub const fn with_phase(self, phase: FilterPhase) -> Self {
+        Self { phase, ..self }
+    }
+}
+
+impl Default for RumbleConfig {
+    fn default() -> Self {
+        Self::belt_drive()
+    }
+}
+
+/// Upper edge of the sub-sonic band measured by [`LowFrequencyLevels`], in Hz.
+const SUBSONIC_HZ: f64 = 20.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Low-frequency content of a signal that a listener does not hear but that
+/// takes up headroom.
+pub struct LowFrequencyLevels {
+    /// Mean sample value.
+    pub dc_offset: f32,
+    /// RMS level of the content below 20 Hz, DC included.
+    pub subsonic_rms: f32,
+}
+
+impl LowFrequencyLevels {
+    /// The levels of the same signal scaled by `gain`.
+    pub(crate) fn scaled(self, gain: f32) -> Self {
+        Self {
+            dc_offset: self.dc_offset * gain,
+            subsonic_rms: self.subsonic_rms * gain.abs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Measures [`LowFrequencyLevels`] block by block.
+pub(crate) struct LowFrequencyMeter {
+    lowpass: Cascade,
+    sum: f64,
+    subsonic_sum_sq: f64,
+    samples: usize,
+}
+
+impl LowFrequencyMeter {
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        Self {
+            lowpass: Cascade::butterworth(4, SUBSONIC_HZ, sample_rate, Response::LowPass),
+            sum: 0.0,
+            subsonic_sum_sq: 0.0,
+            samples: 0,
+        }
+    }
+
+    /// Measures a complete signal.
+    pub(crate) fn measure(signal: &[f32], sample_rate: u32) -> LowFrequencyLevels {
+        let mut meter = Self::new(sample_rate);
+        meter.accumulate(signal);
+        meter.levels()
+    }
+
+    pub(crate) fn accumulate(&mut self, block: &[f32]) {
+        for &sample in block {
+            let sample = f64::from(sample);
+            if self.samples == 0 {
+                self.lowpass.settle(sample);
+            }
+            let subsonic = self.lowpass.process(sample);
+            self.sum += sample;
+            self.subsonic_sum_sq += subsonic * subsonic;
+            self.samples += 1;
+        }
+    }
+
+    pub(crate) fn levels(&self) -> LowFrequencyLevels {
+        if self.samples == 0 {
+            return LowFrequencyLevels::default();
+        }
+        let samples = self.samples as f64;
+        LowFrequencyLevels {
+            dc_offset: (self.sum / samples) as f32,
+            subsonic_rms: (self.subsonic_sum_sq / samples).sqrt() as f32,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The minimum-phase rumble filter, run block by block.
+pub(crate) struct RumbleFilter {
+    cascade: Cascade,
+    started: bool,
+}
+
+impl RumbleFilter {
+    pub(crate) fn new(config: &RumbleConfig, sample_rate: u32) -> Self {
+        Self {
+            cascade: highpass(config, sample_rate),
+            started: false,
+        }
+    }
+
+    /// Filters the next block of the signal in place.
+    pub(crate) fn process(&mut self, block: &mut [f32]) {
+        if let Some(&first) = block.first() {
+            if !self.started {
+                // Start as if the first sample had always been there, so a
+                // DC offset does not ring through the filter.
+                self.cascade.settle(f64::from(first));
+                self.started = true;
+            }
+        }
+        for sample in block {
+            *sample = self.cascade.process(f64::from(*sample)) as f32;
+        }
+    }
+}
+
+/// Filters a complete signal with the phase response `config` asks for.
+pub(crate) fn filter(signal: &[f32], config: &RumbleConfig, sample_rate: u32) -> Vec<f32> {
+    match config.phase {
+        FilterPhase::MinimumPhase => {
+            let mut output = signal.to_vec();
+            RumbleFilter::new(config, sample_rate).process(&mut output);
+            output
+        }
+        FilterPhase::ZeroPhase => filter_zero_phase(signal, config, sample_rate),
+    }
+}
+
+/// Runs the filter forwards and backwards over `signal` extended at both
+/// ends by its odd reflection, which keeps the filters' start-up transients
+/// out of the result.
+fn filter_zero_phase(signal: &[f32], config: &RumbleConfig, sample_rate: u32) -> Vec<f32> {
+    if signal.is_empty() {
+        return Vec::new();
+    }
+    let cutoff = f64::from(config.cutoff_hz.max(f32::MIN_POSITIVE));
+    let pad = ((3.0 * f64::from(sample_rate) / cutoff) as usize).min(signal.len() - 1);
+    let first = f64::from(signal[0]);
+    let last = f64::from(signal[signal.len() - 1]);
+
+    let mut extended = Vec::with_capacity(signal.len() + 2 * pad);
+    extended.extend((1..=pad).rev().map(|k| 2.0 * first - f64::from(signal[k])));
+    extended.extend(signal.iter().map(|&sample| f64::from(sample)));
+    extended.extend((1..=pad).map(|k| 2.0 * last - f64::from(signal[signal.len() - 1 - k])));
+
+    let mut cascade = highpass(config, sample_rate);
+    cascade.settle(extended[0]);
+    for sample in extended.iter_mut() {
+        *sample = cascade.process(*sample);
+    }
+    cascade.settle(extended[extended.len() - 1]);
+    for sample in extended.iter_mut().rev() {
+        *sample = cascade.process(*sample);
+    }
+
+    extended[pad..pad + signal.len()]
+        .iter()
+        .map(|&sample| sample as f32)
+        .collect()
+}
+
+fn highpass(config: &RumbleConfig, sample_rate: u32) -> Cascade {
+    Cascade::butterworth(
+        config.order.clamp(1, 8),
+        f64::from(config.cutoff_hz),
+        sample_rate,
+        Response::HighPass,
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Response {
+    LowPass,
+    HighPass,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// One second-order section, normalized so that `a0 == 1`.
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+}
+
+impl Biquad {
+    /// Gain at DC.
+    fn dc_gain(&self) -> f64 {
+        (self.b[0] + self.b[1] + self.b[2]) / (1.0 + self.a[0] + self.a[1])
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A cascade of biquads in transposed direct form II.
+pub(crate) struct Cascade {
+    sections: Vec<Biquad>,
+    state: Vec<[f64; 2]>,
+}
+
+impl Cascade {
+    /// Butterworth filter of `order` with its 3 dB point at `cutoff` Hz,
+    /// designed with the bilinear transform.
+    pub(crate) fn butterworth(
+        order: usize,
+        cutoff: f64,
+        sample_rate: u32,
+        response: Response,
+    ) -> Self {
+        let nyquist = f64::from(sample_rate.max(1)) / 2.0;
+        let omega = PI * (cutoff / nyquist).clamp(1e-6, 0.999);
+        let (sin, cos) = omega.sin_cos();
+        let mut sections = Vec::with_capacity(order.div_ceil(2));
+
+        for k in 0..order / 2 {
+            let q = 1.0 / (2.0 * (PI * (2 * k + 1) as f64 / (2 * order) as f64).cos());
+            let alpha = sin / (2.0 * q);
+            let a0 = 1.0 + alpha;
+            let b = match response {
+                Response::LowPass => [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+                Response::HighPass => [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            };
+            sections.push(Biquad {
+                b: b.map(|coefficient| coefficient / a0),
+                a: [-2.0 * cos / a0, (1.0 - alpha) / a0],
+            });
+        }
+        if order % 2 == 1 {
+            let k = (omega / 2.0).tan();
+            let a1 = (k - 1.0) / (k + 1.0);
+            let b = match response {
+                Response::LowPass => [k / (1.0 + k), k / (1.0 + k), 0.0],
+                Response::HighPass => [1.0 / (1.0 + k), -1.0 / (1.0 + k), 0.0],
+            };
+            sections.push(Biquad { b, a: [a1, 0.0] });
+        }
+
+        let state = vec![[0.0; 2]; sections.len()];
+        Self { sections, state }
+    }
+
+    pub(crate) fn process(&mut self, input: f64) -> f64 {
+        let mut value = input;
+        for (section, state) in self.sections.iter().zip(&mut self.state) {
+            let output = section.b[0] * value + state[0];
+            state[0] = section.b[1] * value - section.a[0] * output + state[1];
+            state[1] = section.b[2] * value - section.a[1] * output;
+            value = output;
+        }
+        value
+    }
+
+    /// Sets the state to what a constant `input` would have left behind.
+    pub(crate) fn settle(&mut self, input: f64) {
+        let mut value = input;
+        for (section, state) in self.sections.iter().zip(&mut self.state) {
+            let output = section.dc_gain() * value;
+            state[1] = section.b[2] * value - section.a[1] * output;
+            state[0] = section.b[1] * value - section.a[0] * output + state[1];
+            value = output;
+        }
+    }
+}
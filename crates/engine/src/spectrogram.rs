@@ -0,0 +1,459 @@
+//! Spectrogram images for the before/after view.
+//!
+//! A [`Spectrogram`] renders a range of a signal as a tile of `width` by
+//! `height` levels: each column is the short-time spectrum around its share of
+//! the range, each row a band of frequencies, on a linear or logarithmic
+//! axis, and each level the band's magnitude in dB mapped onto `0..=255`
+//! between [`SpectrogramConfig::floor_db`] and
+//! [`SpectrogramConfig::ceiling_db`]. A [`SpectrogramTile`] converts to RGBA
+//! through a [`Colormap`] for drawing on a canvas.
+//!
+//! The [`SpectrogramView::Difference`] view compares the normalized input
+//! with the repaired output and shows how many dB the cleaner removed in every
+//! cell, so clicks, hum lines and hiss reduction stand out against a dark
+//! background where the signal was left alone.
+//!
+//! A column analyzes every frame of its share of the range, one per
+//! [`SpectrogramConfig::hop`], and shows the loudest of them in each band, so
+//! a single click stays visible however far the view is zoomed out. The cost
+//! of a tile grows with the length of its range rather than its width.
+
+use std::ops::Range;
+
+use crate::error::{Error, Result};
+use crate::fft::{hann, Fft};
+use crate::pipeline::{at_least, at_most, within, BaselineOutput};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+/// Parameters of a [`Spectrogram`].
+pub struct SpectrogramConfig {
+    /// Window applied to every analysis frame.
+    pub window: WindowFunction,
+    /// Length of the analysis frames, in samples, rounded up to a power of
+    /// two. Longer frames resolve frequencies more finely but smear clicks
+    /// over a longer time.
+    pub window_length: usize,
+    /// Distance between the starts of consecutive frames, in samples; a
+    /// column shows the loudest of the frames it covers. A column that
+    /// covers fewer samples than the hop still analyzes one frame, centered
+    /// on the column.
+    pub hop: usize,
+    /// How frequencies are spread over the rows.
+    pub frequency_scale: FrequencyScale,
+    /// Level shown as `0`, in dB relative to a full-scale sine.
+    pub floor_db: f32,
+    /// Level shown as `255`, in dB relative to a full-scale sine.
+    pub ceiling_db: f32,
+    /// Reduction shown as `255` in the [`SpectrogramView::Difference`] view,
+    /// in dB.
+    pub difference_range_db: f32,
+}
+
+impl Default for SpectrogramConfig {
+    fn default() -> Self {
+        Self {
+            window: WindowFunction::default(),
+            window_length: 2048,
+            hop: 512,
+            frequency_scale: FrequencyScale::default(),
+            floor_db: -100.0,
+            ceiling_db: 0.0,
+            difference_range_db: 30.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// Window function of a [`Spectrogram`]'s analysis frames.
+pub enum WindowFunction {
+    /// Hann window: a good balance of frequency resolution and leakage.
+    #[default]
+    Hann,
+    /// Hamming window: a narrower main lobe than Hann, with higher far-off
+    /// leakage.
+    Hamming,
+    /// Blackman window: the lowest leakage, for faint tones next to loud
+    /// ones, with a wider main lobe.
+    Blackman,
+    /// No windowing: the sharpest main lobe and the most leakage.
+    Rectangular,
+}
+
+impl WindowFunction {
+    fn coefficients(self, len: usize) -> Vec<f64> {
+        let phase = |n: usize| 2.0 * std::f64::consts::PI * n as f64 / len as f64;
+        match self {
+            WindowFunction::Hann => hann(len),
+            WindowFunction::Hamming => (0..len).map(|n| 0.54 - 0.46 * phase(n).cos()).collect(),
+            WindowFunction::Blackman => (0..len)
+                .map(|n| 0.42 - 0.5 * phase(n).cos() + 0.08 * (2.0 * phase(n)).cos())
+                .collect(),
+            WindowFunction::Rectangular => vec![1.0; len],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// How a [`Spectrogram`] spreads frequencies over the rows of a tile.
+pub enum FrequencyScale {
+    /// Rows of equal width from 0 Hz to the Nyquist frequency.
+    #[default]
+    Linear,
+    /// Rows of equal width in octaves from `min_hz` to the Nyquist
+    /// frequency, which gives the bass and its hum harmonics room.
+    Logarithmic {
+        /// Lowest frequency shown, in Hz.
+        min_hz: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// Which signal of a [`BaselineOutput`] a tile shows.
+pub enum SpectrogramView {
+    /// [`BaselineOutput::normalized`]: the input, before cleaning.
+    Normalized,
+    /// [`BaselineOutput::repaired`]: the cleaned output.
+    Repaired,
+    /// How much the cleaner removed: `normalized` minus `repaired`, in dB.
+    Difference,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// How [`SpectrogramTile::to_rgba`] colors levels.
+pub enum Colormap {
+    /// Black to white.
+    Grayscale,
+    /// Black through purple, red and orange to pale yellow, as in
+    /// matplotlib's "inferno".
+    #[default]
+    Inferno,
+}
+
+/// Color stops of [`Colormap::Inferno`], evenly spaced over `0..=255`.
+const INFERNO: [[u8; 3]; 5] = [
+    [0, 0, 4],
+    [87, 16, 110],
+    [188, 55, 84],
+    [249, 142, 9],
+    [252, 255, 164],
+];
+
+impl Colormap {
+    /// The RGB color of `level`.
+    pub fn color(self, level: u8) -> [u8; 3] {
+        match self {
+            Colormap::Grayscale => [level; 3],
+            Colormap::Inferno => {
+                let position = f32::from(level) / 255.0 * (INFERNO.len() - 1) as f32;
+                let index = (position as usize).min(INFERNO.len() - 2);
+                let fraction = position - index as f32;
+                let (from, to) = (INFERNO[index], INFERNO[index + 1]);
+                [0, 1, 2].map(|channel| {
+                    let (from, to) = (f32::from(from[channel]), f32::from(to[channel]));
+                    (from + (to - from) * fraction).round() as u8
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A rendered range of a spectrogram.
+pub struct SpectrogramTile {
+    /// Number of columns.
+    pub width: usize,
+    /// Number of rows.
+    pub height: usize,
+    /// First frame of the signal covered.
+    pub start_frame: usize,
+    /// One past the last frame covered.
+    pub end_frame: usize,
+    /// One level per cell, row by row from the highest frequency down, so
+    /// the tile draws upright.
+    pub levels: Vec<u8>,
+}
+
+impl SpectrogramTile {
+    /// The tile as RGBA pixels, row by row like [`SpectrogramTile::levels`]
+    /// and fully opaque, ready for a canvas `ImageData`.
+    pub fn to_rgba(&self, colormap: Colormap) -> Vec<u8> {
+        self.levels
+            .iter()
+            .flat_map(|&level| {
+                let [red, green, blue] = colormap.color(level);
+                [red, green, blue, u8::MAX]
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Renders [`SpectrogramTile`]s with a [`SpectrogramConfig`] (see the
+/// [module documentation](self)).
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::spectrogram::{Colormap, Spectrogram, SpectrogramConfig, SpectrogramView};
+/// use vinyl_engine::{run_baseline_pipeline, BaselineConfig};
+///
+/// let mut signal: Vec<f32> = (0..44_100).map(|i| 0.3 * (i as f32 * 0.06).sin()).collect();
+/// signal[20_000] += 0.6;
+/// let output = run_baseline_pipeline(&signal, &BaselineConfig::default());
+///
+/// let spectrogram = Spectrogram::new(SpectrogramConfig::default(), 44_100)?;
+/// let tile = spectrogram.view_tile(&output, SpectrogramView::Difference, 0..44_100, 256, 128);
+/// assert_eq!(tile.levels.len(), 256 * 128);
+/// let pixels = tile.to_rgba(Colormap::Inferno);
+/// assert_eq!(pixels.len(), 256 * 128 * 4);
+/// # Ok::<(), vinyl_engine::Error>(())
+/// ```
+pub struct Spectrogram {
+    config: SpectrogramConfig,
+    sample_rate: u32,
+    fft: Fft,
+    window: Vec<f64>,
+    /// Factor from squared FFT magnitude to the power of a sine, relative to
+    /// full scale.
+    power_scale: f64,
+}
+
+impl Spectrogram {
+    /// Prepares spectrograms of signals sampled at `sample_rate`.
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidConfig`] if the
+    /// window is shorter than 16 or longer than 65536 samples, the hop is
+    /// zero, `floor_db` is not below `ceiling_db`, `difference_range_db` is
+    /// not positive, or the lowest frequency of a logarithmic scale is not
+    /// between 0 Hz and the Nyquist frequency.
+    pub fn new(config: SpectrogramConfig, sample_rate: u32) -> Result<Self> {
+        at_least("window_length", config.window_length, 16)?;
+        at_most("window_length", config.window_length, 1 << 16)?;
+        at_least("hop", config.hop, 1)?;
+        if config.floor_db.partial_cmp(&config.ceiling_db) != Some(std::cmp::Ordering::Less) {
+            return Err(Error::invalid_config(
+                "ceiling_db",
+                format!(
+                    "must be above floor_db ({}), got {}",
+                    config.floor_db, config.ceiling_db
+                ),
+            ));
+        }
+        within(
+            "difference_range_db",
+            config.difference_range_db,
+            f32::MIN_POSITIVE,
+            f32::MAX,
+        )?;
+        if let FrequencyScale::Logarithmic { min_hz } = config.frequency_scale {
+            within(
+                "frequency_scale.min_hz",
+                min_hz,
+                f32::MIN_POSITIVE,
+                sample_rate as f32 / 2.0,
+            )?;
+        }
+
+        let length = config.window_length.next_power_of_two();
+        let window = config.window.coefficients(length);
+        let gain: f64 = window.iter().sum();
+        Ok(Self {
+            config,
+            sample_rate,
+            fft: Fft::new(length),
+            power_scale: 4.0 / (gain * gain),
+            window,
+        })
+    }
+
+    /// The configuration the spectrogram was prepared with.
+    pub fn config(&self) -> &SpectrogramConfig {
+        &self.config
+    }
+
+    /// The frames of tile `index` in a grid of tiles of `width` columns that
+    /// each cover `frames_per_column` frames. Tiles of one grid line up, so
+    /// they can be rendered and cached independently.
+    pub fn tile_frames(index: usize, width: usize, frames_per_column: usize) -> Range<usize> {
+        let frames = width * frames_per_column;
+        index * frames..(index + 1) * frames
+    }
+
+    /// The frequency, in Hz, at the center of `row` of a tile of `height`
+    /// rows, counting from the top like [`SpectrogramTile::levels`].
+    pub fn row_frequency(&self, row: usize, height: usize) -> f32 {
+        let band = height.saturating_sub(row + 1);
+        self.band_edge(band as f64 + 0.5, height) as f32
+    }
+
+    /// Renders `frames` of `signal` as a tile of `width` by `height` levels.
+    ///
+    /// The range may reach past the end of the signal, as the last tile of
+    /// a grid usually does; the missing samples are silent.
+    pub fn tile(
+        &self,
+        signal: &[f32],
+        frames: Range<usize>,
+        width: usize,
+        height: usize,
+    ) -> SpectrogramTile {
+        let (floor, ceiling) = (self.config.floor_db, self.config.ceiling_db);
+        let levels = self
+            .decibels(signal, &frames, width, height)
+            .into_iter()
+            .map(|db| to_level(db - floor, ceiling - floor))
+            .collect();
+        self.assemble(frames, width, height, levels)
+    }
+
+    /// Renders how many dB quieter `after` is than `before` in every cell of
+    /// `frames`, from `0` where nothing changed to `255` at
+    /// [`SpectrogramConfig::difference_range_db`] or more. Cells that got
+    /// louder show as `0`; cells below the floor in both signals count as
+    /// unchanged.
+    pub fn difference_tile(
+        &self,
+        before: &[f32],
+        after: &[f32],
+        frames: Range<usize>,
+        width: usize,
+        height: usize,
+    ) -> SpectrogramTile {
+        let before = self.decibels(before, &frames, width, height);
+        let after = self.decibels(after, &frames, width, height);
+        let levels = before
+            .iter()
+            .zip(&after)
+            .map(|(before, after)| to_level(before - after, self.config.difference_range_db))
+            .collect();
+        self.assemble(frames, width, height, levels)
+    }
+
+    /// Renders `frames` of the `view` of `output`.
+    pub fn view_tile(
+        &self,
+        output: &BaselineOutput,
+        view: SpectrogramView,
+        frames: Range<usize>,
+        width: usize,
+        height: usize,
+    ) -> SpectrogramTile {
+        match view {
+            SpectrogramView::Normalized => self.tile(&output.normalized, frames, width, height),
+            SpectrogramView::Repaired => self.tile(&output.repaired, frames, width, height),
+            SpectrogramView::Difference => {
+                self.difference_tile(&output.normalized, &output.repaired, frames, width, height)
+            }
+        }
+    }
+
+    fn assemble(
+        &self,
+        frames: Range<usize>,
+        width: usize,
+        height: usize,
+        levels: Vec<u8>,
+    ) -> SpectrogramTile {
+        SpectrogramTile {
+            width,
+            height,
+            start_frame: frames.start,
+            end_frame: frames.end.max(frames.start),
+            levels,
+        }
+    }
+
+    /// The level of every cell in dB, clamped to the floor, row by row from
+    /// the top.
+    fn decibels(
+        &self,
+        signal: &[f32],
+        frames: &Range<usize>,
+        width: usize,
+        height: usize,
+    ) -> Vec<f32> {
+        let length = frames.end.saturating_sub(frames.start);
+        let bins = self.fft.len() / 2 + 1;
+        let bands: Vec<Range<usize>> = (0..height)
+            .map(|band| {
+                let bin = |edge: f64| edge * self.fft.len() as f64 / f64::from(self.sample_rate);
+                let low = (bin(self.band_edge(band as f64, height)).floor() as usize).min(bins - 1);
+                let high = (bin(self.band_edge(band as f64 + 1.0, height)).ceil() as usize)
+                    .clamp(low + 1, bins);
+                low..high
+            })
+            .collect();
+
+        let mut cells = vec![self.config.floor_db; width * height];
+        for column in 0..width {
+            let from = frames.start + length * column / width;
+            let to = frames.start + length * (column + 1) / width;
+            let count = (to - from).div_ceil(self.config.hop).max(1);
+            let mut power = vec![0.0_f64; bins];
+            for frame in 0..count {
+                let center = from + (to - from) * (2 * frame + 1) / (2 * count);
+                for (loudest, bin) in power.iter_mut().zip(self.power_spectrum(signal, center)) {
+                    *loudest = loudest.max(bin);
+                }
+            }
+            for (band, bins) in bands.iter().enumerate() {
+                let peak = power[bins.clone()].iter().copied().fold(0.0, f64::max);
+                let db = (10.0 * (peak * self.power_scale).log10()) as f32;
+                cells[(height - 1 - band) * width + column] = db.max(self.config.floor_db);
+            }
+        }
+        cells
+    }
+
+    /// Squared magnitudes of the frame of `signal` centered on `center`,
+    /// bins `0..=len / 2`.
+    fn power_spectrum(&self, signal: &[f32], center: usize) -> Vec<f64> {
+        let length = self.fft.len();
+        let start = center as isize - (length / 2) as isize;
+        let mut re: Vec<f64> = (0..length)
+            .map(|n| {
+                usize::try_from(start + n as isize)
+                    .ok()
+                    .and_then(|index| signal.get(index))
+                    .map_or(0.0, |&sample| f64::from(sample) * self.window[n])
+            })
+            .collect();
+        let mut im = vec![0.0; length];
+        self.fft.transform(&mut re, &mut im, false);
+        re.iter()
+            .zip(&im)
+            .take(length / 2 + 1)
+            .map(|(re, im)| re * re + im * im)
+            .collect()
+    }
+
+    /// The frequency at `position` bands up from the bottom of `height`
+    /// bands, in Hz.
+    fn band_edge(&self, position: f64, height: usize) -> f64 {
+        let nyquist = f64::from(self.sample_rate) / 2.0;
+        let fraction = position / height.max(1) as f64;
+        match self.config.frequency_scale {
+            FrequencyScale::Linear => nyquist * fraction,
+            FrequencyScale::Logarithmic { min_hz } => {
+                let min_hz = f64::from(min_hz);
+                min_hz * (nyquist / min_hz).powf(fraction)
+            }
+        }
+    }
+}
+
+/// Maps `value` in `0.0..=range` onto `0..=255`, clamping outside it.
+fn to_level(value: f32, range: f32) -> u8 {
+    (value / range * 255.0).clamp(0.0, 255.0).round() as u8
+}
@@ -0,0 +1,357 @@
+use std::collections::VecDeque;
+
+use crate::cleanliness::{Cleanliness, CleanlinessMeter};
+use crate::decrackle::DecrackleReport;
+use crate::dehiss::DehissReport;
+use crate::dehum::DehumReport;
+use crate::detect::{DetectionContext, ThresholdEnvelope};
+use crate::event::ImpulseEvent;
+use crate::pipeline::{
+    wow_tracker, BaselineConfig, Pipeline, Restoration, SignalLevels, ValidationResult,
+};
+use crate::rumble::{LowFrequencyMeter, RumbleFilter};
+use crate::wow::{WowFlutterReport, WowTracker};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Results gathered over a complete [`StreamingCleaner`] run.
+///
+/// Together with the samples emitted along the way, this carries the same
+/// information as a [`BaselineOutput`](crate::BaselineOutput) without keeping
+/// the full-length `normalized` and `repaired` buffers in memory.
+pub struct StreamingSummary {
+    /// Impulses detected over the whole stream, in ascending order, with
+    /// positions relative to the start of the stream.
+    pub detected_impulses: Vec<ImpulseEvent>,
+    /// Detection threshold over the whole stream.
+    pub threshold_envelope: ThresholdEnvelope,
+    /// What the decrackle stage removed over the whole stream, if it ran.
+    pub decrackle: Option<DecrackleReport>,
+    /// What the hum removal stage removed over the whole stream, if it ran.
+    pub dehum: Option<DehumReport>,
+    /// What the hiss reduction stage removed over the whole stream, if it
+    /// ran.
+    pub dehiss: Option<DehissReport>,
+    /// Speed variations measured over the whole stream, if the stage ran and
+    /// found a tone to track.
+    pub wow_flutter: Option<WowFlutterReport>,
+    /// Validation metrics over every emitted sample.
+    pub validation: ValidationResult,
+    /// Confidence that the emitted signal is free of defects.
+    pub cleanliness: Cleanliness,
+    /// Total number of samples emitted.
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone)]
+/// Block-based version of [`run_baseline_pipeline`](crate::run_baseline_pipeline)
+/// for signals too long to hold several full-length copies of in memory.
+///
+/// The cleaner keeps a short window of look-behind and look-ahead context
+/// around the samples it is working on, so impulses that straddle a block
+/// boundary are detected and repaired exactly as in a whole-signal pass.
+/// Memory use is bounded by the block size plus that context, independent of
+/// the signal length.
+///
+/// Normalization and the detection threshold depend on whole-signal levels,
+/// which a single pass cannot know up front. They are supplied as
+/// [`SignalLevels`], typically gathered by a cheap first pass over the file.
+/// Given the levels of the complete signal, gathered with
+/// [`SignalLevels::for_config`], the concatenated output is bit-identical to
+/// [`BaselineOutput::repaired`](crate::BaselineOutput::repaired) for any
+/// block size. The one exception is a rumble filter with
+/// [`FilterPhase::ZeroPhase`](crate::rumble::FilterPhase::ZeroPhase), which
+/// needs the whole signal at once: the cleaner runs the minimum-phase filter
+/// instead. Wow and flutter are measured but never corrected, even with
+/// [`WowFlutterConfig::correct`](crate::wow::WowFlutterConfig::correct) set.
+/// The [`Cleanliness`] score is gathered the same way, by running the
+/// detector once more over the emitted samples, and matches that of a
+/// whole-signal pass whenever the output does.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::streaming::StreamingCleaner;
+/// use vinyl_engine::{BaselineConfig, SignalLevels};
+///
+/// let mut signal = vec![0.1_f32; 10_000];
+/// signal[4_096] = 0.9;
+///
+/// // First pass: levels only.
+/// let config = BaselineConfig::default();
+/// let mut levels = SignalLevels::for_config(&config);
+/// for block in signal.chunks(1024) {
+///     levels.accumulate(block);
+/// }
+///
+/// // Second pass: clean block by block.
+/// let mut cleaner = StreamingCleaner::new(config, &levels);
+/// let mut cleaned = Vec::new();
+/// for block in signal.chunks(1024) {
+///     cleaner.process(block, &mut cleaned);
+/// }
+/// let summary = cleaner.finish(&mut cleaned);
+///
+/// assert_eq!(cleaned.len(), signal.len());
+/// assert_eq!(summary.detected_impulses[0].peak_index, 4_096);
+/// ```
+pub struct StreamingCleaner {
+    pipeline: Pipeline,
+    levels: SignalLevels,
+    rumble: Option<RumbleFilter>,
+    gain: f32,
+    margin: usize,
+    /// Normalized samples still needed as context or not yet emitted.
+    /// `buffer[0]` is sample `buffer_start` of the stream.
+    buffer: Vec<f32>,
+    buffer_start: usize,
+    /// Absolute index of the next sample to emit.
+    emitted: usize,
+    detected_impulses: Vec<ImpulseEvent>,
+    threshold_envelope: ThresholdEnvelope,
+    restoration: Restoration,
+    wow_flutter: Option<WowTracker>,
+    validation: ValidationResult,
+    output_low_frequency: LowFrequencyMeter,
+    cleanliness: CleanlinessMeter,
+    /// Detections whose repair has not been fully emitted yet, with the
+    /// normalized samples under them.
+    unscored: VecDeque<(ImpulseEvent, Vec<f32>)>,
+    /// Emitted samples still needed to score repairs or as residual
+    /// detection context. `output_tail[0]` is sample `output_tail_start`.
+    output_tail: Vec<f32>,
+    output_tail_start: usize,
+    /// Absolute index up to which the output has been searched for
+    /// residual clicks.
+    residual_checked: usize,
+}
+
+impl StreamingCleaner {
+    /// Creates a cleaner for a signal with the given whole-signal `levels`.
+    ///
+    /// # Panics
+    /// Panics if `config` enables hiss reduction, automatic hum detection or
+    /// wow and flutter measurement on an automatically chosen tone, and
+    /// `levels` were not created with [`SignalLevels::for_config`].
+    pub fn new(config: BaselineConfig, levels: &SignalLevels) -> Self {
+        Self::with_pipeline(Pipeline::new(&config), levels)
+    }
+
+    /// Creates a cleaner that runs the stages of `pipeline`, for a signal
+    /// with the given whole-signal `levels`.
+    ///
+    /// The output matches [`Pipeline::run`] as long as the pipeline's
+    /// detector and repairer honor their declared context (see
+    /// [`ImpulseDetector`](crate::detect::ImpulseDetector)).
+    ///
+    /// # Panics
+    /// Panics if the pipeline's configuration enables hiss reduction,
+    /// automatic hum detection or wow and flutter measurement on an
+    /// automatically chosen tone, and `levels` were not created with
+    /// [`SignalLevels::for_config`].
+    pub fn with_pipeline(pipeline: Pipeline, levels: &SignalLevels) -> Self {
+        let gain = levels.gain(pipeline.config().target_peak);
+        let margin = pipeline.context_margin();
+        let restoration = Restoration::new(pipeline.config(), levels, gain);
+        let config = pipeline.config();
+        let wow_flutter = wow_tracker(config, levels.tone_detector());
+        let rumble = config
+            .rumble
+            .as_ref()
+            .map(|settings| RumbleFilter::new(settings, config.sample_rate));
+        let output_low_frequency = LowFrequencyMeter::new(config.sample_rate);
+        let validation = ValidationResult {
+            input_low_frequency: levels
+                .input_low_frequency()
+                .unwrap_or_default()
+                .scaled(gain),
+            ..ValidationResult::default()
+        };
+
+        Self {
+            pipeline,
+            levels: levels.clone(),
+            rumble,
+            gain,
+            margin,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            emitted: 0,
+            detected_impulses: Vec::new(),
+            threshold_envelope: ThresholdEnvelope::new(),
+            restoration,
+            wow_flutter,
+            validation,
+            output_low_frequency,
+            cleanliness: CleanlinessMeter::default(),
+            unscored: VecDeque::new(),
+            output_tail: Vec::new(),
+            output_tail_start: 0,
+            residual_checked: 0,
+        }
+    }
+
+    /// Maximum number of input samples the cleaner holds back after a call to
+    /// [`StreamingCleaner::process`] returns.
+    ///
+    /// One margin is look-ahead context that detection and repair need; the
+    /// rest is input collected until a window is worth processing.
+    pub fn latency(&self) -> usize {
+        2 * self.margin
+    }
+
+    /// Feeds the next block of input and appends every sample that is now
+    /// final to `output`.
+    ///
+    /// Blocks may have any length, including zero; larger blocks amortize
+    /// the context overhead better.
+    pub fn process(&mut self, block: &[f32], output: &mut Vec<f32>) {
+        let start = self.buffer.len();
+        self.buffer.extend_from_slice(block);
+        if let Some(filter) = &mut self.rumble {
+            filter.process(&mut self.buffer[start..]);
+        }
+        for sample in &mut self.buffer[start..] {
+            *sample *= self.gain;
+        }
+
+        let pending = self.buffer.len() - (self.emitted - self.buffer_start);
+        if pending >= 2 * self.margin {
+            self.run_window(false, output);
+        }
+    }
+
+    /// Flushes the remaining samples into `output` and returns the summary of
+    /// the whole run.
+    pub fn finish(mut self, output: &mut Vec<f32>) -> StreamingSummary {
+        if self.emitted < self.buffer_start + self.buffer.len() {
+            self.run_window(true, output);
+        }
+        self.score_output(true);
+
+        self.validation.output_low_frequency = self.output_low_frequency.levels();
+        StreamingSummary {
+            detected_impulses: self.detected_impulses,
+            threshold_envelope: self.threshold_envelope,
+            decrackle: self.restoration.decrackle_report(),
+            dehum: self.restoration.dehum_report(),
+            dehiss: self.restoration.dehiss_report(),
+            wow_flutter: self.wow_flutter.as_ref().and_then(WowTracker::report),
+            validation: self.validation,
+            cleanliness: self
+                .cleanliness
+                .finish(self.emitted, self.pipeline.config().sample_rate),
+            samples: self.emitted,
+        }
+    }
+
+    /// Runs detection and repair over the buffered window and emits everything
+    /// except the look-ahead margin (or everything, at the end of the stream).
+    fn run_window(&mut self, end_of_stream: bool, output: &mut Vec<f32>) {
+        let context = DetectionContext {
+            levels: &self.levels,
+            gain: self.gain,
+            offset: self.buffer_start,
+        };
+        let detection = self.pipeline.detector().detect(&self.buffer, &context);
+        let repaired = self
+            .pipeline
+            .repairer()
+            .repair(&self.buffer, &detection.events);
+
+        let emit_start = self.emitted - self.buffer_start;
+        let emit_end = if end_of_stream {
+            self.buffer.len()
+        } else {
+            self.buffer.len() - self.margin
+        };
+
+        let restored = self
+            .restoration
+            .apply(repaired, self.buffer_start, emit_start..emit_end);
+        let emitted = &restored[emit_start..emit_end];
+        output.extend_from_slice(emitted);
+        self.validation.accumulate(emitted);
+        self.output_low_frequency.accumulate(emitted);
+        if let Some(tracker) = &mut self.wow_flutter {
+            tracker.accumulate(emitted);
+        }
+        for event in detection
+            .events
+            .iter()
+            .filter(|event| event.peak_index >= emit_start && event.peak_index < emit_end)
+        {
+            let span = &self.buffer[event.start..event.end.min(self.buffer.len())];
+            self.cleanliness.detected(span);
+            let event = event.shifted(self.buffer_start);
+            self.unscored.push_back((event, span.to_vec()));
+            self.detected_impulses.push(event);
+        }
+        self.output_tail.extend_from_slice(emitted);
+        if let Some(threshold) = detection.threshold.get(emit_start..emit_end) {
+            self.threshold_envelope
+                .extend(threshold, self.buffer_start + emit_start);
+        }
+        self.emitted = self.buffer_start + emit_end;
+
+        // Keep one margin of already-emitted samples as look-behind context.
+        let drop = emit_end.saturating_sub(self.margin);
+        self.buffer.drain(..drop);
+        self.buffer_start += drop;
+        self.score_output(end_of_stream);
+    }
+
+    /// Scores the repairs that have been fully emitted, and searches the
+    /// emitted samples for residual clicks, leaving one margin of look-ahead
+    /// unsearched (none at the end of the stream).
+    fn score_output(&mut self, end_of_stream: bool) {
+        while let Some((event, _)) = self.unscored.front() {
+            if event.end > self.emitted && !end_of_stream {
+                break;
+            }
+            let (event, before) = self.unscored.pop_front().expect("checked above");
+            let end = event.end.min(self.emitted);
+            let start = event.start.min(end);
+            let after =
+                &self.output_tail[start - self.output_tail_start..end - self.output_tail_start];
+            self.cleanliness
+                .repaired(&event, &before[..after.len()], after);
+        }
+
+        let end = if end_of_stream {
+            self.emitted
+        } else {
+            self.emitted.saturating_sub(self.margin)
+        };
+        if end > self.residual_checked {
+            let context = DetectionContext {
+                levels: &self.levels,
+                gain: self.gain,
+                offset: self.output_tail_start,
+            };
+            let checked =
+                self.residual_checked - self.output_tail_start..end - self.output_tail_start;
+            let detection = self.pipeline.detector().detect(&self.output_tail, &context);
+            for event in detection
+                .events
+                .iter()
+                .filter(|event| checked.contains(&event.peak_index))
+            {
+                let span_end = event.end.min(self.output_tail.len());
+                self.cleanliness
+                    .residual(&self.output_tail[event.start.min(span_end)..span_end]);
+            }
+            self.residual_checked = end;
+        }
+
+        // Keep one margin of look-behind for the residual search, and
+        // everything under repairs not scored yet.
+        let keep = self
+            .unscored
+            .front()
+            .map_or(usize::MAX, |(event, _)| event.start)
+            .min(self.residual_checked.saturating_sub(self.margin));
+        let drop = keep.saturating_sub(self.output_tail_start);
+        self.output_tail.drain(..drop);
+        self.output_tail_start += drop;
+    }
+}
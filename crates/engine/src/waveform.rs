@@ -0,0 +1,591 @@
+//! Zoomable waveform overviews for drawing long recordings.
+//!
+//! A [`WaveformPyramid`] summarizes a signal as min/max/RMS buckets at
+//! several zoom levels: level 0 holds one bucket per
+//! [`WaveformPyramid::base_bucket`] samples and every further level halves
+//! the number of buckets, down to a single bucket for the whole signal. A
+//! query for any range and number of columns reads the coarsest level that
+//! still has at least one bucket per column, so drawing an hour-long file
+//! costs about as much as drawing a second of it.
+//!
+//! A [`WaveformCache`] keeps the pyramids of the original and the cleaned
+//! signal together with the clicks found in between, and saves them to a
+//! compact binary file: buckets are stored at 16-bit precision, six bytes
+//! each, which comes to about 8 MB per channel for an hour at 44.1 kHz with
+//! the default base bucket of 256 samples.
+
+use std::fs;
+use std::io::{self, BufReader, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::event::ImpulseEvent;
+use crate::io::invalid_data;
+
+/// Default [`WaveformPyramid::base_bucket`]: 256 samples, about 5.8 ms at
+/// 44.1 kHz.
+pub const DEFAULT_BASE_BUCKET: usize = 256;
+
+/// First bytes of a file written by [`WaveformCache::write_to`].
+const MAGIC: &[u8; 4] = b"VWPK";
+/// Version of the cache file format.
+const FORMAT_VERSION: u16 = 1;
+/// Full scale of the stored 16-bit bucket values.
+const FULL_SCALE: f32 = i16::MAX as f32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Extent of the samples in one bucket or column of a waveform.
+pub struct PeakBucket {
+    /// Lowest sample.
+    pub min: f32,
+    /// Highest sample.
+    pub max: f32,
+    /// Root mean square of the samples.
+    pub rms: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A range of a waveform, reduced to a number of columns by
+/// [`WaveformPyramid::peaks`].
+pub struct WaveformPeaks {
+    /// First frame covered.
+    pub start_frame: usize,
+    /// One past the last frame covered.
+    pub end_frame: usize,
+    /// Number of samples per bucket of the level the columns were read
+    /// from. When it exceeds the samples per column, the range is zoomed in
+    /// further than the pyramid resolves and neighboring columns repeat.
+    pub samples_per_bucket: usize,
+    /// The columns of each channel, in channel order.
+    pub channels: Vec<Vec<PeakBucket>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// A [`PeakBucket`] at the 16-bit precision it is stored in.
+struct StoredBucket {
+    min: i16,
+    max: i16,
+    rms: i16,
+}
+
+impl StoredBucket {
+    fn quantize(bucket: PeakBucket) -> Self {
+        let quantize = |value: f32| (value.clamp(-1.0, 1.0) * FULL_SCALE).round() as i16;
+        Self {
+            min: quantize(bucket.min),
+            max: quantize(bucket.max),
+            rms: quantize(bucket.rms),
+        }
+    }
+}
+
+/// A bucket being built: extremes and energy at full precision.
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    min: f32,
+    max: f32,
+    energy: f64,
+    samples: usize,
+}
+
+impl Accumulator {
+    fn of(samples: &[f32]) -> Self {
+        samples.iter().fold(
+            Self {
+                min: f32::INFINITY,
+                max: f32::NEG_INFINITY,
+                energy: 0.0,
+                samples: samples.len(),
+            },
+            |bucket, &sample| Self {
+                min: bucket.min.min(sample),
+                max: bucket.max.max(sample),
+                energy: bucket.energy + f64::from(sample) * f64::from(sample),
+                ..bucket
+            },
+        )
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            energy: self.energy + other.energy,
+            samples: self.samples + other.samples,
+        }
+    }
+
+    fn peak(self) -> PeakBucket {
+        PeakBucket {
+            min: self.min,
+            max: self.max,
+            rms: (self.energy / self.samples.max(1) as f64).sqrt() as f32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Min/max/RMS summary of a multichannel signal at several zoom levels (see
+/// the [module documentation](self)).
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::waveform::WaveformPyramid;
+///
+/// let signal: Vec<f32> = (0..48_000).map(|i| (i as f32 * 0.01).sin() * 0.5).collect();
+/// let pyramid = WaveformPyramid::build(&[&signal], 48_000, 256);
+///
+/// // The whole second in 10 columns, then a tenth of it in 100.
+/// let overview = pyramid.peaks(0..48_000, 10);
+/// assert_eq!(overview.channels[0].len(), 10);
+/// let detail = pyramid.peaks(pyramid.frame_range(0.5, 0.6), 100);
+/// assert!(detail.samples_per_bucket < overview.samples_per_bucket);
+/// ```
+pub struct WaveformPyramid {
+    sample_rate: u32,
+    frames: usize,
+    base_bucket: usize,
+    /// `channels[channel][level][bucket]`.
+    channels: Vec<Vec<Vec<StoredBucket>>>,
+}
+
+impl WaveformPyramid {
+    /// Summarizes `channels`, sampled at `sample_rate`, with `base_bucket`
+    /// samples per bucket at the finest level.
+    ///
+    /// # Panics
+    /// Panics if `base_bucket` is zero or the channels differ in length.
+    pub fn build<C: AsRef<[f32]>>(channels: &[C], sample_rate: u32, base_bucket: usize) -> Self {
+        assert!(
+            base_bucket > 0,
+            "the base bucket must hold at least one sample"
+        );
+        let frames = channels.first().map_or(0, |channel| channel.as_ref().len());
+        assert!(
+            channels
+                .iter()
+                .all(|channel| channel.as_ref().len() == frames),
+            "all channels must have the same length ({frames} samples expected)"
+        );
+
+        let channels = channels
+            .iter()
+            .map(|channel| {
+                let mut level: Vec<Accumulator> = channel
+                    .as_ref()
+                    .chunks(base_bucket)
+                    .map(Accumulator::of)
+                    .collect();
+                let mut levels = vec![quantize(&level)];
+                while level.len() > 1 {
+                    level = level
+                        .chunks(2)
+                        .map(|pair| pair.iter().copied().reduce(Accumulator::merge).unwrap())
+                        .collect();
+                    levels.push(quantize(&level));
+                }
+                levels
+            })
+            .collect();
+
+        Self {
+            sample_rate,
+            frames,
+            base_bucket,
+            channels,
+        }
+    }
+
+    /// Sample rate of the signal, in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of samples per channel of the signal.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Number of channels.
+    pub fn channel_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Number of samples per bucket at the finest level.
+    pub fn base_bucket(&self) -> usize {
+        self.base_bucket
+    }
+
+    /// Number of zoom levels; level `k` has `base_bucket << k` samples per
+    /// bucket.
+    pub fn levels(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+
+    /// The frames from `start_seconds` to `end_seconds`, clamped to the
+    /// signal.
+    pub fn frame_range(&self, start_seconds: f64, end_seconds: f64) -> Range<usize> {
+        let frame = |seconds: f64| {
+            ((seconds.max(0.0) * f64::from(self.sample_rate)).round() as usize).min(self.frames)
+        };
+        let start = frame(start_seconds);
+        start..frame(end_seconds).max(start)
+    }
+
+    /// Reduces `frames` of every channel to `columns` columns.
+    ///
+    /// The range is clamped to the signal, and fewer columns are returned
+    /// if it holds fewer than `columns` frames. Each column covers its share
+    /// of the range, read from the coarsest level that has at least one
+    /// bucket per column.
+    pub fn peaks(&self, frames: Range<usize>, columns: usize) -> WaveformPeaks {
+        let end = frames.end.min(self.frames);
+        let start = frames.start.min(end);
+        let length = end - start;
+        let columns = columns.min(length);
+
+        let mut level = 0;
+        while level + 1 < self.levels() && columns * (self.base_bucket << (level + 1)) <= length {
+            level += 1;
+        }
+        let size = self.base_bucket << level;
+
+        let channels = self
+            .channels
+            .iter()
+            .map(|levels| {
+                let buckets = &levels[level];
+                (0..columns)
+                    .map(|column| {
+                        let from = start + length * column / columns;
+                        let to = start + length * (column + 1) / columns;
+                        let first = from / size;
+                        let last = to.div_ceil(size).min(buckets.len());
+                        combine(&buckets[first..last], first, size, self.frames)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        WaveformPeaks {
+            start_frame: start,
+            end_frame: end,
+            samples_per_bucket: size,
+            channels,
+        }
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let channels = u16::try_from(self.channels.len())
+            .map_err(|_| invalid_data("a waveform cache holds at most 65535 channels"))?;
+        let base_bucket = u32::try_from(self.base_bucket)
+            .map_err(|_| invalid_data("the base bucket is too large for a waveform cache"))?;
+        writer.write_all(&self.sample_rate.to_le_bytes())?;
+        writer.write_all(&(self.frames as u64).to_le_bytes())?;
+        writer.write_all(&base_bucket.to_le_bytes())?;
+        writer.write_all(&channels.to_le_bytes())?;
+        for bucket in self.channels.iter().flatten().flatten() {
+            writer.write_all(&bucket.min.to_le_bytes())?;
+            writer.write_all(&bucket.max.to_le_bytes())?;
+            writer.write_all(&bucket.rms.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let sample_rate = read_u32(reader)?;
+        let frames = usize::try_from(read_u64(reader)?)
+            .map_err(|_| invalid_data("the cached signal is too long for this platform"))?;
+        let base_bucket = read_u32(reader)? as usize;
+        if base_bucket == 0 {
+            return Err(invalid_data("the cached base bucket is empty"));
+        }
+        let channel_count = read_u16(reader)?;
+
+        // The bucket counts follow from the frames; a truncated or corrupt
+        // file fails on the first missing bucket rather than allocating up
+        // front.
+        let mut counts = vec![frames.div_ceil(base_bucket)];
+        while counts[counts.len() - 1] > 1 {
+            counts.push(counts[counts.len() - 1].div_ceil(2));
+        }
+        let mut channels = Vec::new();
+        for _ in 0..channel_count {
+            let mut levels = Vec::with_capacity(counts.len());
+            for &count in &counts {
+                let mut buckets = Vec::new();
+                for _ in 0..count {
+                    buckets.push(StoredBucket {
+                        min: read_u16(reader)? as i16,
+                        max: read_u16(reader)? as i16,
+                        rms: read_u16(reader)? as i16,
+                    });
+                }
+                levels.push(buckets);
+            }
+            channels.push(levels);
+        }
+
+        Ok(Self {
+            sample_rate,
+            frames,
+            base_bucket,
+            channels,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// Which signal of a [`WaveformCache`] to read.
+pub enum WaveformSignal {
+    /// The signal as it was before cleaning.
+    Original,
+    /// The cleaned signal.
+    Repaired,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Waveform pyramids of a recording before and after cleaning, and the
+/// clicks repaired in between.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::waveform::{WaveformCache, WaveformSignal};
+/// use vinyl_engine::{run_baseline_pipeline, BaselineConfig};
+///
+/// let mut signal: Vec<f32> = (0..44_100).map(|i| 0.1 * (i as f32 * 0.02).sin()).collect();
+/// signal[30_000] += 0.8;
+///
+/// let mut cache = WaveformCache::new(&[&signal], 44_100);
+/// let output = run_baseline_pipeline(&signal, &BaselineConfig::default());
+/// cache.set_cleaned(&[&output.repaired], &output.detected_impulses);
+///
+/// let range = cache.original().frame_range(0.6, 0.8);
+/// assert_eq!(cache.clicks(range.clone())[0].peak_index, 30_000);
+/// let after = cache.peaks(WaveformSignal::Repaired, range, 200).unwrap();
+/// assert_eq!(after.channels[0].len(), 200);
+///
+/// let mut bytes = Vec::new();
+/// cache.write_to(&mut bytes)?;
+/// assert_eq!(WaveformCache::read_from(&mut bytes.as_slice())?, cache);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct WaveformCache {
+    original: WaveformPyramid,
+    repaired: Option<WaveformPyramid>,
+    clicks: Vec<ImpulseEvent>,
+}
+
+impl WaveformCache {
+    /// Builds the cache of a recording that was not cleaned yet, with
+    /// [`DEFAULT_BASE_BUCKET`] samples per bucket.
+    ///
+    /// # Panics
+    /// Panics if the channels differ in length.
+    pub fn new<C: AsRef<[f32]>>(original: &[C], sample_rate: u32) -> Self {
+        Self {
+            original: WaveformPyramid::build(original, sample_rate, DEFAULT_BASE_BUCKET),
+            repaired: None,
+            clicks: Vec::new(),
+        }
+    }
+
+    /// Adds the cleaned channels and the clicks repaired in them, e.g.
+    /// [`MultichannelOutput::combined_impulses`](crate::MultichannelOutput::combined_impulses),
+    /// replacing those of an earlier clean.
+    ///
+    /// # Panics
+    /// Panics if the channels differ in length.
+    pub fn set_cleaned<C: AsRef<[f32]>>(&mut self, repaired: &[C], clicks: &[ImpulseEvent]) {
+        self.repaired = Some(WaveformPyramid::build(
+            repaired,
+            self.original.sample_rate,
+            self.original.base_bucket,
+        ));
+        self.clicks = clicks.to_vec();
+        self.clicks.sort_by_key(|click| click.start);
+    }
+
+    /// The pyramid of the signal before cleaning.
+    pub fn original(&self) -> &WaveformPyramid {
+        &self.original
+    }
+
+    /// The pyramid of the cleaned signal, once there is one.
+    pub fn repaired(&self) -> Option<&WaveformPyramid> {
+        self.repaired.as_ref()
+    }
+
+    /// Reduces `frames` of `signal` to `columns` columns per channel, like
+    /// [`WaveformPyramid::peaks`]. Returns `None` for
+    /// [`WaveformSignal::Repaired`] before the recording was cleaned.
+    pub fn peaks(
+        &self,
+        signal: WaveformSignal,
+        frames: Range<usize>,
+        columns: usize,
+    ) -> Option<WaveformPeaks> {
+        let pyramid = match signal {
+            WaveformSignal::Original => Some(&self.original),
+            WaveformSignal::Repaired => self.repaired.as_ref(),
+        };
+        pyramid.map(|pyramid| pyramid.peaks(frames, columns))
+    }
+
+    /// The repaired clicks that overlap `frames`, ordered by position.
+    pub fn clicks(&self, frames: Range<usize>) -> &[ImpulseEvent] {
+        if frames.is_empty() {
+            return &[];
+        }
+        // Clicks never overlap, so their ends are sorted along with their
+        // starts.
+        let first = self
+            .clicks
+            .partition_point(|click| click.end <= frames.start);
+        let last = self
+            .clicks
+            .partition_point(|click| click.start < frames.end);
+        &self.clicks[first..last.max(first)]
+    }
+
+    /// Writes the cache in its binary format.
+    ///
+    /// # Errors
+    /// Passes through errors from `writer`, and returns an
+    /// [`io::ErrorKind::InvalidData`] error for a signal with more than
+    /// 65535 channels.
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        self.original.write_to(writer)?;
+        match &self.repaired {
+            Some(repaired) => {
+                writer.write_all(&[1])?;
+                repaired.write_to(writer)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        writer.write_all(&(self.clicks.len() as u64).to_le_bytes())?;
+        for click in &self.clicks {
+            writer.write_all(&(click.start as u64).to_le_bytes())?;
+            writer.write_all(&(click.end as u64).to_le_bytes())?;
+            writer.write_all(&(click.peak_index as u64).to_le_bytes())?;
+            writer.write_all(&click.amplitude.to_le_bytes())?;
+            writer.write_all(&click.contrast_ratio.to_le_bytes())?;
+            writer.write_all(&click.confidence.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a cache written by [`WaveformCache::write_to`].
+    ///
+    /// # Errors
+    /// Returns an [`io::ErrorKind::InvalidData`] error if the data is not a
+    /// waveform cache or was written by a newer version of the format, and
+    /// an [`io::ErrorKind::UnexpectedEof`] error if it is truncated.
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a waveform cache"));
+        }
+        let version = read_u16(reader)?;
+        if version != FORMAT_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported waveform cache version {version}"
+            )));
+        }
+
+        let original = WaveformPyramid::read_from(reader)?;
+        let mut has_repaired = [0];
+        reader.read_exact(&mut has_repaired)?;
+        let repaired = match has_repaired[0] {
+            0 => None,
+            1 => Some(WaveformPyramid::read_from(reader)?),
+            _ => return Err(invalid_data("corrupt waveform cache")),
+        };
+        let count = read_u64(reader)?;
+        let mut clicks = Vec::new();
+        for _ in 0..count {
+            clicks.push(ImpulseEvent {
+                start: read_u64(reader)? as usize,
+                end: read_u64(reader)? as usize,
+                peak_index: read_u64(reader)? as usize,
+                amplitude: f32::from_bits(read_u32(reader)?),
+                contrast_ratio: f32::from_bits(read_u32(reader)?),
+                confidence: f32::from_bits(read_u32(reader)?),
+            });
+        }
+
+        Ok(Self {
+            original,
+            repaired,
+            clicks,
+        })
+    }
+
+    /// Writes the cache to the file at `path`, under a temporary name first
+    /// like [`write_wav`](crate::io::write_wav).
+    ///
+    /// # Errors
+    /// See [`WaveformCache::write_to`].
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        crate::io::write_atomically(path.as_ref(), |file| self.write_to(file))
+    }
+
+    /// Reads a cache from the file at `path`.
+    ///
+    /// # Errors
+    /// See [`WaveformCache::read_from`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(fs::File::open(path)?))
+    }
+}
+
+fn quantize(level: &[Accumulator]) -> Vec<StoredBucket> {
+    level
+        .iter()
+        .map(|bucket| StoredBucket::quantize(bucket.peak()))
+        .collect()
+}
+
+/// Combines the consecutive stored buckets of one level, starting with
+/// bucket `first`, into one column. Each bucket's RMS is weighted by the
+/// samples it holds, since the last bucket of a level is usually partial.
+fn combine(buckets: &[StoredBucket], first: usize, size: usize, frames: usize) -> PeakBucket {
+    let (mut min, mut max, mut energy, mut samples) = (i16::MAX, i16::MIN, 0.0_f64, 0_usize);
+    for (index, bucket) in (first..).zip(buckets) {
+        let held = size.min(frames - index * size);
+        min = min.min(bucket.min);
+        max = max.max(bucket.max);
+        energy += f64::from(bucket.rms).powi(2) * held as f64;
+        samples += held;
+    }
+    PeakBucket {
+        min: f32::from(min) / FULL_SCALE,
+        max: f32::from(max) / FULL_SCALE,
+        rms: ((energy / samples.max(1) as f64).sqrt() / f64::from(FULL_SCALE)) as f32,
+    }
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
@@ -0,0 +1,547 @@
+//! Wow and flutter: measurement and correction of speed variations.
+//!
+//! An off-centre pressing, a stretched belt or a slipping idler makes the
+//! platter speed vary, and with it the pitch of everything on the record.
+//! Slow variations (below 6 Hz, typically the once-per-revolution wobble of
+//! an eccentric pressing at 0.55 Hz) are heard as wow, faster ones as a
+//! roughness called flutter.
+//!
+//! The speed is measured on a stable tone: the 3150 Hz tone of a test
+//! record, or any steady tonal component of the programme. The tracker
+//! mixes the signal down to the tone's nominal frequency, low-pass filters
+//! the result and reads the instantaneous frequency from the phase advance
+//! of the remaining baseband signal, like the FM discriminator of a
+//! wow-and-flutter meter. Given the measured speed, the signal can be
+//! resampled with a time-varying ratio so that the tone, and the music
+//! around it, is steady again.
+
+use std::f64::consts::{PI, TAU};
+
+use crate::fft::{hann, Fft};
+use crate::rumble::{Cascade, Response};
+
+/// Frequency of the standard wow-and-flutter test tone, in Hz. Test records
+/// carry it, and [`ToneReference::Auto`] prefers it to any other tone.
+pub const TEST_TONE_HZ: f32 = 3150.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+/// Parameters of wow and flutter measurement ([`BaselineConfig::wow_flutter`]).
+///
+/// [`BaselineConfig::wow_flutter`]: crate::BaselineConfig::wow_flutter
+pub struct WowFlutterConfig {
+    /// Tone the speed is measured on.
+    pub reference: ToneReference,
+    /// Largest speed deviation, in percent, that the tracker follows. Wider
+    /// settings let more of the music around the tone into the
+    /// measurement.
+    pub max_deviation_percent: f32,
+    /// Whether the pipeline resamples its output to remove the measured
+    /// speed variations, or only reports them.
+    ///
+    /// [`StreamingCleaner`](crate::StreamingCleaner) only measures: the
+    /// correction needs the speed of the whole signal before it can place
+    /// the first output sample.
+    pub correct: bool,
+}
+
+impl Default for WowFlutterConfig {
+    fn default() -> Self {
+        Self {
+            reference: ToneReference::default(),
+            max_deviation_percent: 2.0,
+            correct: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+/// Tone whose pitch the speed is measured from.
+pub enum ToneReference {
+    #[default]
+    /// The steadiest tone of the whole signal: the [`TEST_TONE_HZ`] tone if
+    /// it stands out, otherwise the most prominent peak of the long-term
+    /// spectrum between 200 Hz and 8 kHz. If no tone stands out clearly,
+    /// nothing is measured.
+    Auto,
+    /// A tone known to be present, at its nominal frequency in Hz.
+    Fixed(f32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Speed variations measured on a signal.
+///
+/// Percentages are relative to the mean frequency of the tracked tone, so a
+/// constant speed error does not count as wow; compare
+/// [`WowFlutterReport::mean_frequency_hz`] with
+/// [`WowFlutterReport::reference_hz`] for that.
+pub struct WowFlutterReport {
+    /// Nominal frequency of the tracked tone, in Hz.
+    pub reference_hz: f32,
+    /// Mean frequency of the tracked tone over the signal, in Hz.
+    pub mean_frequency_hz: f32,
+    /// RMS speed deviation below 6 Hz, in percent.
+    pub wow_percent: f32,
+    /// RMS speed deviation above 6 Hz, in percent.
+    pub flutter_percent: f32,
+    /// Largest speed deviation, in percent.
+    pub peak_deviation_percent: f32,
+    /// Number of samples each entry of [`WowFlutterReport::deviation`]
+    /// covers.
+    pub hop: usize,
+    /// Relative speed deviation over the course of the signal (`0.001` is
+    /// 0.1 % fast): entry `k` is the mean over samples `k * hop` to
+    /// `(k + 1) * hop`. Stretches where the tone is too weak to track are
+    /// zero.
+    pub deviation: Vec<f32>,
+}
+
+/// Rate, in Hz, at which the speed is tracked.
+const TRACK_RATE_HZ: f64 = 1000.0;
+
+/// Highest speed variation rate, in Hz, that the tracker follows.
+const FLUTTER_BAND_HZ: f64 = 200.0;
+
+/// Boundary between wow and flutter, in Hz.
+const WOW_HZ: f64 = 6.0;
+
+/// Time the tracker's filters take to settle, in seconds. The track is not
+/// evaluated before it.
+const SETTLE_SECONDS: f64 = 0.05;
+
+/// Track points where the tone is weaker than this fraction of its median
+/// level are ignored.
+const LEVEL_GATE: f32 = 0.25;
+
+/// Range of the long-term spectrum searched by [`ToneReference::Auto`], in Hz.
+const TONE_RANGE_HZ: (f64, f64) = (200.0, 8000.0);
+
+/// How many times the median power of its neighbourhood a peak needs for
+/// [`ToneReference::Auto`] to pick it.
+const MIN_PROMINENCE: f64 = 100.0;
+
+/// Number of bins on either side of a peak whose median power it is
+/// compared with.
+const NEIGHBOURHOOD_BINS: usize = 100;
+
+/// Relative distance from [`TEST_TONE_HZ`] within which a peak is taken to
+/// be the test tone.
+const TEST_TONE_TOLERANCE: f64 = 0.03;
+
+/// Half-width, in samples, of the resampling kernel.
+const LANCZOS_LOBES: isize = 8;
+
+/// Measures wow and flutter on a complete signal.
+///
+/// # Parameters
+/// - `signal`: Mono samples.
+/// - `sample_rate`: Sample rate of `signal`, in Hz.
+/// - `config`: Which tone to track and how far it may deviate.
+///
+/// # Returns
+/// The measured speed variations, or `None` if [`ToneReference::Auto`]
+/// finds no tone or the tone is never strong enough to track.
+///
+/// # Examples
+/// ```
+/// use vinyl_engine::wow::{measure_wow_flutter, WowFlutterConfig};
+///
+/// // A steady 3150 Hz tone: no wow, no flutter.
+/// let tone: Vec<f32> = (0..48_000)
+///     .map(|i| (i as f32 * std::f32::consts::TAU * 3150.0 / 48_000.0).sin())
+///     .collect();
+/// let report = measure_wow_flutter(&tone, 48_000, &WowFlutterConfig::default()).unwrap();
+/// assert!((report.mean_frequency_hz - 3150.0).abs() < 0.01);
+/// assert!(report.wow_percent < 0.001);
+/// ```
+pub fn measure_wow_flutter(
+    signal: &[f32],
+    sample_rate: u32,
+    config: &WowFlutterConfig,
+) -> Option<WowFlutterReport> {
+    let reference = match config.reference {
+        ToneReference::Fixed(frequency) => frequency,
+        ToneReference::Auto => {
+            let mut detector = ToneDetector::new(sample_rate);
+            detector.accumulate(signal);
+            detector.reference()?
+        }
+    };
+    let mut tracker = WowTracker::new(reference, config, sample_rate);
+    tracker.accumulate(signal);
+    tracker.report()
+}
+
+/// Resamples `signal` with the time-varying ratio that undoes the speed
+/// variations in `report`.
+///
+/// The speed is interpolated linearly between the entries of
+/// [`WowFlutterReport::deviation`] and scaled so that the output keeps the
+/// length of the input; a constant speed error is left alone. Samples are
+/// interpolated with a Lanczos kernel of eight lobes.
+///
+/// # Parameters
+/// - `signal`: The signal `report` was measured on (or another channel of
+///   the same recording).
+/// - `report`: Speed variations measured with [`measure_wow_flutter`].
+///
+/// # Returns
+/// The corrected signal, as long as `signal`.
+pub fn correct_wow_flutter(signal: &[f32], report: &WowFlutterReport) -> Vec<f32> {
+    let speed: Vec<f64> = (0..signal.len())
+        .map(|index| 1.0 + deviation_at(report, index))
+        .collect();
+    let total: f64 = speed.iter().sum();
+    if total <= 0.0 {
+        return signal.to_vec();
+    }
+    let scale = signal.len() as f64 / total;
+
+    // Input sample `index` spans original time `time` to `time + step`.
+    let mut index = 0;
+    let mut time = 0.0;
+    (0..signal.len())
+        .map(|target| {
+            let target = target as f64;
+            while index + 1 < signal.len() && time + speed[index] * scale <= target {
+                time += speed[index] * scale;
+                index += 1;
+            }
+            let position = index as f64 + (target - time) / (speed[index] * scale);
+            interpolate(signal, position)
+        })
+        .collect()
+}
+
+/// Speed deviation at sample `index`, interpolated between the centres of
+/// the track points.
+fn deviation_at(report: &WowFlutterReport, index: usize) -> f64 {
+    let Some(&last) = report.deviation.last() else {
+        return 0.0;
+    };
+    let point = (index as f64 + 0.5) / report.hop.max(1) as f64 - 0.5;
+    if point <= 0.0 {
+        return f64::from(report.deviation[0]);
+    }
+    let before = point as usize;
+    if before + 1 >= report.deviation.len() {
+        return f64::from(last);
+    }
+    let fraction = point - before as f64;
+    let (a, b) = (
+        f64::from(report.deviation[before]),
+        f64::from(report.deviation[before + 1]),
+    );
+    a + (b - a) * fraction
+}
+
+/// Value of `signal` at a fractional `position`, with the signal held at its
+/// end values beyond its ends.
+fn interpolate(signal: &[f32], position: f64) -> f32 {
+    let last = signal.len() as isize - 1;
+    let base = position.floor();
+    let fraction = position - base;
+    let base = base as isize;
+    if fraction == 0.0 {
+        return signal[base.clamp(0, last) as usize];
+    }
+
+    let mut sum = 0.0;
+    let mut weights = 0.0;
+    for tap in (1 - LANCZOS_LOBES)..=LANCZOS_LOBES {
+        let distance = tap as f64 - fraction;
+        let weight = sinc(distance) * sinc(distance / LANCZOS_LOBES as f64);
+        sum += weight * f64::from(signal[(base + tap).clamp(0, last) as usize]);
+        weights += weight;
+    }
+    (sum / weights) as f32
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Finds the reference tone for [`ToneReference::Auto`] in the long-term
+/// spectrum, built from blocks aligned to the start of the signal.
+pub(crate) struct ToneDetector {
+    sample_rate: f64,
+    fft: Fft,
+    window: Vec<f64>,
+    /// Samples of the current, incomplete block.
+    pending: Vec<f32>,
+    /// Power accumulated in each bin.
+    power: Vec<f64>,
+}
+
+impl ToneDetector {
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        // Blocks of about a quarter of a second resolve tones a few hertz
+        // apart.
+        let length = (sample_rate as usize / 4).max(256).next_power_of_two();
+        Self {
+            sample_rate: f64::from(sample_rate),
+            fft: Fft::new(length),
+            window: hann(length),
+            pending: Vec::with_capacity(length),
+            power: vec![0.0; length / 2 + 1],
+        }
+    }
+
+    pub(crate) fn accumulate(&mut self, mut block: &[f32]) {
+        let length = self.fft.len();
+        let mut re = vec![0.0; length];
+        let mut im = vec![0.0; length];
+        while !block.is_empty() {
+            let take = (length - self.pending.len()).min(block.len());
+            self.pending.extend_from_slice(&block[..take]);
+            block = &block[take..];
+            if self.pending.len() == length {
+                for ((re, im), (&sample, &window)) in re
+                    .iter_mut()
+                    .zip(im.iter_mut())
+                    .zip(self.pending.iter().zip(&self.window))
+                {
+                    *re = f64::from(sample) * window;
+                    *im = 0.0;
+                }
+                self.fft.transform(&mut re, &mut im, false);
+                for (bin, power) in self.power.iter_mut().enumerate() {
+                    *power += re[bin] * re[bin] + im[bin] * im[bin];
+                }
+                self.pending.clear();
+            }
+        }
+    }
+
+    /// Frequency of the tone to track, if one stands out clearly.
+    pub(crate) fn reference(&self) -> Option<f32> {
+        let bin_hz = self.sample_rate / self.fft.len() as f64;
+        let bins = self.power.len();
+        let first = ((TONE_RANGE_HZ.0 / bin_hz).ceil() as usize).max(1);
+        let last = ((TONE_RANGE_HZ.1.min(0.45 * self.sample_rate) / bin_hz) as usize).min(bins - 2);
+
+        let mut best: Option<(f64, f64)> = None;
+        let mut test_tone: Option<(f64, f64)> = None;
+        let mut neighbourhood = Vec::with_capacity(2 * NEIGHBOURHOOD_BINS + 1);
+        for bin in first..=last {
+            let power = self.power[bin];
+            if power <= self.power[bin - 1] || power < self.power[bin + 1] {
+                continue;
+            }
+            neighbourhood.clear();
+            neighbourhood.extend_from_slice(
+                &self.power[bin.saturating_sub(NEIGHBOURHOOD_BINS).max(1)
+                    ..(bin + NEIGHBOURHOOD_BINS + 1).min(bins)],
+            );
+            let middle = neighbourhood.len() / 2;
+            let median = *neighbourhood
+                .select_nth_unstable_by(middle, f64::total_cmp)
+                .1;
+            let prominence = if median > 0.0 {
+                power / median
+            } else {
+                f64::INFINITY
+            };
+            if prominence < MIN_PROMINENCE {
+                continue;
+            }
+
+            let frequency = (bin as f64 + self.peak_offset(bin)) * bin_hz;
+            let candidate = Some((prominence, frequency));
+            if best.is_none_or(|(strongest, _)| prominence > strongest) {
+                best = candidate;
+            }
+            let test_tone_distance = (frequency / f64::from(TEST_TONE_HZ) - 1.0).abs();
+            if test_tone_distance <= TEST_TONE_TOLERANCE
+                && test_tone.is_none_or(|(strongest, _)| prominence > strongest)
+            {
+                test_tone = candidate;
+            }
+        }
+        test_tone.or(best).map(|(_, frequency)| frequency as f32)
+    }
+
+    /// Position of the true peak relative to the centre of peak bin `bin`,
+    /// from a parabola through the log power of the bin and its neighbours.
+    fn peak_offset(&self, bin: usize) -> f64 {
+        let [before, peak, after] =
+            [bin - 1, bin, bin + 1].map(|bin| self.power[bin].max(f64::MIN_POSITIVE).ln());
+        let curvature = before - 2.0 * peak + after;
+        if curvature < 0.0 {
+            (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Tracks the frequency of a tone sample by sample.
+///
+/// Every step is causal and positions are counted from the start of the
+/// signal, so any split of a signal into blocks gives the same track.
+pub(crate) struct WowTracker {
+    reference: f64,
+    sample_rate: f64,
+    hop: usize,
+    /// Low-pass filters of the real and imaginary parts of the mixed-down
+    /// signal.
+    lowpass: [Cascade; 2],
+    /// Number of samples seen so far.
+    position: usize,
+    /// Baseband signal at the end of the previous hop.
+    previous: (f64, f64),
+    /// Frequency and level of the tone over each completed hop.
+    track: Vec<(f32, f32)>,
+}
+
+impl WowTracker {
+    /// Prepares to track the tone at `reference` Hz.
+    pub(crate) fn new(reference: f32, config: &WowFlutterConfig, sample_rate: u32) -> Self {
+        let rate = f64::from(sample_rate.max(1));
+        let reference = f64::from(reference);
+        let bandwidth = (reference * f64::from(config.max_deviation_percent.max(0.0)) / 100.0
+            + FLUTTER_BAND_HZ)
+            .min(0.45 * rate);
+        let lowpass = || Cascade::butterworth(4, bandwidth, sample_rate, Response::LowPass);
+        Self {
+            reference,
+            sample_rate: rate,
+            hop: ((rate / TRACK_RATE_HZ).round() as usize).max(1),
+            lowpass: [lowpass(), lowpass()],
+            position: 0,
+            previous: (0.0, 0.0),
+            track: Vec::new(),
+        }
+    }
+
+    pub(crate) fn accumulate(&mut self, block: &[f32]) {
+        let omega = TAU * self.reference / self.sample_rate;
+        for &sample in block {
+            let (sin, cos) = (omega * self.position as f64).sin_cos();
+            let sample = f64::from(sample);
+            let re = self.lowpass[0].process(sample * cos);
+            let im = self.lowpass[1].process(-sample * sin);
+            self.position += 1;
+
+            if self.position.is_multiple_of(self.hop) {
+                // Phase advance over the hop: the argument of z * conj(previous).
+                let (previous_re, previous_im) = self.previous;
+                let advance = (im * previous_re - re * previous_im)
+                    .atan2(re * previous_re + im * previous_im);
+                let frequency =
+                    self.reference + advance * self.sample_rate / (TAU * self.hop as f64);
+                self.track.push((frequency as f32, re.hypot(im) as f32));
+                self.previous = (re, im);
+            }
+        }
+    }
+
+    /// Evaluates the track gathered so far.
+    pub(crate) fn report(&self) -> Option<WowFlutterReport> {
+        let settle = (SETTLE_SECONDS * self.sample_rate / self.hop as f64).ceil() as usize;
+        let mut levels: Vec<f32> = self
+            .track
+            .iter()
+            .skip(settle)
+            .map(|&(_, level)| level)
+            .collect();
+        if levels.is_empty() {
+            return None;
+        }
+        let middle = levels.len() / 2;
+        let gate = LEVEL_GATE * *levels.select_nth_unstable_by(middle, f32::total_cmp).1;
+        let valid: Vec<bool> = self
+            .track
+            .iter()
+            .enumerate()
+            .map(|(index, &(_, level))| index >= settle && level > 0.0 && level >= gate)
+            .collect();
+        let count = valid.iter().filter(|&&valid| valid).count();
+        if count == 0 {
+            return None;
+        }
+
+        let mean = self
+            .track
+            .iter()
+            .zip(&valid)
+            .filter(|(_, &valid)| valid)
+            .map(|(&(frequency, _), _)| f64::from(frequency))
+            .sum::<f64>()
+            / count as f64;
+        let deviation: Vec<f64> = self
+            .track
+            .iter()
+            .zip(&valid)
+            .map(|(&(frequency, _), &valid)| {
+                if valid {
+                    f64::from(frequency) / mean - 1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let wow = self.wow_component(&deviation);
+
+        let mut wow_sum_sq = 0.0;
+        let mut flutter_sum_sq = 0.0;
+        let mut peak: f64 = 0.0;
+        for ((&deviation, &wow), _) in deviation
+            .iter()
+            .zip(&wow)
+            .zip(&valid)
+            .filter(|(_, &valid)| valid)
+        {
+            wow_sum_sq += wow * wow;
+            flutter_sum_sq += (deviation - wow) * (deviation - wow);
+            peak = peak.max(deviation.abs());
+        }
+        let percent_rms = |sum_sq: f64| (100.0 * (sum_sq / count as f64).sqrt()) as f32;
+
+        Some(WowFlutterReport {
+            reference_hz: self.reference as f32,
+            mean_frequency_hz: mean as f32,
+            wow_percent: percent_rms(wow_sum_sq),
+            flutter_percent: percent_rms(flutter_sum_sq),
+            peak_deviation_percent: (100.0 * peak) as f32,
+            hop: self.hop,
+            deviation: deviation.iter().map(|&value| value as f32).collect(),
+        })
+    }
+
+    /// The part of `deviation` below [`WOW_HZ`], from a zero-phase low-pass
+    /// filter run over the whole track.
+    fn wow_component(&self, deviation: &[f64]) -> Vec<f64> {
+        let Some(&first) = deviation.first() else {
+            return Vec::new();
+        };
+        // The track is sampled once per hop; scaling the cutoff by the hop
+        // designs the filter at the track's rate.
+        let mut lowpass = Cascade::butterworth(
+            2,
+            WOW_HZ * self.hop as f64,
+            self.sample_rate as u32,
+            Response::LowPass,
+        );
+        let mut wow = deviation.to_vec();
+        lowpass.settle(first);
+        for value in wow.iter_mut() {
+            *value = lowpass.process(*value);
+        }
+        lowpass.settle(wow[wow.len() - 1]);
+        for value in wow.iter_mut().rev() {
+            *value = lowpass.process(*value);
+        }
+        wow
+    }
+}
@@ -0,0 +1,196 @@
+use vinyl_engine::{
+    run_baseline_pipeline, transient_preservation, try_run_multichannel_pipeline, try_run_pipeline,
+    try_transient_preservation, BaselineConfig, DehumConfig, Error, HumFundamental, PresetRegistry,
+    RepairMode, RumbleConfig, ThresholdLevel,
+};
+
+fn signal() -> Vec<f32> {
+    let mut signal: Vec<f32> = (0..4_000).map(|i| 0.1 * (i as f32 * 0.02).sin()).collect();
+    signal[1_000] += 0.9;
+    signal
+}
+
+fn setting_error(config: &BaselineConfig) -> (String, String) {
+    match try_run_pipeline(&signal(), config) {
+        Err(Error::InvalidConfig { setting, message }) => (setting, message),
+        other => panic!("expected an invalid setting, got {other:?}"),
+    }
+}
+
+#[test]
+fn valid_input_runs_like_the_infallible_pipeline() {
+    let config = BaselineConfig::standard();
+    let output = try_run_pipeline(&signal(), &config).unwrap();
+    let expected = run_baseline_pipeline(&signal(), &config);
+    assert_eq!(output.repaired, expected.repaired);
+    assert_eq!(output.detected_impulses, expected.detected_impulses);
+
+    for preset in PresetRegistry::default().iter() {
+        assert_eq!(preset.config.validate(), Ok(()), "{}", preset.id);
+    }
+}
+
+#[test]
+fn out_of_range_settings_are_named() {
+    let (setting, message) = setting_error(&BaselineConfig {
+        diff_threshold: -0.1,
+        ..BaselineConfig::default()
+    });
+    assert_eq!(setting, "diff_threshold");
+    assert_eq!(message, "must not be negative, got -0.1");
+
+    let (setting, _) = setting_error(&BaselineConfig {
+        impulse_abs_min: f32::NAN,
+        ..BaselineConfig::default()
+    });
+    assert_eq!(setting, "impulse_abs_min");
+
+    let (setting, message) = setting_error(&BaselineConfig {
+        threshold_level: ThresholdLevel::Windowed {
+            window_ms: 370.0,
+            percentile: 140.0,
+        },
+        ..BaselineConfig::default()
+    });
+    assert_eq!(setting, "threshold_level.percentile");
+    assert_eq!(message, "must be between 0 and 100, got 140");
+
+    let (setting, _) = setting_error(&BaselineConfig {
+        repair_mode: RepairMode::Autoregressive {
+            order: 32,
+            context: 16,
+        },
+        ..BaselineConfig::default()
+    });
+    assert_eq!(setting, "repair_mode.context");
+
+    let (setting, message) = setting_error(&BaselineConfig {
+        sample_rate: 8_000,
+        rumble: Some(RumbleConfig {
+            cutoff_hz: 5_000.0,
+            ..RumbleConfig::default()
+        }),
+        ..BaselineConfig::default()
+    });
+    assert_eq!(setting, "rumble.cutoff_hz");
+    assert_eq!(message, "must be between 0 and 4000, got 5000");
+
+    let (setting, _) = setting_error(&BaselineConfig {
+        dehum: Some(DehumConfig {
+            fundamental: HumFundamental::Fixed(0.0),
+            ..DehumConfig::default()
+        }),
+        ..BaselineConfig::default()
+    });
+    assert_eq!(setting, "dehum.fundamental");
+}
+
+#[test]
+fn unsupported_sample_rates_are_rejected() {
+    for sample_rate in [0, 4_000, 768_000] {
+        let config = BaselineConfig {
+            sample_rate,
+            ..BaselineConfig::default()
+        };
+        let error = try_run_pipeline(&signal(), &config).unwrap_err();
+        assert_eq!(error, Error::UnsupportedSampleRate { sample_rate });
+    }
+    let error = BaselineConfig {
+        sample_rate: 4_000,
+        ..BaselineConfig::default()
+    }
+    .validate()
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "unsupported sample rate 4000 Hz: expected 8000 to 384000 Hz"
+    );
+}
+
+#[test]
+fn non_finite_input_is_rejected_with_its_position() {
+    let config = BaselineConfig::default();
+    let mut samples = signal();
+    samples[1_234] = f32::INFINITY;
+    let error = try_run_pipeline(&samples, &config).unwrap_err();
+    assert_eq!(
+        error,
+        Error::NonFiniteInput {
+            channel: 0,
+            index: 1_234,
+            value: f32::INFINITY,
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "input sample 1234 of channel 0 is inf: the input must be finite"
+    );
+
+    let left = signal();
+    let mut right = signal();
+    right[7] = f32::NAN;
+    let error = try_run_multichannel_pipeline(&[&left, &right], &config).unwrap_err();
+    assert!(matches!(
+        error,
+        Error::NonFiniteInput {
+            channel: 1,
+            index: 7,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn channels_of_different_lengths_are_rejected() {
+    let left = signal();
+    let right = &left[..3_000];
+    let error =
+        try_run_multichannel_pipeline(&[&left[..], right], &BaselineConfig::default()).unwrap_err();
+    assert_eq!(
+        error,
+        Error::ChannelLengthMismatch {
+            channel: 1,
+            expected: 4_000,
+            found: 3_000,
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "channel 1 has 3000 samples, but channel 0 has 4000: all channels must have the same length"
+    );
+
+    let output = try_run_multichannel_pipeline(&[&left, &left], &BaselineConfig::default());
+    assert_eq!(output.unwrap().channels.len(), 2);
+}
+
+#[test]
+fn inputs_without_channels_are_rejected() {
+    let channels: [&[f32]; 0] = [];
+    let error = try_run_multichannel_pipeline(&channels, &BaselineConfig::default()).unwrap_err();
+    assert_eq!(error, Error::NoChannels);
+    assert_eq!(error.to_string(), "the input has no channels");
+}
+
+#[test]
+fn shorter_repaired_signals_are_rejected_by_the_metrics() {
+    let original = signal();
+    let regions = [(900, 1_100)];
+    let error = try_transient_preservation(&original, &original[..3_000], &regions).unwrap_err();
+    assert_eq!(
+        error,
+        Error::LengthMismatch {
+            expected: 4_000,
+            found: 3_000,
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "the signal has 3000 samples, but the signal it is compared with has 4000"
+    );
+
+    let score = try_transient_preservation(&original, &original, &regions);
+    assert_eq!(
+        score,
+        Ok(transient_preservation(&original, &original, &regions))
+    );
+}
@@ -1,5 +1,9 @@
 use vinyl_engine::{
-    click_precision_recall, run_baseline_pipeline, transient_preservation, BaselineConfig,
+    click_precision_recall, run_baseline_pipeline, snr_improvement, transient_preservation,
+    BaselineConfig, DecrackleConfig, DehissConfig, DehumConfig, DetectionMode, HumFundamental,
+    measure_wow_flutter, NoiseSource, RepairMode, ResidualConfig, RumbleConfig, ThresholdLevel,
+    Aggressiveness, PresetRegistry, TimeTolerance, ToneReference, WowFlutterConfig, analyze,
+    CleanlinessBand, ClickMetrics, ImpulseEvent, ImpulseRepairer, Pipeline,
 };
 
 struct TestClip {
@@ -7,6 +11,11 @@ struct TestClip {
     samples: Vec<f32>,
     impulses: Vec<usize>,
     transients: Vec<(usize, usize)>,
+    /// Clicks from barely above the music to far above it, among sharp
+    /// attacks: no configuration gets them all right, so the clip measures
+    /// the trade-off between presets rather than meeting the thresholds of
+    /// [`assert_corpus_quality`].
+    graded: bool,
 }
 
 fn generate_corpus() -> Vec<TestClip> {
@@ -28,11 +37,12 @@ fn generate_corpus() -> Vec<TestClip> {
         samples: sine_with_clicks,
         impulses,
         transients: Vec::new(),
+        graded: false,
     });
 
     let mut burst = vec![0.0_f32; 2048];
-    for i in 900..980 {
-        burst[i] = (1.0 - ((i - 900) as f32 / 80.0)) * 0.7;
+    for (offset, sample) in burst[900..980].iter_mut().enumerate() {
+        *sample = (1.0 - (offset as f32 / 80.0)) * 0.7;
     }
     let mut burst_with_clicks = burst.clone();
     let impulses = vec![200, 1300];
@@ -44,6 +54,7 @@ fn generate_corpus() -> Vec<TestClip> {
         samples: burst_with_clicks,
         impulses,
         transients: vec![(900, 980)],
+        graded: false,
     });
 
     // Edge case: Empty signal
@@ -52,6 +63,7 @@ fn generate_corpus() -> Vec<TestClip> {
         samples: Vec::new(),
         impulses: Vec::new(),
         transients: Vec::new(),
+        graded: false,
     });
 
     // Edge case: Single sample signal (len < 3, no detection possible)
@@ -60,6 +72,7 @@ fn generate_corpus() -> Vec<TestClip> {
         samples: vec![0.5],
         impulses: Vec::new(),
         transients: Vec::new(),
+        graded: false,
     });
 
     // Edge case: Two sample signal (len < 3, no detection possible)
@@ -68,6 +81,7 @@ fn generate_corpus() -> Vec<TestClip> {
         samples: vec![0.3, 0.8],
         impulses: Vec::new(),
         transients: Vec::new(),
+        graded: false,
     });
 
     // Edge case: All-zero signal
@@ -76,6 +90,7 @@ fn generate_corpus() -> Vec<TestClip> {
         samples: vec![0.0; 512],
         impulses: Vec::new(),
         transients: Vec::new(),
+        graded: false,
     });
 
     // Edge case: Near-zero amplitude signal
@@ -84,10 +99,12 @@ fn generate_corpus() -> Vec<TestClip> {
         samples: vec![0.001; 512],
         impulses: Vec::new(),
         transients: Vec::new(),
+        graded: false,
     });
 
-    // Edge case: Consecutive impulses (Note: neighbor-based detection cannot detect
-    // all samples in a consecutive run, only peaks. This test validates graceful handling)
+    // Edge case: Consecutive impulses (Note: neighbor-based detection only triggers on
+    // the peak of a consecutive run; region growing then extends the event over the
+    // rest of the group. This test validates graceful handling)
     let mut consecutive_impulses_signal = vec![0.15; 512];
     // Create impulse groups where the middle one is highest (detectable as local peak)
     consecutive_impulses_signal[99] = 0.3;
@@ -101,6 +118,7 @@ fn generate_corpus() -> Vec<TestClip> {
         samples: consecutive_impulses_signal,
         impulses: vec![100, 200], // Only the peaks of each group
         transients: Vec::new(),
+        graded: false,
     });
 
     // Edge case: Impulses near edges (but not at index 0 or len-1, which cannot be detected)
@@ -114,44 +132,886 @@ fn generate_corpus() -> Vec<TestClip> {
         samples: edge_impulses_signal,
         impulses,
         transients: Vec::new(),
+        graded: false,
+    });
+
+    // Two seconds of swelling music over hiss, long enough to learn a hiss
+    // profile from its quiet stretches, with clear clicks.
+    let (samples, impulses) = music_over_hiss();
+    clips.push(TestClip {
+        name: "music_over_hiss",
+        samples,
+        impulses,
+        transients: Vec::new(),
+        graded: false,
+    });
+
+    let (samples, impulses, transients) = graded_clicks_and_attacks();
+    clips.push(TestClip {
+        name: "graded_clicks_and_attacks",
+        samples,
+        impulses,
+        transients,
+        graded: true,
     });
 
     clips
 }
 
+/// Two seconds of music swelling in and out over uniform hiss, with a click
+/// of either polarity every 14_000 samples. Returns the samples and the
+/// click positions.
+fn music_over_hiss() -> (Vec<f32>, Vec<usize>) {
+    let rate = 44_100.0;
+    let tau = std::f32::consts::TAU;
+    let mut state = 11_u32;
+    let mut samples: Vec<f32> = (0..88_200)
+        .map(|i| {
+            let t = i as f32 / rate;
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let hiss = (state >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0;
+            let swell = 0.5 - 0.5 * (t * 0.5 * tau).cos();
+            let music = 0.2 * (t * 330.0 * tau).sin() + 0.05 * (t * 660.0 * tau).sin();
+            swell * music + 0.002 * hiss
+        })
+        .collect();
+    let clicks: Vec<usize> = (0..6).map(|n| 4_000 + n * 14_000).collect();
+    for (n, &at) in clicks.iter().enumerate() {
+        samples[at] += if n % 2 == 0 { 0.8 } else { -0.8 };
+    }
+    (samples, clicks)
+}
+
+/// Music with sharp drum-like attacks and clicks of graded amplitude, from
+/// barely above the music to far above it. Returns the samples, the click
+/// positions and the spans of the attacks.
+fn graded_clicks_and_attacks() -> (Vec<f32>, Vec<usize>, Vec<(usize, usize)>) {
+    let rate = 44_100.0;
+    let tau = std::f32::consts::TAU;
+    let mut samples: Vec<f32> = (0..88_200)
+        .map(|i| {
+            let t = i as f32 / rate;
+            0.1 * (t * 196.0 * tau).sin() + 0.05 * (t * 523.0 * tau).sin()
+        })
+        .collect();
+    let mut attacks = Vec::new();
+    for attack in 0..12 {
+        let at = 3_000 + attack * 7_000;
+        let level = 0.3 + 0.05 * attack as f32;
+        for k in 0..400 {
+            let t = k as f32 / rate;
+            samples[at + k] += level * (-(k as f32) / 60.0).exp() * (t * 2_500.0 * tau).cos();
+        }
+        attacks.push((at, at + 400));
+    }
+    let mut clicks = Vec::new();
+    for click in 0..24 {
+        let at = 1_500 + click * 3_500;
+        let amplitude = 0.1 + 0.04 * click as f32;
+        samples[at] += if click % 2 == 0 { amplitude } else { -amplitude };
+        clicks.push(at);
+    }
+    (samples, clicks, attacks)
+}
+
+// Allow detected impulses to deviate by ±1 sample from the ground truth.
+// This tight tolerance is appropriate for the synthetic clips defined in `generate_corpus`.
+const CLICK_TOLERANCE_SAMPLES: usize = 1;
+
+/// Runs every clip of the corpus but the graded one through the pipeline
+/// with `config` and checks detection accuracy and transient preservation.
+fn assert_corpus_quality(config: &BaselineConfig) {
+    for clip in generate_corpus().iter().filter(|clip| !clip.graded) {
+        assert_clip_quality(clip, config);
+    }
+}
+
+/// Runs `clip` through the pipeline with `config` and checks detection
+/// accuracy and transient preservation.
+fn assert_clip_quality(clip: &TestClip, config: &BaselineConfig) {
+    let output = run_baseline_pipeline(&clip.samples, config);
+    let metrics =
+        click_precision_recall(&output.detected_impulses, &clip.impulses, CLICK_TOLERANCE_SAMPLES);
+    let transient_score = transient_preservation(&clip.samples, &output.repaired, &clip.transients);
+
+    assert!(
+        metrics.recall >= 0.8,
+        "{} recall below threshold: {:.2}",
+        clip.name,
+        metrics.recall
+    );
+    assert!(
+        metrics.precision >= 0.8,
+        "{} precision below threshold: {:.2}",
+        clip.name,
+        metrics.precision
+    );
+    assert!(
+        transient_score >= 0.9,
+        "{} transient preservation below threshold: {:.2}",
+        clip.name,
+        transient_score
+    );
+}
+
 #[test]
 fn baseline_pipeline_meets_quality_thresholds() {
-    let config = BaselineConfig::default();
-    let corpus = generate_corpus();
+    assert_corpus_quality(&BaselineConfig::default());
+}
+
+#[test]
+fn residual_detector_meets_quality_thresholds() {
+    let config = BaselineConfig {
+        detection_mode: DetectionMode::Residual(ResidualConfig::default()),
+        ..BaselineConfig::default()
+    };
+    assert_corpus_quality(&config);
+}
+
+#[test]
+fn detected_events_describe_each_click() {
+    let mut samples = vec![0.1_f32; 1024];
+    samples[300] = 0.9;
+    samples[700] = -0.75;
+
+    let output = run_baseline_pipeline(&samples, &BaselineConfig::default());
+    assert_eq!(output.detected_impulses.len(), 2);
 
-    // Allow detected impulses to deviate by ±1 sample from the ground truth.
-    // This tight tolerance is appropriate for the synthetic clips defined in `generate_corpus`.
-    let click_tolerance_samples: usize = 1;
+    let (loud, quiet) = (output.detected_impulses[0], output.detected_impulses[1]);
+    assert_eq!((loud.start, loud.end, loud.peak_index), (300, 301, 300));
+    assert!(loud.amplitude > 0.0 && quiet.amplitude < 0.0);
+    assert!(loud.contrast_ratio > quiet.contrast_ratio);
+    assert!(loud.confidence > quiet.confidence);
+    assert!((0.0..=1.0).contains(&quiet.confidence));
 
-    for clip in corpus {
-        let output = run_baseline_pipeline(&clip.samples, &config);
-        let metrics =
-            click_precision_recall(&output.detected_impulses, &clip.impulses, click_tolerance_samples);
-        let transient_score =
-            transient_preservation(&clip.samples, &output.repaired, &clip.transients);
+    // Events are accepted directly by the metrics, matching anywhere in their span.
+    let metrics = click_precision_recall(&output.detected_impulses, &[300, 701], 1);
+    assert_eq!((metrics.recall, metrics.precision), (1.0, 1.0));
+}
+
+/// Adds a decaying, ringing pop of roughly `5 * decay` samples starting at `at`.
+fn add_pop(samples: &mut [f32], at: usize, amplitude: f32, decay: f32) {
+    for (k, sample) in samples[at..].iter_mut().enumerate().take((decay * 6.0) as usize) {
+        *sample += amplitude * (-(k as f32) / decay).exp() * (2.0 * k as f32).cos();
+    }
+}
+
+#[test]
+fn wide_pops_are_repaired_over_their_full_span() {
+    let clean: Vec<f32> = (0..20_000).map(|i| 0.2 * (i as f32 * 0.004).sin()).collect();
+    let mut samples = clean.clone();
+    add_pop(&mut samples, 5_000, 1.0, 40.0);
+    add_pop(&mut samples, 12_000, -0.8, 25.0);
+
+    let output = run_baseline_pipeline(&samples, &BaselineConfig::default());
 
+    assert_eq!(output.detected_impulses.len(), 2);
+    let pops = [(5_000, 40.0_f32), (12_000, 25.0)];
+    for (event, &(onset, decay)) in output.detected_impulses.iter().zip(&pops) {
+        assert_eq!(event.peak_index, onset);
         assert!(
-            metrics.recall >= 0.8,
-            "{} recall below threshold: {:.2}",
-            clip.name,
-            metrics.recall
+            event.len() as f32 >= decay * 3.0,
+            "pop at {onset} only covered {} samples",
+            event.len()
         );
+    }
+
+    // Compare against the clean signal at the same gain: almost all of each
+    // pop's energy must be gone, not just its peak sample.
+    let peak = samples.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+    let gain = BaselineConfig::default().target_peak / peak;
+    for (onset, decay) in pops {
+        let region = onset..onset + (decay * 6.0) as usize;
+        let energy = |signal: &[f32]| -> f32 {
+            region
+                .clone()
+                .map(|i| (signal[i] - clean[i] * gain).powi(2))
+                .sum()
+        };
+        let before = energy(&output.normalized);
+        let after = energy(&output.repaired);
         assert!(
-            metrics.precision >= 0.8,
-            "{} precision below threshold: {:.2}",
-            clip.name,
-            metrics.precision
+            after < before * 0.05,
+            "pop at {onset} kept {:.1}% of its energy",
+            100.0 * after / before
         );
+    }
+}
+
+#[test]
+fn autoregressive_repair_preserves_tonal_content_across_wide_gaps() {
+    let clean: Vec<f32> = (0..20_000)
+        .map(|i| {
+            let t = i as f32;
+            0.2 * (t * 0.03).sin() + 0.1 * (t * 0.071).sin() + 0.05 * (t * 0.13).sin()
+        })
+        .collect();
+    // Bursts of full-scale ringing that stop abruptly: the whole burst has to
+    // be replaced, leaving gaps of 60 to 200 samples.
+    let mut samples = clean.clone();
+    let regions = [(4_000, 4_060), (9_000, 9_120), (15_000, 15_200)];
+    for &(start, end) in &regions {
+        for (k, sample) in samples[start..end].iter_mut().enumerate() {
+            *sample += (2.0 * k as f32).cos();
+        }
+    }
+
+    let peak = samples.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+    let linear_config = BaselineConfig::default();
+    let reference: Vec<f32> = clean
+        .iter()
+        .map(|s| s * linear_config.target_peak / peak)
+        .collect();
+
+    let linear = run_baseline_pipeline(&samples, &linear_config);
+    let ar_config = BaselineConfig {
+        repair_mode: RepairMode::autoregressive(),
+        ..BaselineConfig::default()
+    };
+    let autoregressive = run_baseline_pipeline(&samples, &ar_config);
+
+    assert_eq!(linear.detected_impulses, autoregressive.detected_impulses);
+    let linear_score = transient_preservation(&reference, &linear.repaired, &regions);
+    let ar_score = transient_preservation(&reference, &autoregressive.repaired, &regions);
+    assert!(
+        ar_score > linear_score + 0.2 && ar_score >= 0.9,
+        "AR repair scored {ar_score:.3}, linear scored {linear_score:.3}"
+    );
+}
+
+#[test]
+fn residual_detector_finds_clicks_on_loud_passages() {
+    // A loud passage followed by a quiet one with a decaying bass note.
+    let mut samples: Vec<f32> = (0..20_000)
+        .map(|i| {
+            let t = i as f32;
+            if i < 10_000 {
+                0.8 * (t * 0.01).sin() + 0.1 * (t * 0.07).sin()
+            } else {
+                0.05 * (t * 0.013).sin() + 0.03 * (t * 0.05).sin()
+            }
+        })
+        .collect();
+    for (k, sample) in samples[14_000..17_000].iter_mut().enumerate() {
+        let t = k as f32;
+        *sample += 0.5 * (-t / 600.0).exp() * (t * std::f32::consts::TAU / 200.0).sin();
+    }
+    let clicks = [2_000, 5_000, 8_000, 12_000, 18_000];
+    for (n, &index) in clicks.iter().enumerate() {
+        samples[index] += if n % 2 == 0 { 0.3 } else { -0.3 };
+    }
+
+    let baseline = run_baseline_pipeline(&samples, &BaselineConfig::default());
+    let residual_config = BaselineConfig {
+        detection_mode: DetectionMode::Residual(ResidualConfig::default()),
+        ..BaselineConfig::default()
+    };
+    let residual = run_baseline_pipeline(&samples, &residual_config);
+
+    let baseline_metrics = click_precision_recall(&baseline.detected_impulses, &clicks, 1);
+    let residual_metrics = click_precision_recall(&residual.detected_impulses, &clicks, 1);
+    assert!(
+        residual_metrics.recall >= 0.8 && residual_metrics.precision >= 0.8,
+        "residual detector: {residual_metrics:?}"
+    );
+    assert!(
+        residual_metrics.recall > baseline_metrics.recall,
+        "residual {residual_metrics:?} vs baseline {baseline_metrics:?}"
+    );
+}
+
+#[test]
+fn threshold_envelopes_are_finite_for_any_length() {
+    let residual = BaselineConfig {
+        detection_mode: DetectionMode::Residual(ResidualConfig::default()),
+        ..BaselineConfig::default()
+    };
+    for config in [BaselineConfig::default(), residual] {
+        // Lengths that leave a last frame, or the whole signal, shorter
+        // than the model order.
+        for frames in [3, 17, 2_056, 4_100] {
+            let samples: Vec<f32> = (0..frames)
+                .map(|i| 0.3 * (i as f32 * 0.05).sin())
+                .collect();
+            let output = run_baseline_pipeline(&samples, &config);
+            let envelope = &output.threshold_envelope.values;
+            assert!(
+                envelope.iter().all(|value| value.is_finite()),
+                "{frames} frames: {envelope:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn windowed_threshold_follows_programme_level() {
+    // A quiet intro with two clicks, then a loud finale.
+    let mut samples: Vec<f32> = (0..40_000)
+        .map(|i| {
+            let t = i as f32;
+            if i < 20_000 {
+                0.02 * (t * 0.05).sin()
+            } else {
+                0.6 * (t * 0.01).sin()
+            }
+        })
+        .collect();
+    let clicks = [5_000, 12_000];
+    for &index in &clicks {
+        samples[index] += 0.3;
+    }
+
+    let global_config = BaselineConfig {
+        threshold_level: ThresholdLevel::Global,
+        ..BaselineConfig::default()
+    };
+    let global = run_baseline_pipeline(&samples, &global_config);
+    let windowed = run_baseline_pipeline(&samples, &BaselineConfig::default());
+
+    let global_metrics = click_precision_recall(&global.detected_impulses, &clicks, 1);
+    let windowed_metrics = click_precision_recall(&windowed.detected_impulses, &clicks, 1);
+    assert_eq!(global_metrics.recall, 0.0, "loud finale should mask the intro clicks");
+    assert_eq!((windowed_metrics.recall, windowed_metrics.precision), (1.0, 1.0));
+
+    let envelope = &windowed.threshold_envelope;
+    assert_eq!(envelope.values.len(), samples.len().div_ceil(envelope.hop));
+    assert!(envelope.at(8_000).unwrap() < envelope.at(32_000).unwrap());
+    let global_envelope = &global.threshold_envelope;
+    assert_eq!(global_envelope.at(8_000), global_envelope.at(32_000));
+}
+
+/// Deterministic noise source for the synthetic crackle.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[test]
+fn decrackle_removes_dense_crackle_and_keeps_clicks_for_the_detector() {
+    let clean: Vec<f32> = (0..44_100)
+        .map(|i| {
+            let t = i as f32;
+            0.3 * (t * 0.02).sin() + 0.1 * (t * 0.11).sin()
+        })
+        .collect();
+    let mut samples = clean.clone();
+    let mut rng = Lcg(7);
+    for sample in samples.iter_mut() {
+        if rng.next() < 0.01 {
+            *sample += (rng.next() - 0.5) * 0.06;
+        }
+    }
+    let clicks = [3_000, 11_000];
+    samples[clicks[0]] += 1.5;
+    samples[clicks[1]] -= 1.5;
+
+    let plain = run_baseline_pipeline(&samples, &BaselineConfig::default());
+    let config = BaselineConfig {
+        decrackle: Some(DecrackleConfig::default()),
+        ..BaselineConfig::default()
+    };
+    let decrackled = run_baseline_pipeline(&samples, &config);
+
+    let metrics = click_precision_recall(&decrackled.detected_impulses, &clicks, 1);
+    assert_eq!((metrics.recall, metrics.precision), (1.0, 1.0));
+
+    // Error against the clean programme, away from the repaired clicks.
+    let peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    let gain = 0.95 / peak;
+    let error = |output: &[f32]| -> f64 {
+        output
+            .iter()
+            .zip(&clean)
+            .enumerate()
+            .filter(|(index, _)| clicks.iter().all(|&click| index.abs_diff(click) > 400))
+            .map(|(_, (&out, &reference))| f64::from(out - reference * gain).powi(2))
+            .sum()
+    };
+    let plain_error = error(&plain.repaired);
+    let decrackled_error = error(&decrackled.repaired);
+    assert!(
+        decrackled_error < 0.5 * plain_error,
+        "error {decrackled_error:.4} with decrackle, {plain_error:.4} without"
+    );
+
+    let report = decrackled.decrackle.expect("decrackle was enabled");
+    assert!(report.repaired_samples > 0 && report.removed_energy > 0.0);
+    assert!(report.removed_db() < -20.0, "{report:?}");
+    assert!(plain.decrackle.is_none());
+}
+
+#[test]
+fn decrackle_leaves_isolated_clicks_alone() {
+    let config = BaselineConfig {
+        decrackle: Some(DecrackleConfig::default()),
+        ..BaselineConfig::default()
+    };
+    for clip in generate_corpus().iter().filter(|clip| !clip.graded) {
+        let plain = run_baseline_pipeline(&clip.samples, &BaselineConfig::default());
+        let decrackled = run_baseline_pipeline(&clip.samples, &config);
+
+        assert_eq!(decrackled.detected_impulses, plain.detected_impulses, "{}", clip.name);
+        assert_eq!(decrackled.repaired, plain.repaired, "{}", clip.name);
+        assert_eq!(decrackled.decrackle.unwrap().repaired_samples, 0, "{}", clip.name);
+    }
+}
+
+#[test]
+fn dehiss_improves_snr_with_every_noise_source() {
+    // A lead-in groove with nothing but hiss, then chords over the same hiss.
+    let lead_in = 16_384;
+    let clean: Vec<f32> = (0..88_200)
+        .map(|i| {
+            if i < lead_in {
+                return 0.0;
+            }
+            let t = i as f32;
+            0.25 * (t * 0.031).sin() + 0.15 * (t * 0.047).sin() + 0.08 * (t * 0.23).sin()
+        })
+        .collect();
+    let mut rng = Lcg(11);
+    let noisy: Vec<f32> = clean
+        .iter()
+        .map(|&sample| sample + (rng.next() - 0.5) * 0.06)
+        .collect();
+
+    let peak = noisy.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    let gain = 0.95 / peak;
+    let reference: Vec<f32> = clean.iter().map(|sample| sample * gain).collect();
+
+    let plain = run_baseline_pipeline(&noisy, &BaselineConfig::default());
+    assert!(plain.dehiss.is_none());
+    let baseline = snr_improvement(&reference, &plain.normalized, &plain.repaired);
+    assert!(baseline.abs() < 0.1, "no stage should not change the SNR: {baseline:.2} dB");
+
+    for source in [
+        NoiseSource::LeadIn { length: lead_in },
+        NoiseSource::Region { start: 4_000, end: 14_000 },
+        NoiseSource::default(),
+    ] {
+        let config = BaselineConfig {
+            dehiss: Some(DehissConfig {
+                noise_source: source,
+                ..DehissConfig::default()
+            }),
+            ..BaselineConfig::default()
+        };
+        let output = run_baseline_pipeline(&noisy, &config);
+        let report = output.dehiss.expect("dehiss was enabled");
+        assert!(report.noise_frames > 0, "{source:?}");
+
+        let music = lead_in..noisy.len();
+        let improvement = snr_improvement(
+            &reference[music.clone()],
+            &output.normalized[music.clone()],
+            &output.repaired[music],
+        );
+        let lead_in_improvement = snr_improvement(
+            &reference[..lead_in],
+            &output.normalized[..lead_in],
+            &output.repaired[..lead_in],
+        );
+        assert!(improvement > 5.0, "{source:?}: {improvement:.2} dB over the music");
+        assert!(lead_in_improvement > 6.0, "{source:?}: {lead_in_improvement:.2} dB over the lead-in");
+    }
+}
+
+#[test]
+fn rumble_filter_removes_warp_and_dc_before_detection() {
+    // Quiet music on a warped record: a 0.7 Hz excursion, 12 Hz motor rumble
+    // and a DC offset, all far larger than the music.
+    let sample_rate = 44_100.0;
+    let mut samples: Vec<f32> = (0..88_200)
+        .map(|i| {
+            let t = i as f32 / sample_rate;
+            let music = 0.05 * (t * 440.0 * std::f32::consts::TAU).sin()
+                + 0.03 * (t * 1_250.0 * std::f32::consts::TAU).sin();
+            let warp = 0.6 * (t * 0.7 * std::f32::consts::TAU).sin();
+            let rumble = 0.1 * (t * 12.0 * std::f32::consts::TAU).sin();
+            music + warp + rumble + 0.15
+        })
+        .collect();
+    let clicks = [10_000, 30_000, 50_000, 70_000];
+    for (n, &index) in clicks.iter().enumerate() {
+        samples[index] += if n % 2 == 0 { 0.4 } else { -0.4 };
+    }
+
+    let plain = run_baseline_pipeline(&samples, &BaselineConfig::default());
+    for rumble in [RumbleConfig::belt_drive(), RumbleConfig::warped()] {
+        let config = BaselineConfig {
+            rumble: Some(rumble),
+            ..BaselineConfig::default()
+        };
+        let filtered = run_baseline_pipeline(&samples, &config);
+        let metrics = click_precision_recall(&filtered.detected_impulses, &clicks, 1);
+        assert_eq!((metrics.recall, metrics.precision), (1.0, 1.0), "{rumble:?}");
+
+        let validation = &filtered.validation;
+        assert!(validation.output_low_frequency.dc_offset.abs() < 1e-3, "{validation:?}");
         assert!(
-            transient_score >= 0.9,
-            "{} transient preservation below threshold: {:.2}",
-            clip.name,
-            transient_score
+            validation.output_low_frequency.subsonic_rms
+                < 0.05 * validation.input_low_frequency.subsonic_rms,
+            "{validation:?}"
+        );
+    }
+
+    let plain_metrics = click_precision_recall(&plain.detected_impulses, &clicks, 1);
+    assert!(plain_metrics.recall < 1.0, "{plain_metrics:?}");
+    let validation = &plain.validation;
+    assert!(validation.input_low_frequency.dc_offset > 0.1, "{validation:?}");
+    assert_eq!(validation.input_low_frequency, validation.output_low_frequency);
+}
+
+/// Music plus mains hum whose frequency drifts slowly around `mains` Hz.
+fn hummed_music(mains: f32, sample_rate: f32) -> (Vec<f32>, Vec<f32>) {
+    let tau = std::f32::consts::TAU;
+    let music: Vec<f32> = (0..(3.0 * sample_rate) as usize)
+        .map(|i| {
+            let t = i as f32 / sample_rate;
+            0.3 * (t * 440.0 * tau).sin() + 0.2 * (t * 1_234.0 * tau).sin()
+                + 0.1 * (t * 87.0 * tau).sin()
+        })
+        .collect();
+    let mut phase = 0.0f32;
+    let hummed = music
+        .iter()
+        .enumerate()
+        .map(|(i, &sample)| {
+            let t = i as f32 / sample_rate;
+            phase += tau * (mains + 0.3 * (t * 0.5 * tau).sin()) / sample_rate;
+            let hum: f32 = (1..=5)
+                .map(|harmonic| 0.08 / harmonic as f32 * (phase * harmonic as f32).sin())
+                .sum();
+            sample + hum
+        })
+        .collect();
+    (music, hummed)
+}
+
+#[test]
+fn dehum_detects_mains_frequency_and_removes_drifting_hum() {
+    for (mains, sample_rate) in [(50.0, 44_100), (60.0, 48_000)] {
+        let (music, hummed) = hummed_music(mains, sample_rate as f32);
+        let config = BaselineConfig {
+            sample_rate,
+            dehum: Some(DehumConfig::default()),
+            ..BaselineConfig::default()
+        };
+        let output = run_baseline_pipeline(&hummed, &config);
+        let report = output.dehum.expect("dehum was enabled");
+        assert_eq!(report.fundamental_hz, Some(mains));
+
+        let peak = hummed.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        let reference: Vec<f32> = music.iter().map(|sample| sample * 0.95 / peak).collect();
+        let improvement = snr_improvement(&reference, &output.normalized, &output.repaired);
+        assert!(improvement > 15.0, "{mains} Hz at {sample_rate} Hz: {improvement:.2} dB");
+    }
+}
+
+#[test]
+fn dehum_leaves_hum_free_music_alone() {
+    let (music, _) = hummed_music(50.0, 44_100.0);
+    let config = BaselineConfig {
+        dehum: Some(DehumConfig::default()),
+        ..BaselineConfig::default()
+    };
+    let output = run_baseline_pipeline(&music, &config);
+    assert_eq!(output.dehum.unwrap().fundamental_hz, None);
+    assert_eq!(output.repaired, output.normalized);
+
+    // A fixed frequency is removed whether or not it stands out.
+    let fixed = BaselineConfig {
+        dehum: Some(DehumConfig {
+            fundamental: HumFundamental::Fixed(60.0),
+            ..DehumConfig::default()
+        }),
+        ..BaselineConfig::default()
+    };
+    let output = run_baseline_pipeline(&music, &fixed);
+    let report = output.dehum.unwrap();
+    assert_eq!(report.fundamental_hz, Some(60.0));
+    assert!(report.removed_db() < -30.0, "{report:?}");
+}
+
+/// Music with a 3150 Hz test tone, played back with 0.3 % wow at 0.55 Hz (an
+/// off-centre pressing at 33 rpm) and 0.05 % flutter at 10 Hz.
+fn wobbly_test_tone(sample_rate: f64) -> Vec<f32> {
+    let tau = std::f64::consts::TAU;
+    let mut time = 0.0f64;
+    (0..(8.0 * sample_rate) as usize)
+        .map(|i| {
+            let t = i as f64 / sample_rate;
+            time += (1.0 + 0.003 * (t * 0.55 * tau).sin() + 0.0005 * (t * 10.0 * tau).sin())
+                / sample_rate;
+            (0.3 * (time * 3_150.0 * tau).sin() + 0.2 * (time * 440.0 * tau).sin()
+                + 0.1 * (time * 87.0 * tau).sin()) as f32
+        })
+        .collect()
+}
+
+#[test]
+fn wow_flutter_is_measured_on_the_test_tone_and_corrected() {
+    let sample_rate = 44_100;
+    let signal = wobbly_test_tone(f64::from(sample_rate));
+    let config = BaselineConfig {
+        sample_rate,
+        wow_flutter: Some(WowFlutterConfig::default()),
+        ..BaselineConfig::default()
+    };
+    let output = run_baseline_pipeline(&signal, &config);
+    let report = output.wow_flutter.expect("the test tone is tracked");
+    assert!((report.reference_hz - 3_150.0).abs() < 20.0, "{}", report.reference_hz);
+    assert!((report.mean_frequency_hz - 3_150.0).abs() < 1.0, "{}", report.mean_frequency_hz);
+    // RMS of sinusoidal deviations of 0.3 % and 0.05 %.
+    assert!((report.wow_percent - 0.212).abs() < 0.02, "{}", report.wow_percent);
+    assert!((report.flutter_percent - 0.035).abs() < 0.007, "{}", report.flutter_percent);
+    assert!(
+        (0.3..0.45).contains(&report.peak_deviation_percent),
+        "{}",
+        report.peak_deviation_percent
+    );
+    assert_eq!(output.repaired, output.normalized);
+
+    let correcting = BaselineConfig {
+        wow_flutter: Some(WowFlutterConfig {
+            correct: true,
+            ..WowFlutterConfig::default()
+        }),
+        ..config
+    };
+    let corrected = run_baseline_pipeline(&signal, &correcting).repaired;
+    assert_eq!(corrected.len(), signal.len());
+    let steady = WowFlutterConfig {
+        reference: ToneReference::Fixed(3_150.0),
+        ..WowFlutterConfig::default()
+    };
+    let residual = measure_wow_flutter(&corrected, sample_rate, &steady).unwrap();
+    assert!(residual.wow_percent < 0.02, "{residual:?}");
+    assert!(residual.flutter_percent < 0.01, "{residual:?}");
+}
+
+#[test]
+fn click_duration_limits_are_the_same_at_every_sample_rate() {
+    // Scuffs: a sharp onset followed by 5 ms of broadband disturbance.
+    let burst_ms = 5.0;
+    for sample_rate in [44_100u32, 96_000, 192_000] {
+        let rate = sample_rate as f32;
+        let mut samples: Vec<f32> = (0..sample_rate as usize / 2)
+            .map(|i| 0.1 * (i as f32 / rate * 220.0 * std::f32::consts::TAU).sin())
+            .collect();
+        let onsets: Vec<usize> = [0.1, 0.25, 0.4].iter().map(|t| (t * rate) as usize).collect();
+        let mut rng = Lcg(11);
+        for &onset in &onsets {
+            samples[onset] = 0.9;
+            let length = (burst_ms / 1000.0 * rate) as usize;
+            for sample in &mut samples[onset + 1..onset + length] {
+                *sample += (rng.next() - 0.5) * 0.6;
+            }
+        }
+
+        let config = BaselineConfig {
+            sample_rate,
+            ..BaselineConfig::default()
+        };
+        let output = run_baseline_pipeline(&samples, &config);
+        let metrics = click_precision_recall(
+            &output.detected_impulses,
+            &onsets,
+            TimeTolerance::new(0.05, sample_rate),
+        );
+        assert_eq!((metrics.recall, metrics.precision), (1.0, 1.0), "{sample_rate} Hz");
+        for event in &output.detected_impulses {
+            let duration_ms = (event.end - event.start) as f32 / rate * 1000.0;
+            assert!(
+                (duration_ms - burst_ms).abs() < 0.5,
+                "{sample_rate} Hz: {duration_ms:.2} ms"
+            );
+        }
+    }
+}
+
+/// Precision and recall of `config` over the whole corpus, counting the
+/// clicks and detections of all clips together.
+fn corpus_precision_recall(config: &BaselineConfig) -> ClickMetrics {
+    let (mut found, mut expected, mut correct, mut detected) = (0.0, 0, 0.0, 0);
+    for clip in generate_corpus() {
+        let output = run_baseline_pipeline(&clip.samples, config);
+        let metrics = click_precision_recall(
+            &output.detected_impulses,
+            &clip.impulses,
+            CLICK_TOLERANCE_SAMPLES,
         );
+        found += metrics.recall * clip.impulses.len() as f32;
+        expected += clip.impulses.len();
+        correct += metrics.precision * output.detected_impulses.len() as f32;
+        detected += output.detected_impulses.len();
+    }
+    ClickMetrics {
+        recall: found / expected as f32,
+        precision: correct / detected as f32,
+    }
+}
+
+#[test]
+fn presets_trade_precision_for_recall_over_the_corpus() {
+    // The built-in presets from the least to the most aggressive; the
+    // presets of one tier share the detection settings of an aggressiveness.
+    let tiers = [
+        ["low", "modern-reissue"],
+        ["standard", "live-bootleg"],
+        ["high", "warm-vinyl"],
+    ];
+    let registry = PresetRegistry::default();
+    assert!(registry
+        .iter()
+        .all(|preset| tiers.as_flattened().contains(&preset.id.as_str())));
+    let metrics = tiers.map(|tier| {
+        tier.map(|id| corpus_precision_recall(&registry.get(id).unwrap().config))
+    });
+
+    for (tier, presets) in metrics.iter().enumerate() {
+        for (id, preset) in tiers[tier].iter().zip(presets) {
+            // Only the most aggressive tier mistakes attacks for clicks.
+            assert_eq!(preset.precision == 1.0, tier < 2, "{id}: {preset:?}");
+            let more_aggressive = tiers[tier + 1..].as_flattened().iter();
+            for (more_id, more) in more_aggressive.zip(metrics[tier + 1..].as_flattened()) {
+                assert!(
+                    preset.recall < more.recall && preset.precision >= more.precision,
+                    "{id} {preset:?} vs {more_id} {more:?}"
+                );
+            }
+        }
     }
 }
+
+#[test]
+fn every_builtin_preset_meets_quality_thresholds() {
+    for preset in PresetRegistry::default().iter() {
+        for clip in generate_corpus().iter().filter(|clip| !clip.graded) {
+            // Short clips give the hiss reduction nothing but the music to
+            // learn a profile from; the presets are checked as shipped on
+            // the clips long enough for one.
+            let config = if clip.samples.len() >= 44_100 {
+                preset.config.clone()
+            } else {
+                BaselineConfig {
+                    dehiss: None,
+                    ..preset.config.clone()
+                }
+            };
+            assert_clip_quality(clip, &config);
+        }
+    }
+}
+
+/// Two seconds of music over surface noise at `noise` (peak amplitude of
+/// the uniform noise), with a click every `click_spacing` samples. Returns
+/// the samples and the click positions.
+fn record_with_surface(noise: f32, click_spacing: usize) -> (Vec<f32>, Vec<usize>) {
+    let rate = 44_100.0;
+    let tau = std::f32::consts::TAU;
+    let mut state = 7_u32;
+    let mut samples: Vec<f32> = (0..88_200)
+        .map(|i| {
+            let t = i as f32 / rate;
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let hiss = (state >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0;
+            0.1 * (t * 220.0 * tau).sin() + 0.03 * (t * 660.0 * tau).sin() + noise * hiss
+        })
+        .collect();
+    let clicks: Vec<usize> = (click_spacing / 2..samples.len())
+        .step_by(click_spacing)
+        .collect();
+    for (n, &at) in clicks.iter().enumerate() {
+        samples[at] += if n % 2 == 0 { 0.6 } else { -0.6 };
+    }
+    (samples, clicks)
+}
+
+#[test]
+fn analysis_derives_the_configuration_from_the_record() {
+    // A worn, noisy record: eight clicks per second and hiss 40 dB down.
+    let (worn, clicks) = record_with_surface(0.01, 5_512);
+    let profile = analyze(&worn, 44_100);
+    assert!(profile.analysis.click_density > 7.0, "{:?}", profile.analysis);
+    assert!(profile.analysis.noise_floor_db > -50.0, "{:?}", profile.analysis);
+    assert_eq!(
+        profile.config.impulse_threshold_multiplier,
+        BaselineConfig::high().impulse_threshold_multiplier
+    );
+    assert!(profile.config.dehiss.is_some());
+    assert!(profile.summary.contains("worn surface") && profile.summary.contains("noisy"));
+    assert_eq!(profile.reasoning.len(), 4);
+
+    let output = run_baseline_pipeline(&worn, &profile.config);
+    let metrics = click_precision_recall(&output.detected_impulses, &clicks, 1);
+    assert_eq!(metrics.recall, 1.0);
+    assert_eq!(metrics.precision, 1.0);
+
+    // A clean, quiet pressing: no clicks and hiss 80 dB down.
+    let (clean, _) = record_with_surface(0.000_03, usize::MAX);
+    let profile = analyze(&clean, 44_100);
+    assert_eq!(profile.analysis.click_density, 0.0);
+    assert!(profile.analysis.noise_floor_db < -70.0, "{:?}", profile.analysis);
+    assert_eq!(profile.config.impulse_abs_min, BaselineConfig::low().impulse_abs_min);
+    assert!(profile.config.dehiss.is_none());
+    assert!(profile.summary.contains("clean surface") && profile.summary.contains("quiet"));
+
+    // Silence gets the standard preset.
+    let profile = analyze(&[0.0; 1_000], 48_000);
+    assert_eq!(
+        profile.config,
+        BaselineConfig {
+            sample_rate: 48_000,
+            ..BaselineConfig::standard()
+        }
+    );
+}
+
+/// Leaves every click in place.
+#[derive(Debug)]
+struct KeepClicks;
+
+impl ImpulseRepairer for KeepClicks {
+    fn repair(&self, signal: &[f32], _impulses: &[ImpulseEvent]) -> Vec<f32> {
+        signal.to_vec()
+    }
+
+    fn context(&self) -> usize {
+        0
+    }
+}
+
+#[test]
+fn cleanliness_reflects_what_the_cleaning_left_behind() {
+    let (worn, _) = record_with_surface(0.001, 5_512);
+    let config = BaselineConfig::standard();
+    let cleaned = run_baseline_pipeline(&worn, &config).cleanliness;
+    assert_eq!(cleaned.band, CleanlinessBand::Excellent, "{cleaned:?}");
+    assert_eq!(cleaned.clicks_per_second_before, 8.0);
+    assert_eq!(cleaned.clicks_per_second_after, 0.0);
+    assert_eq!(cleaned.residual_impulse_energy, 0.0);
+
+    let untouched = Pipeline::builder(&config)
+        .repairer(KeepClicks)
+        .build()
+        .run(&worn)
+        .cleanliness;
+    assert_eq!(untouched.band, CleanlinessBand::Good, "{untouched:?}");
+    assert_eq!(untouched.clicks_per_second_after, 8.0);
+    assert_eq!(untouched.residual_impulse_energy, 1.0);
+
+    // High also flattens the drum attacks, which costs it its band.
+    let (samples, _, _) = graded_clicks_and_attacks();
+    let [standard, high] = [Aggressiveness::Standard, Aggressiveness::High]
+        .map(|aggressiveness| run_baseline_pipeline(&samples, &aggressiveness.config()).cleanliness);
+    assert_eq!(standard.transient_preservation, 1.0);
+    assert!(high.transient_preservation < 0.6, "{high:?}");
+    assert!(high.repaired_fraction > standard.repaired_fraction);
+    assert_eq!(standard.band, CleanlinessBand::Great, "{standard:?}");
+    assert_eq!(high.band, CleanlinessBand::Good, "{high:?}");
+}
@@ -0,0 +1,80 @@
+use vinyl_engine::multichannel::{deinterleave, interleave};
+use vinyl_engine::{
+    run_baseline_pipeline, run_interleaved_pipeline, run_multichannel_pipeline, BaselineConfig,
+};
+
+fn stereo_sine(frames: usize) -> (Vec<f32>, Vec<f32>) {
+    let left = (0..frames).map(|i| 0.3 * (i as f32 * 0.02).sin()).collect();
+    let right = (0..frames)
+        .map(|i| 0.25 * (i as f32 * 0.02 + 0.3).sin())
+        .collect();
+    (left, right)
+}
+
+#[test]
+fn click_hitting_both_channels_is_linked_and_repaired_on_both() {
+    let (mut left, mut right) = stereo_sine(2048);
+    left[700] += 1.0;
+    right[702] += 0.6;
+    let config = BaselineConfig::default();
+
+    let output = run_multichannel_pipeline(&[left.clone(), right.clone()], &config);
+
+    assert_eq!(output.combined_impulses.len(), 1);
+    let click = output.combined_impulses[0];
+    assert_eq!(click.peak_index, 700);
+    assert_eq!((click.start, click.end), (700, 703));
+    assert_eq!(output.channels[0].detected_impulses.len(), 1);
+    assert_eq!(output.channels[0].detected_impulses[0].peak_index, 700);
+    // The right-channel click is too quiet to pass the amplitude threshold on
+    // its own, but is picked up through the link with the left channel.
+    assert!(output.channels[1].detected_impulses.is_empty());
+
+    // Both channels are repaired over the same linked span, so neither keeps
+    // any of the click energy.
+    for channel in &output.channels {
+        for index in 700..=702 {
+            let neighbours = (channel.normalized[699] + channel.normalized[703]) * 0.5;
+            assert!((channel.repaired[index] - neighbours).abs() < 0.05);
+        }
+    }
+
+    // An independent mono pass leaves the quieter right-channel click in place.
+    let mono_right = run_baseline_pipeline(&right, &config);
+    assert!(mono_right.detected_impulses.is_empty());
+    assert_eq!(mono_right.repaired, mono_right.normalized);
+}
+
+#[test]
+fn channels_share_one_normalization_gain() {
+    let (left, right) = stereo_sine(1024);
+    let output = run_multichannel_pipeline(&[&left, &right], &BaselineConfig::default());
+
+    let ratio_in = left[100] / right[100];
+    let ratio_out = output.channels[0].normalized[100] / output.channels[1].normalized[100];
+    assert!((ratio_in - ratio_out).abs() < 1e-4);
+}
+
+#[test]
+fn interleaved_entry_point_matches_planar() {
+    let (mut left, right) = stereo_sine(1024);
+    left[400] += 0.9;
+    let interleaved = interleave(&[&left, &right]);
+    assert_eq!(
+        deinterleave(&interleaved, 2),
+        vec![left.clone(), right.clone()]
+    );
+
+    let config = BaselineConfig::default();
+    let from_interleaved = run_interleaved_pipeline(&interleaved, 2, &config);
+    let from_planar = run_multichannel_pipeline(&[left, right], &config);
+
+    assert_eq!(
+        from_interleaved.combined_impulses,
+        from_planar.combined_impulses
+    );
+    assert_eq!(
+        from_interleaved.interleaved_repaired(),
+        from_planar.interleaved_repaired()
+    );
+}
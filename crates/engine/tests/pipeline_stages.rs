@@ -0,0 +1,155 @@
+use vinyl_engine::{
+    run_baseline_pipeline, BaselineConfig, Detection, DetectionContext, ImpulseDetector,
+    ImpulseEvent, ImpulseRepairer, Pipeline, SignalLevels, StreamingCleaner,
+};
+
+/// Reports an impulse at fixed positions, whatever the signal looks like.
+#[derive(Debug)]
+struct FixedPositions(Vec<usize>);
+
+impl ImpulseDetector for FixedPositions {
+    fn detect(&self, signal: &[f32], _context: &DetectionContext) -> Detection {
+        let events: Vec<ImpulseEvent> = self
+            .0
+            .iter()
+            .filter(|&&index| index < signal.len())
+            .map(|&index| ImpulseEvent {
+                start: index,
+                end: index + 1,
+                peak_index: index,
+                amplitude: signal[index],
+                contrast_ratio: 1.0,
+                confidence: 1.0,
+            })
+            .collect();
+        events.into()
+    }
+
+    fn context(&self) -> usize {
+        0
+    }
+}
+
+/// Flags every sample whose magnitude reaches a fixed level.
+#[derive(Debug)]
+struct AboveLevel(f32);
+
+impl ImpulseDetector for AboveLevel {
+    fn detect(&self, signal: &[f32], _context: &DetectionContext) -> Detection {
+        let events = (0..signal.len())
+            .filter(|&index| signal[index].abs() >= self.0)
+            .map(|index| ImpulseEvent {
+                start: index,
+                end: index + 1,
+                peak_index: index,
+                amplitude: signal[index],
+                contrast_ratio: 1.0,
+                confidence: 1.0,
+            })
+            .collect();
+        Detection {
+            events,
+            threshold: vec![self.0; signal.len()],
+        }
+    }
+
+    fn context(&self) -> usize {
+        0
+    }
+}
+
+/// Silences every reported region.
+#[derive(Debug)]
+struct Mute;
+
+impl ImpulseRepairer for Mute {
+    fn repair(&self, signal: &[f32], impulses: &[ImpulseEvent]) -> Vec<f32> {
+        let mut repaired = signal.to_vec();
+        for event in impulses {
+            repaired[event.start..event.end].fill(0.0);
+        }
+        repaired
+    }
+
+    fn context(&self) -> usize {
+        0
+    }
+}
+
+fn clicky_signal() -> Vec<f32> {
+    let mut signal: Vec<f32> = (0..4_000).map(|i| 0.1 * (i as f32 * 0.02).sin()).collect();
+    signal[1_000] += 0.9;
+    signal[2_500] -= 0.8;
+    signal
+}
+
+#[test]
+fn default_pipeline_matches_baseline_function() {
+    let signal = clicky_signal();
+    let config = BaselineConfig::default();
+    let baseline = run_baseline_pipeline(&signal, &config);
+    let output = Pipeline::new(&config).run(&signal);
+
+    assert_eq!(output.detected_impulses, baseline.detected_impulses);
+    assert_eq!(output.repaired, baseline.repaired);
+}
+
+#[test]
+fn builder_composes_custom_detector_and_repairer() {
+    let signal = clicky_signal();
+    let config = BaselineConfig::default();
+
+    // Custom detector, default repairer: the positions come from the
+    // detector, the interpolation from the baseline.
+    let detected_only = Pipeline::builder(&config)
+        .detector(FixedPositions(vec![300]))
+        .build()
+        .run(&signal);
+    assert_eq!(detected_only.detected_impulses.len(), 1);
+    assert_eq!(detected_only.detected_impulses[0].peak_index, 300);
+    assert_ne!(detected_only.repaired[300], detected_only.normalized[300]);
+    assert_eq!(
+        detected_only.repaired[1_000],
+        detected_only.normalized[1_000]
+    );
+
+    // Default detector, custom repairer.
+    let muted = Pipeline::builder(&config)
+        .repairer(Mute)
+        .build()
+        .run(&signal);
+    assert_eq!(muted.detected_impulses.len(), 2);
+    for event in &muted.detected_impulses {
+        assert!(muted.repaired[event.start..event.end]
+            .iter()
+            .all(|&sample| sample == 0.0));
+    }
+}
+
+#[test]
+fn custom_pipeline_runs_on_multichannel_and_streaming_input() {
+    let signal = clicky_signal();
+    let pipeline = Pipeline::builder(&BaselineConfig::default())
+        .detector(AboveLevel(0.5))
+        .repairer(Mute)
+        .build();
+    let offline = pipeline.run(&signal);
+    assert_eq!(offline.detected_impulses.len(), 2);
+
+    let stereo = pipeline.run_multichannel(&[&signal, &signal]);
+    for channel in &stereo.channels {
+        assert_eq!(channel.repaired, offline.repaired);
+    }
+
+    let levels = SignalLevels::scan(&signal);
+    let mut cleaner = StreamingCleaner::with_pipeline(pipeline.clone(), &levels);
+    let mut output = Vec::new();
+    for block in signal.chunks(333) {
+        cleaner.process(block, &mut output);
+    }
+    let summary = cleaner.finish(&mut output);
+
+    assert_eq!(output, offline.repaired);
+    assert_eq!(summary.detected_impulses, offline.detected_impulses);
+    assert_eq!(summary.threshold_envelope, offline.threshold_envelope);
+}
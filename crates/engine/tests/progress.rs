@@ -0,0 +1,221 @@
+use vinyl_engine::{
+    BaselineConfig, CancellationToken, DehissConfig, DehumConfig, DetectionContext, Error,
+    Pipeline, PresetRegistry, Progress, RepairMode, SignalLevels, Stage,
+};
+
+/// Three seconds of hummy, hissy music with a few clicks: long enough for
+/// the restoration stages to run in several blocks.
+fn noisy_record() -> Vec<f32> {
+    let mut state = 1_u32;
+    let mut signal: Vec<f32> = (0..132_300)
+        .map(|i| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let t = i as f32 / 44_100.0;
+            0.1 * (t * 440.0 * std::f32::consts::TAU).sin()
+                + 0.01 * (t * 50.0 * std::f32::consts::TAU).sin()
+                + 0.004 * ((state >> 8) as f32 / (1 << 24) as f32 - 0.5)
+        })
+        .collect();
+    for index in [20_000, 70_000, 110_000] {
+        signal[index] += 0.7;
+    }
+    signal
+}
+
+fn restoring_config() -> BaselineConfig {
+    BaselineConfig {
+        dehum: Some(DehumConfig::default()),
+        dehiss: Some(DehissConfig::default()),
+        ..BaselineConfig::standard()
+    }
+}
+
+#[test]
+fn progress_runs_match_plain_runs_and_report_every_stage_in_order() {
+    let signal = noisy_record();
+    let pipeline = Pipeline::new(&restoring_config());
+    let mut updates: Vec<Progress> = Vec::new();
+    let output = pipeline
+        .run_with_progress(
+            &signal,
+            &mut |progress: &Progress| updates.push(*progress),
+            &CancellationToken::new(),
+        )
+        .unwrap();
+
+    let expected = pipeline.run(&signal);
+    assert_eq!(output.repaired, expected.repaired);
+    assert_eq!(output.detected_impulses, expected.detected_impulses);
+    assert_eq!(output.dehum, expected.dehum);
+    assert_eq!(output.dehiss, expected.dehiss);
+    assert_eq!(output.cleanliness, expected.cleanliness);
+
+    assert!(updates
+        .windows(2)
+        .all(|pair| pair[0].fraction <= pair[1].fraction && pair[0].stage <= pair[1].stage));
+    let stages: Vec<Stage> = Stage::ALL
+        .into_iter()
+        .filter(|stage| updates.iter().any(|update| update.stage == *stage))
+        .collect();
+    assert_eq!(stages, Stage::ALL);
+    let restore_updates = updates
+        .iter()
+        .filter(|update| update.stage == Stage::Restore)
+        .count();
+    assert_eq!(restore_updates, 3);
+
+    let last = updates.last().unwrap();
+    assert_eq!((last.stage, last.fraction), (Stage::Validate, 1.0));
+    assert_eq!(last.eta, Some(std::time::Duration::ZERO));
+}
+
+#[test]
+fn cancelling_stops_the_run_at_the_next_block() {
+    let signal = noisy_record();
+    let pipeline = Pipeline::new(&restoring_config());
+    let cancel = CancellationToken::new();
+    let mut updates = Vec::new();
+    let result = pipeline.run_with_progress(
+        &signal,
+        &mut |progress: &Progress| {
+            updates.push(*progress);
+            if progress.stage == Stage::Restore {
+                cancel.cancel();
+            }
+        },
+        &cancel,
+    );
+
+    assert_eq!(result.unwrap_err(), Error::Cancelled);
+    assert_eq!(updates.last().unwrap().stage, Stage::Restore);
+    assert_eq!(updates.last().unwrap().stage_fraction, 0.0);
+    assert_eq!(Error::Cancelled.to_string(), "the run was cancelled");
+
+    // A token cancelled up front stops the run before any work.
+    let result = pipeline.run_with_progress(&signal, &mut |_: &Progress| {}, &cancel);
+    assert_eq!(result.unwrap_err(), Error::Cancelled);
+}
+
+#[test]
+fn detection_and_repair_report_and_cancel_as_they_go() {
+    let signal = noisy_record();
+    let pipeline = Pipeline::new(&restoring_config());
+    let mut updates: Vec<Progress> = Vec::new();
+    pipeline
+        .run_with_progress(
+            &signal,
+            &mut |progress: &Progress| updates.push(*progress),
+            &CancellationToken::new(),
+        )
+        .unwrap();
+    // Three blocks each of detection, repair and the residual search.
+    for stage in [Stage::Detect, Stage::Repair] {
+        let count = updates
+            .iter()
+            .filter(|update| update.stage == stage)
+            .count();
+        assert_eq!(count, 3, "{stage:?}");
+    }
+    assert!(updates
+        .iter()
+        .filter(|update| update.stage == Stage::Validate)
+        .any(|update| update.stage_fraction > 0.0 && update.stage_fraction < 1.0));
+
+    for stage in [Stage::Detect, Stage::Repair, Stage::Validate] {
+        let cancel = CancellationToken::new();
+        let mut last = None;
+        let result = pipeline.run_with_progress(
+            &signal,
+            &mut |progress: &Progress| {
+                last = Some(*progress);
+                if progress.stage == stage && progress.stage_fraction > 0.0 {
+                    cancel.cancel();
+                }
+            },
+            &cancel,
+        );
+        assert_eq!(result.unwrap_err(), Error::Cancelled);
+        let last = last.unwrap();
+        assert_eq!(last.stage, stage);
+        assert!(last.stage_fraction > 0.0 && last.stage_fraction < 1.0);
+    }
+}
+
+#[test]
+fn detection_and_repair_in_blocks_match_a_single_pass() {
+    let mut signal = noisy_record();
+    // Clicks on and around the block boundaries.
+    for index in [65_534, 65_536, 131_072] {
+        signal[index] -= 0.8;
+    }
+    let config = BaselineConfig {
+        repair_mode: RepairMode::autoregressive(),
+        ..BaselineConfig::default()
+    };
+    let pipeline = Pipeline::new(&config);
+    let output = pipeline.run(&signal);
+
+    let levels = SignalLevels::scan(&signal);
+    let context = DetectionContext {
+        levels: &levels,
+        gain: config.target_peak / levels.peak(),
+        offset: 0,
+    };
+    let detection = pipeline.detector().detect(&output.normalized, &context);
+    assert_eq!(output.detected_impulses, detection.events);
+    let peaks: Vec<usize> = detection
+        .events
+        .iter()
+        .map(|event| event.peak_index)
+        .collect();
+    assert_eq!(peaks, [20_000, 65_534, 70_000, 110_000, 131_072]);
+    assert_eq!(
+        output.repaired,
+        pipeline
+            .repairer()
+            .repair(&output.normalized, &detection.events)
+    );
+}
+
+#[test]
+fn multichannel_progress_covers_every_channel() {
+    let left = noisy_record();
+    let right: Vec<f32> = left.iter().map(|sample| sample * 0.8).collect();
+    let config = PresetRegistry::default()
+        .get("live-bootleg")
+        .unwrap()
+        .config
+        .clone();
+    let pipeline = Pipeline::new(&config);
+    let mut updates: Vec<Progress> = Vec::new();
+    let output = pipeline
+        .run_multichannel_with_progress(
+            &[&left, &right],
+            &mut |progress: &Progress| updates.push(*progress),
+            &CancellationToken::new(),
+        )
+        .unwrap();
+
+    let expected = pipeline.run_multichannel(&[&left, &right]);
+    assert_eq!(output.combined_impulses, expected.combined_impulses);
+    for (channel, expected) in output.channels.iter().zip(&expected.channels) {
+        assert_eq!(channel.repaired, expected.repaired);
+    }
+
+    // Three blocks of restoration on each of the two channels.
+    let restore: Vec<f32> = updates
+        .iter()
+        .filter(|update| update.stage == Stage::Restore)
+        .map(|update| update.stage_fraction)
+        .collect();
+    assert_eq!(restore.len(), 6);
+    assert!(restore.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(restore[3], 0.5);
+    assert_eq!(updates.last().unwrap().fraction, 1.0);
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let result =
+        pipeline.run_multichannel_with_progress(&[&left, &right], &mut |_: &Progress| {}, &cancel);
+    assert_eq!(result.unwrap_err(), Error::Cancelled);
+}
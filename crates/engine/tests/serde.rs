@@ -0,0 +1,221 @@
+#![cfg(feature = "serde")]
+
+use vinyl_engine::{
+    BaselineConfig, ClickMetrics, DetectionMode, NoiseSource, Pipeline, PresetError,
+    PresetRegistry, ResidualConfig, RunReport, ThresholdLevel, REPORT_FORMAT_VERSION,
+};
+
+fn clicky_signal() -> Vec<f32> {
+    let mut signal: Vec<f32> = (0..8_000).map(|i| 0.1 * (i as f32 * 0.02).sin()).collect();
+    signal[1_000] += 0.9;
+    signal[5_500] -= 0.8;
+    signal
+}
+
+#[test]
+fn every_builtin_preset_round_trips_through_json_and_toml() {
+    for preset in PresetRegistry::default().iter() {
+        let json = serde_json::to_string(&preset.config).unwrap();
+        let from_json: BaselineConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json, preset.config, "{json}");
+
+        let toml = toml::to_string(&preset.config).unwrap();
+        let from_toml: BaselineConfig = toml::from_str(&toml).unwrap();
+        assert_eq!(from_toml, preset.config, "{toml}");
+    }
+}
+
+#[test]
+fn config_schema_uses_field_names_and_snake_case_values() {
+    let config: BaselineConfig = toml::from_str(
+        r#"
+        sample_rate = 48000
+        impulse_abs_min = 0.3
+        threshold_level = "global"
+        detection_mode = { residual = { order = 8, frame_length = 512, threshold_multiplier = 6.0, min_residual = 0.1 } }
+
+        [dehiss]
+        max_reduction_db = 9.0
+        noise_source = { lead_in = { length = 4800 } }
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.sample_rate, 48_000);
+    assert_eq!(config.impulse_abs_min, 0.3);
+    assert_eq!(config.threshold_level, ThresholdLevel::Global);
+    assert_eq!(
+        config.detection_mode,
+        DetectionMode::Residual(ResidualConfig {
+            order: 8,
+            frame_length: 512,
+            threshold_multiplier: 6.0,
+            min_residual: 0.1,
+        })
+    );
+    let dehiss = config.dehiss.unwrap();
+    assert_eq!(dehiss.max_reduction_db, 9.0);
+    // Settings left out keep their defaults.
+    assert_eq!(
+        dehiss.frame_length,
+        vinyl_engine::DehissConfig::default().frame_length
+    );
+    assert_eq!(config.target_peak, BaselineConfig::default().target_peak);
+    assert!(config.rumble.is_none());
+
+    let wrong_type = toml::from_str::<BaselineConfig>("impulse_abs_min = \"high\"");
+    assert!(wrong_type.is_err());
+}
+
+#[test]
+fn run_report_is_written_as_json() {
+    let signal = clicky_signal();
+    let config = BaselineConfig::standard();
+    let (output, report) = Pipeline::new(&config).run_with_report(&signal);
+
+    assert_eq!(report.format_version, REPORT_FORMAT_VERSION);
+    assert_eq!(report.samples, signal.len());
+    assert_eq!(report.impulses, output.detected_impulses);
+    assert!(report.timing.elapsed_ms >= 0.0);
+
+    let json = report.to_json();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["impulses"][0]["peak_index"], 1_000);
+    assert_eq!(
+        value["config"]["repair_mode"]["autoregressive"]["order"],
+        32
+    );
+    assert_eq!(value["validation"]["has_nan"], false);
+    assert_eq!(value["cleanliness"]["band"], "excellent");
+    assert_eq!(RunReport::from_json(&json).unwrap(), report);
+
+    let metrics = ClickMetrics {
+        recall: 0.5,
+        precision: 1.0,
+    };
+    let value = serde_json::to_value(metrics).unwrap();
+    assert_eq!(value["recall"], 0.5);
+}
+
+#[test]
+fn presets_load_from_toml_on_top_of_a_base() {
+    let mut registry = PresetRegistry::default();
+    let loaded = registry
+        .load_toml(
+            r#"
+            [[preset]]
+            id = "noisy-78"
+            name = "Noisy 78"
+            description = "Shellac with a loud surface."
+            base = "warm-vinyl"
+
+            [preset.config]
+            impulse_abs_min = 0.1
+            dehiss = { max_reduction_db = 12.0 }
+
+            [[preset]]
+            id = "standard"
+            name = "Standard"
+            base = "noisy-78"
+            "#,
+        )
+        .unwrap();
+    assert_eq!(loaded, 2);
+
+    let warm = registry.get("warm-vinyl").unwrap().config.clone();
+    let noisy = registry.get("noisy-78").unwrap();
+    assert_eq!(noisy.description, "Shellac with a loud surface.");
+    assert_eq!(noisy.config.impulse_abs_min, 0.1);
+    assert_eq!(noisy.config.dehiss.unwrap().max_reduction_db, 12.0);
+    assert_eq!(
+        noisy.config.dehiss.unwrap().noise_source,
+        warm.dehiss.unwrap().noise_source
+    );
+    assert_eq!(noisy.config.decrackle, warm.decrackle);
+    // A later preset can build on an earlier one, and replaces a built-in.
+    assert_eq!(registry.get("standard").unwrap().config, noisy.config);
+    assert_eq!(registry.iter().count(), 7);
+
+    let before = registry.clone();
+    let error = registry
+        .load_toml("[[preset]]\nid = \"x\"\nname = \"X\"\nbase = \"missing\"\n")
+        .unwrap_err();
+    assert!(matches!(error, PresetError::UnknownBase { .. }));
+    assert_eq!(
+        error.to_string(),
+        "preset \"x\" is based on unknown preset \"missing\""
+    );
+    let error = registry
+        .load_toml("[[preset]]\nid = \"x\"\nname = \"X\"\n[preset.config]\ntarget_peek = 1.0\n")
+        .unwrap_err();
+    assert!(matches!(error, PresetError::Toml(_)), "{error}");
+    assert_eq!(registry, before);
+}
+
+#[test]
+fn presets_switch_the_variant_of_an_inherited_setting() {
+    let mut registry = PresetRegistry::default();
+    registry
+        .load_toml(
+            r#"
+            [[preset]]
+            id = "lead-in"
+            name = "Lead-in"
+            base = "warm-vinyl"
+
+            [preset.config.dehiss.noise_source.lead_in]
+            length = 44100
+
+            [[preset]]
+            id = "few-frames"
+            name = "Few frames"
+            base = "warm-vinyl"
+
+            [preset.config.dehiss.noise_source.auto]
+            frames = 8
+            "#,
+        )
+        .unwrap();
+    let warm = registry.get("warm-vinyl").unwrap().config.dehiss.unwrap();
+    let lead_in = registry.get("lead-in").unwrap().config.dehiss.unwrap();
+    assert_eq!(lead_in.noise_source, NoiseSource::LeadIn { length: 44_100 });
+    assert_eq!(lead_in.max_reduction_db, warm.max_reduction_db);
+    let few = registry.get("few-frames").unwrap().config.dehiss.unwrap();
+    assert_eq!(few.noise_source, NoiseSource::Auto { frames: 8 });
+}
+
+#[test]
+fn presets_switch_off_inherited_stages_with_false() {
+    let mut registry = PresetRegistry::default();
+    registry
+        .load_toml(
+            r#"
+            [[preset]]
+            id = "bare"
+            name = "Bare"
+            base = "warm-vinyl"
+
+            [preset.config]
+            rumble = false
+            decrackle = false
+            dehiss = false
+            dehum = false
+            "#,
+        )
+        .unwrap();
+    let warm = registry.get("warm-vinyl").unwrap().config.clone();
+    let bare = &registry.get("bare").unwrap().config;
+    assert!(warm.rumble.is_some() && warm.decrackle.is_some() && warm.dehiss.is_some());
+    assert_eq!(
+        (bare.rumble, bare.decrackle, bare.dehiss),
+        (None, None, None)
+    );
+    assert_eq!(bare.dehum, None);
+    assert_eq!(bare.repair_mode, warm.repair_mode);
+
+    // Only optional stages can be switched off.
+    let error = registry
+        .load_toml("[[preset]]\nid = \"x\"\nname = \"X\"\n[preset.config]\ntarget_peak = false\n")
+        .unwrap_err();
+    assert!(matches!(error, PresetError::Toml(_)), "{error}");
+}
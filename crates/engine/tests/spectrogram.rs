@@ -0,0 +1,223 @@
+use vinyl_engine::spectrogram::{
+    Colormap, FrequencyScale, Spectrogram, SpectrogramConfig, SpectrogramView, WindowFunction,
+};
+use vinyl_engine::{run_baseline_pipeline, BaselineConfig, Error};
+
+fn tone(frequency: f32, amplitude: f32, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .map(|i| amplitude * (i as f32 / 44_100.0 * frequency * std::f32::consts::TAU).sin())
+        .collect()
+}
+
+/// Index of the row with the highest level in `column`.
+fn loudest_row(levels: &[u8], width: usize, column: usize) -> usize {
+    levels
+        .iter()
+        .skip(column)
+        .step_by(width)
+        .enumerate()
+        .max_by_key(|(_, level)| **level)
+        .unwrap()
+        .0
+}
+
+#[test]
+fn a_tone_lights_up_its_row_at_its_level() {
+    let signal = tone(1_000.0, 0.5, 44_100);
+    for window in [
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::Blackman,
+        WindowFunction::Rectangular,
+    ] {
+        let config = SpectrogramConfig {
+            window,
+            ..SpectrogramConfig::default()
+        };
+        let spectrogram = Spectrogram::new(config, 44_100).unwrap();
+        let tile = spectrogram.tile(&signal, 0..44_100, 64, 220);
+        assert_eq!(
+            (tile.width, tile.height, tile.levels.len()),
+            (64, 220, 64 * 220)
+        );
+
+        // 220 rows of about 100 Hz each; -6 dB is 94 % of the way up, less
+        // up to 4 dB of scalloping for a tone between two bins.
+        for column in [5, 32, 58] {
+            let row = loudest_row(&tile.levels, 64, column);
+            let frequency = spectrogram.row_frequency(row, 220);
+            assert!(
+                (frequency - 1_000.0).abs() < 100.0,
+                "{window:?}: {frequency} Hz"
+            );
+            let level = tile.levels[row * 64 + column];
+            assert!((229..=242).contains(&level), "{window:?}: level {level}");
+        }
+        // Far from the tone, a Blackman window leaks the least.
+        if window == WindowFunction::Blackman {
+            assert!(tile.levels[..64 * 100].iter().all(|&level| level < 80));
+        }
+    }
+}
+
+#[test]
+fn logarithmic_rows_spread_octaves_evenly() {
+    let config = SpectrogramConfig {
+        frequency_scale: FrequencyScale::Logarithmic { min_hz: 20.0 },
+        window_length: 8192,
+        ..SpectrogramConfig::default()
+    };
+    let spectrogram = Spectrogram::new(config, 44_100).unwrap();
+    let frequencies: Vec<f32> = (0..100)
+        .map(|row| spectrogram.row_frequency(row, 100))
+        .collect();
+    assert!(frequencies.windows(2).all(|pair| pair[0] > pair[1]));
+    let octaves_per_row = (frequencies[0] / frequencies[99]).log2() / 99.0;
+    let middle = (frequencies[49] / frequencies[50]).log2();
+    assert!((middle - octaves_per_row).abs() < 1e-3);
+    assert!(frequencies[99] > 20.0 && frequencies[99] < 22.0);
+
+    // A 60 Hz hum sits low on a log scale, not in the bottom row of a
+    // linear one.
+    let hum = tone(60.0, 0.2, 44_100);
+    let tile = spectrogram.tile(&hum, 0..44_100, 8, 100);
+    let frequency = spectrogram.row_frequency(loudest_row(&tile.levels, 8, 4), 100);
+    assert!((frequency - 60.0).abs() < 5.0, "{frequency} Hz");
+}
+
+#[test]
+fn the_difference_view_shows_what_was_removed() {
+    let music = tone(440.0, 0.3, 44_100);
+    let hum = tone(120.0, 0.05, 44_100);
+    let before: Vec<f32> = music.iter().zip(&hum).map(|(a, b)| a + b).collect();
+    let spectrogram = Spectrogram::new(SpectrogramConfig::default(), 44_100).unwrap();
+    let tile = spectrogram.difference_tile(&before, &music, 0..44_100, 16, 441);
+
+    // Rows of 50 Hz: the hum in row 438 was removed, the tone in row 432 was
+    // kept. The outer columns reach past the signal, where the cut-off tone
+    // leaks over the hum.
+    let row = |row: usize| &tile.levels[row * 16 + 1..(row + 1) * 16 - 1];
+    assert!(row(438).iter().all(|&level| level == 255), "{:?}", row(438));
+    assert!(row(432).iter().all(|&level| level < 10), "{:?}", row(432));
+    // Cells that got louder count as unchanged.
+    let louder = spectrogram.difference_tile(&music, &before, 0..44_100, 16, 441);
+    assert!(louder.levels[438 * 16..439 * 16]
+        .iter()
+        .all(|&level| level == 0));
+
+    // The views of a pipeline run: a repaired click shows in the difference.
+    let mut signal = tone(140.0, 0.1, 44_100);
+    signal[22_000] += 0.8;
+    let output = run_baseline_pipeline(&signal, &BaselineConfig::default());
+    assert_eq!(output.detected_impulses.len(), 1);
+    let view = |view| spectrogram.view_tile(&output, view, 0..44_100, 40, 64);
+    assert_eq!(
+        view(SpectrogramView::Repaired),
+        spectrogram.tile(&output.repaired, 0..44_100, 40, 64)
+    );
+    assert_eq!(
+        view(SpectrogramView::Normalized),
+        spectrogram.tile(&output.normalized, 0..44_100, 40, 64)
+    );
+    let difference = view(SpectrogramView::Difference);
+    let removed = |column: usize| -> u32 {
+        (0..64)
+            .map(|row| u32::from(difference.levels[row * 40 + column]))
+            .sum()
+    };
+    // The click at 22_000 is in column 19 of 40, 1102 frames each.
+    assert!(removed(19) > 20 * removed(5).max(1), "{}", removed(19));
+}
+
+#[test]
+fn an_overview_shows_a_click_anywhere_in_its_columns() {
+    let mut signal = tone(140.0, 0.1, 5 * 44_100);
+    signal[100_000] += 0.8;
+    let output = run_baseline_pipeline(&signal, &BaselineConfig::default());
+    assert_eq!(output.detected_impulses.len(), 1);
+    let spectrogram = Spectrogram::new(SpectrogramConfig::default(), 44_100).unwrap();
+    let difference =
+        spectrogram.view_tile(&output, SpectrogramView::Difference, 0..5 * 44_100, 4, 64);
+
+    // Columns of 55_125 frames: the click sits between the frames a sample of
+    // the column would analyze, but every frame counts.
+    let removed = |column: usize| -> u32 {
+        (0..64)
+            .map(|row| u32::from(difference.levels[row * 4 + column]))
+            .sum()
+    };
+    assert!(removed(1) > 20 * removed(3).max(1), "{}", removed(1));
+}
+
+#[test]
+fn tiles_cover_their_grid_cell_and_convert_to_rgba() {
+    assert_eq!(Spectrogram::tile_frames(0, 256, 100), 0..25_600);
+    assert_eq!(Spectrogram::tile_frames(3, 256, 100), 76_800..102_400);
+
+    // The last tile of a grid reaches past the signal; the rest is silence.
+    let spectrogram = Spectrogram::new(SpectrogramConfig::default(), 44_100).unwrap();
+    let signal = tone(3_000.0, 0.5, 40_000);
+    let tile = spectrogram.tile(&signal, Spectrogram::tile_frames(1, 100, 300), 100, 32);
+    assert_eq!((tile.start_frame, tile.end_frame), (30_000, 60_000));
+    let column_peak = |column: usize| (0..32).map(|row| tile.levels[row * 100 + column]).max();
+    assert!(column_peak(10) > Some(200));
+    assert_eq!(column_peak(60), Some(0));
+
+    let rgba = tile.to_rgba(Colormap::Grayscale);
+    assert_eq!(rgba.len(), 100 * 32 * 4);
+    assert!(rgba
+        .chunks(4)
+        .zip(&tile.levels)
+        .all(|(pixel, &level)| pixel == [level, level, level, 255]));
+    assert_eq!(Colormap::Inferno.color(0), [0, 0, 4]);
+    assert_eq!(Colormap::Inferno.color(255), [252, 255, 164]);
+}
+
+#[test]
+fn invalid_configurations_are_rejected() {
+    let invalid = |config: SpectrogramConfig| match Spectrogram::new(config, 44_100) {
+        Err(Error::InvalidConfig { setting, .. }) => setting,
+        other => panic!("expected an invalid configuration, got {other:?}"),
+    };
+    let default = SpectrogramConfig::default();
+    assert_eq!(
+        invalid(SpectrogramConfig {
+            window_length: 8,
+            ..default
+        }),
+        "window_length"
+    );
+    assert_eq!(invalid(SpectrogramConfig { hop: 0, ..default }), "hop");
+    assert_eq!(
+        invalid(SpectrogramConfig {
+            floor_db: 0.0,
+            ..default
+        }),
+        "ceiling_db"
+    );
+    assert_eq!(
+        invalid(SpectrogramConfig {
+            frequency_scale: FrequencyScale::Logarithmic { min_hz: 30_000.0 },
+            ..default
+        }),
+        "frequency_scale.min_hz"
+    );
+    assert_eq!(
+        invalid(SpectrogramConfig {
+            difference_range_db: 0.0,
+            ..default
+        }),
+        "difference_range_db"
+    );
+    // Window lengths round up to a power of two internally, but the
+    // configuration is kept as given.
+    let spectrogram = Spectrogram::new(
+        SpectrogramConfig {
+            window_length: 1000,
+            ..default
+        },
+        44_100,
+    )
+    .unwrap();
+    assert_eq!(spectrogram.config().window_length, 1000);
+}
@@ -0,0 +1,266 @@
+use vinyl_engine::{
+    run_baseline_pipeline, BaselineConfig, DecrackleConfig, DehissConfig, DehumConfig,
+    DetectionMode, FilterPhase, RepairMode, ResidualConfig, RumbleConfig, SignalLevels,
+    StreamingCleaner, StreamingSummary, WowFlutterConfig,
+};
+
+fn clicky_signal() -> Vec<f32> {
+    let mut signal: Vec<f32> = (0..20_000)
+        .map(|i| 0.3 * (i as f32 * 0.013).sin() + 0.05 * (i as f32 * 0.31).sin())
+        .collect();
+    // Clicks placed on and around typical block boundaries.
+    for &index in &[511, 512, 1023, 4096, 4097, 9_999, 15_000, 19_998] {
+        signal[index] += if index % 2 == 0 { 1.1 } else { -1.1 };
+    }
+    // A wide, ringing pop straddling the 8192 boundary.
+    for k in 0..150 {
+        signal[8_150 + k] += (-(k as f32) / 25.0).exp() * (2.0 * k as f32).cos();
+    }
+    signal
+}
+
+fn run_streaming(
+    signal: &[f32],
+    config: &BaselineConfig,
+    block_size: usize,
+) -> (Vec<f32>, StreamingSummary) {
+    let mut levels = SignalLevels::for_config(config);
+    for block in signal.chunks(block_size) {
+        levels.accumulate(block);
+    }
+
+    let mut cleaner = StreamingCleaner::new(config.clone(), &levels);
+    let mut output = Vec::new();
+    for block in signal.chunks(block_size) {
+        cleaner.process(block, &mut output);
+        assert!(signal.len() >= output.len());
+    }
+    let summary = cleaner.finish(&mut output);
+    assert_eq!(summary.samples, output.len());
+    (output, summary)
+}
+
+fn assert_matches_offline(signal: &[f32], config: &BaselineConfig) {
+    let offline = run_baseline_pipeline(signal, config);
+    assert!(!offline.detected_impulses.is_empty());
+
+    for block_size in [1, 7, 128, 512, 4096, 100_000] {
+        let (output, summary) = run_streaming(signal, config, block_size);
+        assert_eq!(
+            summary.detected_impulses, offline.detected_impulses,
+            "block size {block_size}"
+        );
+        assert!(
+            output
+                .iter()
+                .zip(&offline.repaired)
+                .all(|(a, b)| a.to_bits() == b.to_bits()),
+            "block size {block_size} diverged from the offline output"
+        );
+        assert_eq!(output.len(), offline.repaired.len());
+        assert_eq!(
+            summary.cleanliness, offline.cleanliness,
+            "block size {block_size}"
+        );
+    }
+}
+
+#[test]
+fn streaming_output_is_bit_identical_to_offline() {
+    assert_matches_offline(&clicky_signal(), &BaselineConfig::default());
+}
+
+#[test]
+fn streaming_autoregressive_repair_is_bit_identical_to_offline() {
+    let config = BaselineConfig {
+        repair_mode: RepairMode::autoregressive(),
+        ..BaselineConfig::default()
+    };
+    assert_matches_offline(&clicky_signal(), &config);
+}
+
+#[test]
+fn streaming_residual_detection_is_bit_identical_to_offline() {
+    let config = BaselineConfig {
+        detection_mode: DetectionMode::Residual(ResidualConfig::default()),
+        ..BaselineConfig::default()
+    };
+    assert_matches_offline(&clicky_signal(), &config);
+}
+
+#[test]
+fn streaming_decrackle_is_bit_identical_to_offline() {
+    let mut signal = clicky_signal();
+    for (index, sample) in signal.iter_mut().enumerate() {
+        if index % 89 == 0 {
+            *sample += if index % 2 == 0 { 0.02 } else { -0.02 };
+        }
+    }
+    let config = BaselineConfig {
+        decrackle: Some(DecrackleConfig::default()),
+        ..BaselineConfig::default()
+    };
+    assert_matches_offline(&signal, &config);
+
+    let offline = run_baseline_pipeline(&signal, &config);
+    let report = offline.decrackle.unwrap();
+    assert!(report.repaired_samples > 0);
+
+    let levels = SignalLevels::scan(&signal);
+    let mut cleaner = StreamingCleaner::new(config, &levels);
+    let mut output = Vec::new();
+    for block in signal.chunks(1000) {
+        cleaner.process(block, &mut output);
+    }
+    let summary = cleaner.finish(&mut output);
+    assert_eq!(summary.decrackle, Some(report));
+}
+
+#[test]
+fn streaming_dehiss_is_bit_identical_to_offline() {
+    let mut signal = clicky_signal();
+    let mut state = 1_u32;
+    for sample in signal.iter_mut() {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        *sample += (state >> 8) as f32 / (1 << 24) as f32 * 0.02 - 0.01;
+    }
+    let config = BaselineConfig {
+        decrackle: Some(DecrackleConfig::default()),
+        dehiss: Some(DehissConfig::default()),
+        ..BaselineConfig::default()
+    };
+    let offline = run_baseline_pipeline(&signal, &config);
+
+    for block_size in [1, 500, 4096, 100_000] {
+        let mut levels = SignalLevels::for_config(&config);
+        for block in signal.chunks(block_size) {
+            levels.accumulate(block);
+        }
+        let mut cleaner = StreamingCleaner::new(config.clone(), &levels);
+        let mut output = Vec::new();
+        for block in signal.chunks(block_size) {
+            cleaner.process(block, &mut output);
+        }
+        let summary = cleaner.finish(&mut output);
+
+        assert_eq!(output, offline.repaired, "block size {block_size}");
+        assert_eq!(summary.dehiss, offline.dehiss, "block size {block_size}");
+    }
+}
+
+#[test]
+fn streaming_minimum_phase_rumble_filter_is_bit_identical_to_offline() {
+    // Clicks on top of a DC offset and a slow warp.
+    let signal: Vec<f32> = clicky_signal()
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| sample + 0.1 + 0.3 * (i as f32 * 0.0005).sin())
+        .collect();
+    let config = BaselineConfig {
+        rumble: Some(RumbleConfig::default().with_phase(FilterPhase::MinimumPhase)),
+        ..BaselineConfig::default()
+    };
+    assert_matches_offline(&signal, &config);
+
+    let offline = run_baseline_pipeline(&signal, &config);
+    let mut levels = SignalLevels::for_config(&config);
+    levels.accumulate(&signal);
+    let mut cleaner = StreamingCleaner::new(config, &levels);
+    let mut output = Vec::new();
+    for block in signal.chunks(777) {
+        cleaner.process(block, &mut output);
+    }
+    let summary = cleaner.finish(&mut output);
+    assert_eq!(
+        summary.validation.input_low_frequency,
+        offline.validation.input_low_frequency
+    );
+    assert_eq!(
+        summary.validation.output_low_frequency,
+        offline.validation.output_low_frequency
+    );
+}
+
+#[test]
+fn streaming_dehum_is_bit_identical_to_offline() {
+    let signal: Vec<f32> = clicky_signal()
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let phase = i as f32 * std::f32::consts::TAU * 50.0 / 8_000.0;
+            sample + 0.05 * phase.sin() + 0.02 * (3.0 * phase).sin()
+        })
+        .collect();
+    let config = BaselineConfig {
+        sample_rate: 8_000,
+        dehum: Some(DehumConfig {
+            window_ms: 100.0,
+            ..DehumConfig::default()
+        }),
+        ..BaselineConfig::default()
+    };
+    let offline = run_baseline_pipeline(&signal, &config);
+    assert_eq!(offline.dehum.unwrap().fundamental_hz, Some(50.0));
+    assert_matches_offline(&signal, &config);
+}
+
+#[test]
+fn streaming_wow_flutter_measurement_matches_offline() {
+    // A 3150 Hz test tone with 0.2 % wow at 0.5 Hz.
+    let mut phase = 0.0f64;
+    let signal: Vec<f32> = clicky_signal()
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let speed = 1.0 + 0.002 * (i as f64 * std::f64::consts::TAU * 0.5 / 8_000.0).sin();
+            phase += std::f64::consts::TAU * 3_150.0 * speed / 8_000.0;
+            sample + 0.1 * phase.sin() as f32
+        })
+        .collect();
+    let config = BaselineConfig {
+        sample_rate: 8_000,
+        wow_flutter: Some(WowFlutterConfig {
+            correct: true,
+            ..WowFlutterConfig::default()
+        }),
+        ..BaselineConfig::default()
+    };
+    let offline = run_baseline_pipeline(&signal, &config);
+    let report = offline.wow_flutter.expect("the test tone is tracked");
+    assert!(report.wow_percent > 0.1, "{report:?}");
+
+    for block_size in [1, 7, 4096] {
+        let (output, summary) = run_streaming(&signal, &config, block_size);
+        assert_eq!(summary.wow_flutter.as_ref(), Some(&report));
+        // The cleaner measures, but leaves the correction to offline runs.
+        let measured = BaselineConfig {
+            wow_flutter: None,
+            ..config.clone()
+        };
+        assert_eq!(output, run_baseline_pipeline(&signal, &measured).repaired);
+    }
+}
+
+#[test]
+fn streaming_validation_matches_offline() {
+    let signal = clicky_signal();
+    let offline = run_baseline_pipeline(&signal, &BaselineConfig::default());
+
+    let levels = SignalLevels::scan(&signal);
+    let mut cleaner = StreamingCleaner::new(BaselineConfig::default(), &levels);
+    let mut output = Vec::new();
+    let mut fed = 0;
+    for block in signal.chunks(1000) {
+        cleaner.process(block, &mut output);
+        fed += block.len();
+        assert!(fed - output.len() <= cleaner.latency());
+    }
+    let summary = cleaner.finish(&mut output);
+
+    assert_eq!(summary.validation.peak, offline.validation.peak);
+    assert_eq!(
+        summary.validation.clipped_samples,
+        offline.validation.clipped_samples
+    );
+    assert_eq!(summary.validation.has_nan, offline.validation.has_nan);
+}
@@ -0,0 +1,145 @@
+use vinyl_engine::io::{decode_wav, encode_wav, read_wav, write_wav};
+use vinyl_engine::{run_baseline_pipeline, BaselineConfig, SampleFormat};
+
+fn stereo_test_signal() -> Vec<Vec<f32>> {
+    let left = (0..1000)
+        .map(|i| 0.5 * (i as f32 * 0.05).sin())
+        .collect::<Vec<_>>();
+    let right = (0..1000)
+        .map(|i| -0.25 * (i as f32 * 0.02).cos())
+        .collect::<Vec<_>>();
+    vec![left, right]
+}
+
+#[test]
+fn round_trip_preserves_samples_for_every_format() {
+    let channels = stereo_test_signal();
+    let formats = [
+        (SampleFormat::Int16, 1.0 / 32_767.0),
+        (SampleFormat::Int24, 1.0 / 8_388_607.0),
+        (SampleFormat::Int32, 1e-6),
+        (SampleFormat::Float32, 0.0),
+        (SampleFormat::Float64, 0.0),
+    ];
+
+    for (format, tolerance) in formats {
+        let mut bytes = Vec::new();
+        encode_wav(&mut bytes, &channels, 96_000, format).unwrap();
+        let decoded = decode_wav(&bytes).unwrap();
+
+        assert_eq!(decoded.sample_rate, 96_000);
+        assert_eq!(decoded.format, format);
+        assert_eq!(decoded.channels.len(), 2);
+        assert_eq!(decoded.frames(), 1000);
+        for (original, decoded) in channels.iter().zip(&decoded.channels) {
+            for (a, b) in original.iter().zip(decoded) {
+                assert!(
+                    (a - b).abs() <= tolerance,
+                    "{format:?} sample drifted: {a} vs {b}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn decodes_wave_format_extensible() {
+    let channels = vec![vec![0.1_f32, -0.2, 0.3]; 4];
+    let mut bytes = Vec::new();
+    encode_wav(&mut bytes, &channels, 48_000, SampleFormat::Int24).unwrap();
+
+    // More than two channels forces the extensible header.
+    assert_eq!(u16::from_le_bytes([bytes[20], bytes[21]]), 0xFFFE);
+
+    let decoded = decode_wav(&bytes).unwrap();
+    assert_eq!(decoded.format, SampleFormat::Int24);
+    assert_eq!(decoded.channels.len(), 4);
+    assert!((decoded.channels[3][2] - 0.3).abs() < 1e-6);
+}
+
+#[test]
+fn downmixes_to_the_mean_of_the_channels() {
+    let mut bytes = Vec::new();
+    encode_wav(
+        &mut bytes,
+        &[[0.5_f32, -0.25], [0.25, 0.25]],
+        44_100,
+        SampleFormat::Float32,
+    )
+    .unwrap();
+    assert_eq!(decode_wav(&bytes).unwrap().downmix(), [0.375, 0.0]);
+}
+
+#[test]
+fn rejects_malformed_input() {
+    assert!(decode_wav(b"not a wav file").is_err());
+
+    let mut bytes = Vec::new();
+    encode_wav(&mut bytes, &[vec![0.0_f32; 8]], 44_100, SampleFormat::Int16).unwrap();
+    // Turn the PCM tag into an unsupported one (A-law).
+    bytes[20] = 0x06;
+    assert!(decode_wav(&bytes).is_err());
+
+    let ragged = vec![vec![0.0_f32; 8], vec![0.0_f32; 7]];
+    assert!(encode_wav(&mut Vec::new(), &ragged, 44_100, SampleFormat::Int16).is_err());
+
+    // Header fields that do not fit their integer width are rejected, not
+    // wrapped.
+    let wide = vec![Vec::<f32>::new(); 9_000];
+    let error = encode_wav(&mut Vec::new(), &wide, 44_100, SampleFormat::Float64).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    let error =
+        encode_wav(&mut Vec::new(), &[[0.0_f32]], u32::MAX, SampleFormat::Int32).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn decoded_channels_feed_the_pipeline() {
+    let mut channel = vec![0.0_f32; 512];
+    for (i, sample) in channel.iter_mut().enumerate() {
+        *sample = 0.2 * (i as f32 * 0.1).sin();
+    }
+    channel[300] = 0.9;
+
+    let mut bytes = Vec::new();
+    encode_wav(&mut bytes, &[&channel], 44_100, SampleFormat::Int16).unwrap();
+    let audio = decode_wav(&bytes).unwrap();
+
+    let output = run_baseline_pipeline(&audio.channels[0], &BaselineConfig::default());
+    assert_eq!(output.detected_impulses.len(), 1);
+    assert_eq!(output.detected_impulses[0].peak_index, 300);
+
+    let mut encoded = Vec::new();
+    encode_wav(
+        &mut encoded,
+        &[&output.repaired],
+        audio.sample_rate,
+        SampleFormat::Int24,
+    )
+    .unwrap();
+    assert_eq!(decode_wav(&encoded).unwrap().frames(), 512);
+}
+
+#[test]
+fn failed_writes_leave_no_file_behind() {
+    let directory = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("wav_io_writes");
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("side_a.wav");
+    let partial = directory.join("side_a.wav.partial");
+    let _ = std::fs::remove_file(&path);
+
+    let ragged = vec![vec![0.0_f32; 8], vec![0.0_f32; 7]];
+    assert!(write_wav(&path, &ragged, 44_100, SampleFormat::Int16).is_err());
+    assert!(!path.exists());
+    assert!(!partial.exists());
+
+    let channels = stereo_test_signal();
+    write_wav(&path, &channels, 44_100, SampleFormat::Float32).unwrap();
+    assert!(!partial.exists());
+    assert_eq!(read_wav(&path).unwrap().channels, channels);
+
+    // A failed rewrite keeps the previous file intact.
+    assert!(write_wav(&path, &ragged, 44_100, SampleFormat::Int16).is_err());
+    assert_eq!(read_wav(&path).unwrap().channels, channels);
+    assert!(!partial.exists());
+}
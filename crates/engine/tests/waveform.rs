@@ -0,0 +1,158 @@
+use std::io::ErrorKind;
+
+use vinyl_engine::waveform::{WaveformCache, WaveformPyramid, WaveformSignal};
+use vinyl_engine::ImpulseEvent;
+
+/// Ten seconds of a slowly swelling tone: long enough for a dozen levels.
+fn swelling_tone(frames: usize) -> Vec<f32> {
+    (0..frames)
+        .map(|i| {
+            let t = i as f32 / 44_100.0;
+            0.9 * (t / 10.0) * (t * 220.0 * std::f32::consts::TAU).sin()
+        })
+        .collect()
+}
+
+fn click(start: usize, end: usize) -> ImpulseEvent {
+    ImpulseEvent {
+        start,
+        end,
+        peak_index: start + (end - start) / 2,
+        amplitude: 0.5,
+        contrast_ratio: 12.0,
+        confidence: 0.8,
+    }
+}
+
+#[test]
+fn columns_match_the_raw_samples_at_every_zoom() {
+    let left = swelling_tone(441_000);
+    let right: Vec<f32> = left.iter().map(|sample| -0.5 * sample).collect();
+    let pyramid = WaveformPyramid::build(&[&left, &right], 44_100, 256);
+    assert_eq!(pyramid.frames(), 441_000);
+    assert_eq!(pyramid.channel_count(), 2);
+    // 1723 base buckets halve down to one in 11 steps.
+    assert_eq!(pyramid.levels(), 12);
+
+    let tolerance = 1.0 / 32_767.0;
+    for (range, columns) in [(0..441_000, 50), (100_000..103_000, 30), (5..85, 80)] {
+        let peaks = pyramid.peaks(range.clone(), columns);
+        assert_eq!(
+            (peaks.start_frame, peaks.end_frame),
+            (range.start, range.end)
+        );
+        assert!(peaks.samples_per_bucket * columns <= range.len().max(256 * columns));
+
+        for (samples, columns) in [&left, &right].iter().zip(&peaks.channels) {
+            // Columns read from buckets that may reach past their edges, so
+            // they always contain the raw extremes of their own share.
+            for (column, bucket) in columns.iter().enumerate() {
+                let from = range.start + range.len() * column / columns.len();
+                let to = range.start + range.len() * (column + 1) / columns.len();
+                let raw = &samples[from..to];
+                let min = raw.iter().copied().fold(f32::INFINITY, f32::min);
+                let max = raw.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                assert!(bucket.min <= min + tolerance && bucket.max >= max - tolerance);
+                assert!(bucket.rms >= 0.0 && bucket.rms <= bucket.max.max(-bucket.min));
+            }
+        }
+    }
+
+    // The whole signal in one column is its exact extent, read from the two
+    // buckets of the level below the top: the top bucket is larger than the
+    // signal.
+    let whole = pyramid.peaks(0..usize::MAX, 1);
+    let max = left.iter().copied().fold(0.0, f32::max);
+    assert_eq!(whole.samples_per_bucket, 256 << 10);
+    assert!((whole.channels[0][0].max - max).abs() <= tolerance);
+    let rms = (left.iter().map(|s| f64::from(*s).powi(2)).sum::<f64>() / 441_000.0).sqrt();
+    assert!((f64::from(whole.channels[0][0].rms) - rms).abs() < 1e-3);
+}
+
+#[test]
+fn ranges_are_clamped_and_seconds_convert_to_frames() {
+    let pyramid = WaveformPyramid::build(&[swelling_tone(44_100)], 44_100, 64);
+    assert_eq!(pyramid.frame_range(0.5, 0.75), 22_050..33_075);
+    assert_eq!(pyramid.frame_range(-1.0, 9.0), 0..44_100);
+    assert_eq!(pyramid.frame_range(0.8, 0.2), 35_280..35_280);
+
+    let peaks = pyramid.peaks(40_000..90_000, 1_000);
+    assert_eq!((peaks.start_frame, peaks.end_frame), (40_000, 44_100));
+    assert_eq!(peaks.channels[0].len(), 1_000);
+    assert!(pyramid.peaks(50_000..60_000, 10).channels[0].is_empty());
+    // Fewer frames than columns gives one column per frame.
+    assert_eq!(pyramid.peaks(100..110, 400).channels[0].len(), 10);
+}
+
+#[test]
+fn clicks_are_queried_by_overlap() {
+    let signal = swelling_tone(44_100);
+    let mut cache = WaveformCache::new(&[&signal], 44_100);
+    assert!(cache
+        .peaks(WaveformSignal::Repaired, 0..44_100, 10)
+        .is_none());
+    assert!(cache.clicks(0..44_100).is_empty());
+
+    let clicks = [
+        click(30_000, 30_040),
+        click(1_000, 1_010),
+        click(9_000, 9_100),
+    ];
+    cache.set_cleaned(&[&signal], &clicks);
+    let starts = |range| -> Vec<usize> {
+        cache
+            .clicks(range)
+            .iter()
+            .map(|click: &ImpulseEvent| click.start)
+            .collect()
+    };
+    assert_eq!(starts(0..44_100), [1_000, 9_000, 30_000]);
+    assert_eq!(starts(1_009..9_001), [1_000, 9_000]);
+    assert_eq!(starts(1_010..9_000), Vec::<usize>::new());
+    assert_eq!(starts(30_039..30_039), Vec::<usize>::new());
+    assert_eq!(starts(30_039..30_040), [30_000]);
+    assert_eq!(
+        cache.peaks(WaveformSignal::Repaired, 0..44_100, 10),
+        cache.peaks(WaveformSignal::Original, 0..44_100, 10)
+    );
+}
+
+#[test]
+fn caches_round_trip_through_files_and_reject_corrupt_data() {
+    let signal = swelling_tone(100_000);
+    let repaired: Vec<f32> = signal.iter().map(|sample| sample * 0.5).collect();
+    let mut cache = WaveformCache::new(&[&signal, &signal], 44_100);
+
+    let mut bytes = Vec::new();
+    cache.write_to(&mut bytes).unwrap();
+    assert_eq!(
+        WaveformCache::read_from(&mut bytes.as_slice()).unwrap(),
+        cache
+    );
+
+    cache.set_cleaned(&[&repaired, &repaired], &[click(5, 9), click(70, 80)]);
+    let dir = std::env::temp_dir().join(format!("vinyl-waveform-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("side_a.vwpk");
+    cache.save(&path).unwrap();
+    let loaded = WaveformCache::load(&path).unwrap();
+    assert_eq!(loaded, cache);
+    assert_eq!(
+        loaded.peaks(WaveformSignal::Repaired, 0..100_000, 25),
+        cache.peaks(WaveformSignal::Repaired, 0..100_000, 25)
+    );
+
+    // Four channel pyramids of 391 base buckets and 400 coarser ones, at
+    // six bytes a bucket, and a small header.
+    let size = std::fs::metadata(&path).unwrap().len();
+    assert!(size < 4 * 6 * (391 + 400) + 200, "{size} bytes");
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let truncated = &bytes[..bytes.len() - 3];
+    let error = WaveformCache::read_from(&mut &truncated[..]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    bytes[0] = b'X';
+    let error = WaveformCache::read_from(&mut bytes.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}